@@ -1,7 +1,10 @@
+use crate::core::archive::remove_temporary_dir;
 use crate::core::config::{self, try_load_from_file, try_save_to_file, Configuration};
 use crate::event::{Event, Events};
 use crate::ui::UserInterface;
-use std::io::Stdout;
+use std::io::{self, Stdout};
+use std::path::Path;
+use std::process::Command;
 use termion::event::Key;
 use termion::raw::RawTerminal;
 use tui::backend::TermionBackend;
@@ -25,6 +28,7 @@ pub enum InputMode {
 }
 
 pub struct Application {
+    external_command: Option<Command>,
     input_mode: InputMode,
 }
 
@@ -36,6 +40,7 @@ impl Application {
     /// A new instance of the `Application` struct with the default input mode set.
     pub fn new() -> Self {
         Application {
+            external_command: None,
             input_mode: InputMode::default(),
         }
     }
@@ -82,11 +87,18 @@ impl Application {
                     Event::Tick => ui.tick(self),
                 }
             }
+
+            if let Some(command) = self.external_command.take() {
+                if let Err(error) = run_in_terminal(terminal, &events, command) {
+                    ui.show_error(error.to_string(), self);
+                }
+            }
         }
         // temporary solution to avoid Rc<RefCell<Configuration> everywhere in the ui
         ui.update_config();
         let config_to_save = ui.config();
         save_config(config_to_save);
+        remove_temporary_dir();
     }
 
     /// Retrieves the current input mode of the application.
@@ -106,6 +118,63 @@ impl Application {
     pub(crate) fn set_input_mode(&mut self, input_mode: InputMode) {
         self.input_mode = input_mode;
     }
+
+    /// Opens the `file` in the editor set by the `VISUAL` or `EDITOR` environment variables,
+    /// falling back to `vi`.
+    /// The editor is started after the current event is handled.
+    pub(crate) fn edit_file(&mut self, file: &Path) {
+        let editor = std::env::var("VISUAL")
+            .or_else(|_| std::env::var("EDITOR"))
+            .unwrap_or_default();
+        self.external_command = Some(program_command(&editor, "vi", file));
+    }
+
+    /// Opens the `file` in the pager set by the `PAGER` environment variable,
+    /// falling back to `less`.
+    /// The pager is started after the current event is handled.
+    pub(crate) fn view_file(&mut self, file: &Path) {
+        let pager = std::env::var("PAGER").unwrap_or_default();
+        self.external_command = Some(program_command(&pager, "less", file));
+    }
+}
+
+/// Creates the command running the `program` on the `file`, or the `fallback` program
+/// if it is empty. The program may be set with its arguments, e.g. "code --wait".
+fn program_command(program: &str, fallback: &str, file: &Path) -> Command {
+    let mut words = program.split_whitespace();
+    let mut command = Command::new(words.next().unwrap_or(fallback));
+    command.args(words).arg(file);
+    command
+}
+
+/// Runs the `command` in the terminal in place of the user interface until it exits.
+/// The terminal stays in raw mode, full screen programs like editors set it up for themselves.
+fn run_in_terminal(
+    terminal: &mut Terminal<TermionBackend<RawTerminal<Stdout>>>,
+    events: &Events,
+    mut command: Command,
+) -> io::Result<()> {
+    events.pause();
+    terminal.clear()?;
+    terminal.show_cursor()?;
+    let status = command.status();
+    terminal.hide_cursor()?;
+    // the whole user interface is redrawn
+    terminal.clear()?;
+    events.resume();
+
+    let program = command.get_program().to_string_lossy().to_string();
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(io::Error::other(format!(
+            "{} exited with {}",
+            program, status
+        ))),
+        Err(error) => Err(io::Error::new(
+            error.kind(),
+            format!("{}: {}", program, error),
+        )),
+    }
 }
 
 fn get_config() -> Configuration {
@@ -0,0 +1,771 @@
+use super::{
+    copy::{c_path, TransferControl},
+    list_dir::{DirContent, FilterOptions},
+    vfs::{FileKind, FileStat, Vfs},
+};
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    fs::{self, File, OpenOptions, Permissions},
+    io::{self, BufReader, Cursor, Read, Write},
+    ops::ControlFlow,
+    os::unix::{
+        ffi::OsStringExt,
+        fs::{symlink, PermissionsExt},
+    },
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// The archive formats which can be browsed like directories.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+    TarXz,
+    TarZst,
+}
+
+impl ArchiveFormat {
+    pub const ALL: [ArchiveFormat; 5] = [
+        ArchiveFormat::Zip,
+        ArchiveFormat::Tar,
+        ArchiveFormat::TarGz,
+        ArchiveFormat::TarXz,
+        ArchiveFormat::TarZst,
+    ];
+
+    /// Returns the usual extension of the archives of the format, e.g. `.tar.gz`.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => ".zip",
+            ArchiveFormat::Tar => ".tar",
+            ArchiveFormat::TarGz => ".tar.gz",
+            ArchiveFormat::TarXz => ".tar.xz",
+            ArchiveFormat::TarZst => ".tar.zst",
+        }
+    }
+
+    /// Recognizes the format of an archive by the extension of its file name.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        let formats = [
+            (".zip", ArchiveFormat::Zip),
+            (".tar", ArchiveFormat::Tar),
+            (".tar.gz", ArchiveFormat::TarGz),
+            (".tgz", ArchiveFormat::TarGz),
+            (".tar.xz", ArchiveFormat::TarXz),
+            (".txz", ArchiveFormat::TarXz),
+            (".tar.zst", ArchiveFormat::TarZst),
+            (".tzst", ArchiveFormat::TarZst),
+        ];
+        formats
+            .iter()
+            .find(|(extension, _)| name.len() > extension.len() && name.ends_with(extension))
+            .map(|(_, format)| *format)
+    }
+}
+
+/// The kind of an archive member.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EntryKind {
+    File,
+    Dir,
+    /// A symbolic link with the path it points to.
+    Symlink(PathBuf),
+    /// A hard link to the earlier member at the path, relative to the archive root.
+    HardLink(PathBuf),
+}
+
+/// A file or directory stored in an archive.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArchiveEntry {
+    /// The path of the member relative to the archive root.
+    pub path: PathBuf,
+    pub kind: EntryKind,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    /// The Unix permission bits, if the archive stores them.
+    pub mode: Option<u32>,
+}
+
+impl ArchiveEntry {
+    fn dir(path: PathBuf) -> Self {
+        ArchiveEntry {
+            path,
+            kind: EntryKind::Dir,
+            size: 0,
+            modified: None,
+            mode: None,
+        }
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.kind == EntryKind::Dir
+    }
+}
+
+impl From<&ArchiveEntry> for DirContent {
+    fn from(entry: &ArchiveEntry) -> Self {
+        let date = match entry.modified {
+            Some(modified) => DateTime::<Local>::from(modified)
+                .format("%Y.%m.%d %H:%M")
+                .to_string(),
+            None => String::new(),
+        };
+        let link_target = match &entry.kind {
+            EntryKind::Symlink(target) => Some(target.clone()),
+            _ => None,
+        };
+        DirContent {
+            name: entry
+                .path
+                .file_name()
+                .map(OsString::from)
+                .unwrap_or_default(),
+            is_dir: entry.is_dir(),
+            size: match entry.is_dir() {
+                true => None,
+                false => Some(entry.size),
+            },
+            date,
+            attrs: String::new(),
+            is_symlink: link_target.is_some(),
+            link_target,
+            is_broken_link: false,
+        }
+    }
+}
+
+/// The table of contents of an archive, read once when the archive is opened.
+/// The members are addressed by their paths relative to the archive root,
+/// the directories missing from the archive are added for the paths of their members.
+pub struct ArchiveIndex {
+    archive: PathBuf,
+    format: ArchiveFormat,
+    entries: BTreeMap<PathBuf, ArchiveEntry>,
+}
+
+impl ArchiveIndex {
+    /// Reads the members of the `archive`.
+    /// Fails if the format is not supported or the archive cannot be read.
+    pub fn open(archive: &Path) -> io::Result<Self> {
+        let format = ArchiveFormat::from_path(archive).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "Unsupported archive format")
+        })?;
+        let mut index = ArchiveIndex {
+            archive: PathBuf::from(archive),
+            format,
+            entries: BTreeMap::new(),
+        };
+        let mut entries = BTreeMap::new();
+        index.for_each_member(|entry, _| {
+            insert_entry(&mut entries, entry);
+            ControlFlow::Continue(())
+        })?;
+        index.entries = entries;
+        Ok(index)
+    }
+
+    /// Returns the path of the archive file.
+    pub fn path(&self) -> &Path {
+        &self.archive
+    }
+
+    /// Returns the path of the member inside the archive for a path under the archive file,
+    /// e.g. `dir/file` for `/home/user/archive.zip/dir/file`.
+    pub fn member_path<'path>(&self, path: &'path Path) -> Option<&'path Path> {
+        path.strip_prefix(&self.archive).ok()
+    }
+
+    pub fn entry(&self, member: &Path) -> Option<&ArchiveEntry> {
+        self.entries.get(member)
+    }
+
+    /// Returns true if `member` is a directory, the root of the archive included.
+    pub fn is_dir(&self, member: &Path) -> bool {
+        member.as_os_str().is_empty() || self.entry(member).is_some_and(ArchiveEntry::is_dir)
+    }
+
+    /// Returns the members directly in the `dir` member.
+    pub fn list(&self, dir: &Path) -> impl Iterator<Item = &ArchiveEntry> {
+        let dir = PathBuf::from(dir);
+        self.entries
+            .values()
+            .filter(move |entry| entry.path.parent() == Some(dir.as_path()))
+    }
+
+    /// Returns the `member` and all the members under it.
+    pub fn tree<'index>(
+        &'index self,
+        member: &'index Path,
+    ) -> impl Iterator<Item = &'index ArchiveEntry> {
+        self.entries
+            .values()
+            .filter(move |entry| entry.path.starts_with(member))
+    }
+
+    /// Lists the members in the `dir` member like `list_dir()` lists a directory.
+    pub fn list_dir(&self, dir: &Path, filter_options: &FilterOptions) -> Vec<DirContent> {
+        self.list(dir)
+            .map(DirContent::from)
+            .filter(|file| {
+                filter_options.show_hidden_files || !file.name.to_string_lossy().starts_with('.')
+            })
+            .collect()
+    }
+
+    /// Returns the member storing the content of the `member`:
+    /// the content of a hard link is stored in the member it points to.
+    fn content_member<'member>(&'member self, member: &'member Path) -> &'member Path {
+        match self.entry(member).map(|entry| &entry.kind) {
+            Some(EntryKind::HardLink(linked)) => linked.as_path(),
+            _ => member,
+        }
+    }
+
+    /// Returns the member for a `path` under the archive file, fails if there is no such member.
+    fn existing_member<'path>(&self, path: &'path Path) -> io::Result<&'path Path> {
+        self.member_path(path)
+            .filter(|member| self.is_dir(member) || self.entry(member).is_some())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{} is not in the archive", path.display()),
+                )
+            })
+    }
+
+    /// Reads the members in the archive order, passing their content to `visit`.
+    /// The members with unsafe paths, e.g. containing `..`, are left out.
+    pub fn for_each_member<F>(&self, mut visit: F) -> io::Result<()>
+    where
+        F: FnMut(ArchiveEntry, &mut dyn Read) -> ControlFlow<()>,
+    {
+        let file = File::open(&self.archive)?;
+        let reader: Box<dyn Read> = match self.format {
+            ArchiveFormat::Zip => return for_each_zip_member(file, visit),
+            ArchiveFormat::Tar => Box::new(BufReader::new(file)),
+            ArchiveFormat::TarGz => {
+                Box::new(flate2::read::MultiGzDecoder::new(BufReader::new(file)))
+            }
+            ArchiveFormat::TarXz => Box::new(lzma_rust2::XzReader::new(BufReader::new(file), true)),
+            ArchiveFormat::TarZst => Box::new(
+                ruzstd::decoding::StreamingDecoder::new(BufReader::new(file))
+                    .map_err(io::Error::other)?,
+            ),
+        };
+        let mut archive = tar::Archive::new(reader);
+        for member in archive.entries()? {
+            let mut member = member?;
+            if let Some(entry) = tar_entry(&member)? {
+                if visit(entry, &mut member).is_break() {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the members under the `member`, the `member` included.
+    pub fn for_each_member_in<F>(&self, member: &Path, mut visit: F) -> io::Result<()>
+    where
+        F: FnMut(ArchiveEntry, &mut dyn Read) -> ControlFlow<()>,
+    {
+        self.for_each_member(|entry, reader| match entry.path.starts_with(member) {
+            true => visit(entry, reader),
+            false => ControlFlow::Continue(()),
+        })
+    }
+
+    /// Passes the content of the `member` file to `read`.
+    pub fn read_member<F>(&self, member: &Path, mut read: F) -> io::Result<()>
+    where
+        F: FnMut(&mut dyn Read) -> io::Result<()>,
+    {
+        let mut result = Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} is not in the archive", member.display()),
+        ));
+        self.for_each_member(|entry, reader| match entry.path == member {
+            true => {
+                result = read(reader);
+                ControlFlow::Break(())
+            }
+            false => ControlFlow::Continue(()),
+        })?;
+        result
+    }
+}
+
+/// The archive mounted under the archive file, e.g. `/home/user/archive.zip/dir/file`.
+/// It is read-only, and symbolic links are not followed in it.
+impl Vfs for ArchiveIndex {
+    fn list(&self, dir: &Path, filter_options: &FilterOptions) -> io::Result<Vec<DirContent>> {
+        let dir = self.existing_member(dir)?;
+        match self.is_dir(dir) {
+            true => Ok(self.list_dir(dir, filter_options)),
+            false => Err(io::ErrorKind::NotADirectory.into()),
+        }
+    }
+
+    fn stat(&self, path: &Path) -> io::Result<FileStat> {
+        let member = self.existing_member(path)?;
+        let stat = match self.entry(member) {
+            Some(entry) => FileStat {
+                kind: match entry.kind {
+                    EntryKind::File | EntryKind::HardLink(_) => FileKind::File,
+                    EntryKind::Dir => FileKind::Dir,
+                    EntryKind::Symlink(_) => FileKind::Symlink,
+                },
+                size: entry.size,
+                modified: entry.modified,
+                mode: entry.mode,
+            },
+            // the root of the archive
+            None => FileStat {
+                kind: FileKind::Dir,
+                size: 0,
+                modified: None,
+                mode: None,
+            },
+        };
+        Ok(stat)
+    }
+
+    fn symlink_stat(&self, path: &Path) -> io::Result<FileStat> {
+        self.stat(path)
+    }
+
+    /// Decompresses the member into memory.
+    fn open_read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        let member = self.existing_member(path)?;
+        let mut content = Vec::new();
+        self.read_member(self.content_member(member), |reader| {
+            reader.read_to_end(&mut content).map(|_| ())
+        })?;
+        Ok(Box::new(Cursor::new(content)))
+    }
+
+    fn open_write(&self, _path: &Path) -> io::Result<Box<dyn Write + Send>> {
+        Err(read_only())
+    }
+
+    fn mkdir(&self, _dir: &Path, _mode: Option<u32>) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn remove(&self, _path: &Path) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn rename(&self, _from: &Path, _to: &Path) -> io::Result<()> {
+        Err(read_only())
+    }
+}
+
+fn read_only() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        "The archive cannot be modified",
+    )
+}
+
+/// The directory of the files extracted temporarily, once it was created.
+static TEMPORARY_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Returns the directory of the files extracted temporarily, it is created on the first call.
+/// The directory has an unpredictable name and only the user can access it,
+/// it is created under `$XDG_RUNTIME_DIR` if it is set, otherwise in the system's temporary directory.
+pub fn temporary_dir() -> io::Result<PathBuf> {
+    let mut dir = TEMPORARY_DIR
+        .lock()
+        .unwrap_or_else(|error| error.into_inner());
+    if let Some(dir) = dir.as_ref() {
+        return Ok(dir.clone());
+    }
+    let parent = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|runtime_dir| runtime_dir.is_absolute() && runtime_dir.is_dir())
+        .unwrap_or_else(std::env::temp_dir);
+    let created = make_private_dir(&parent.join("twc-XXXXXX"))?;
+    *dir = Some(created.clone());
+    Ok(created)
+}
+
+/// Removes the directory of the files extracted temporarily, if it was created.
+pub fn remove_temporary_dir() {
+    let mut dir = TEMPORARY_DIR
+        .lock()
+        .unwrap_or_else(|error| error.into_inner());
+    if let Some(dir) = dir.take() {
+        let _ = fs::remove_dir_all(dir);
+    }
+}
+
+/// Creates a new directory with the mode 0700 by `mkdtemp()`,
+/// the trailing `XXXXXX` of the `template` is replaced to make a unique name.
+fn make_private_dir(template: &Path) -> io::Result<PathBuf> {
+    let mut template = c_path(template)?.into_bytes_with_nul();
+    match unsafe { libc::mkdtemp(template.as_mut_ptr() as *mut libc::c_char) }.is_null() {
+        true => Err(io::Error::last_os_error()),
+        false => {
+            template.pop();
+            Ok(PathBuf::from(OsString::from_vec(template)))
+        }
+    }
+}
+
+/// Returns the relative path of an archive member without `.` components,
+/// or None if it is empty or it would point outside of the archive root.
+pub fn member_path(name: &Path) -> Option<PathBuf> {
+    let mut path = PathBuf::new();
+    for component in name.components() {
+        match component {
+            Component::Normal(name) => path.push(name),
+            Component::RootDir | Component::CurDir | Component::Prefix(_) => {}
+            Component::ParentDir => return None,
+        }
+    }
+    match path.as_os_str().is_empty() {
+        true => None,
+        false => Some(path),
+    }
+}
+
+/// Returns the path of the member at the `relative` path extracted under `root`.
+/// Fails if a directory on the way is a symbolic link: the archive could write
+/// outside of `root` through a link stored in it.
+pub fn extraction_target(root: &Path, relative: &Path) -> io::Result<PathBuf> {
+    let mut target = PathBuf::from(root);
+    let mut components = relative.components().peekable();
+    while let Some(component) = components.next() {
+        target.push(component);
+        let is_symlink =
+            fs::symlink_metadata(&target).is_ok_and(|metadata| metadata.file_type().is_symlink());
+        if is_symlink && components.peek().is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!(
+                    "{} is a symbolic link, nothing is extracted through it",
+                    target.display()
+                ),
+            ));
+        }
+    }
+    Ok(target)
+}
+
+/// Writes the content of the `entry` file read from `reader` to `to`,
+/// with the permissions and the modification time stored in the archive.
+/// An existing `to` file is overwritten, or the part of the content missing from its end
+/// is appended to it if `append` is set.
+///
+/// Calls `progress` with the number of the bytes written so far after every chunk.
+/// The extraction waits while the transfer is paused, and the partially written `to`
+/// is removed if the transfer is cancelled.
+pub fn extract_file<F>(
+    entry: &ArchiveEntry,
+    reader: &mut dyn Read,
+    to: &Path,
+    append: bool,
+    control: &TransferControl,
+    mut progress: F,
+) -> io::Result<()>
+where
+    F: FnMut(u64),
+{
+    let mut target = match append {
+        true => {
+            let target = OpenOptions::new().append(true).open(to)?;
+            let existing = target.metadata()?.len();
+            io::copy(&mut reader.take(existing), &mut io::sink())?;
+            target
+        }
+        false => File::create(to)?,
+    };
+    let mut buffer = vec![0; CHUNK_SIZE];
+    let mut written = 0;
+    let result = loop {
+        if !control.proceed() {
+            break Err(io::Error::new(
+                io::ErrorKind::Interrupted,
+                "The transfer was cancelled",
+            ));
+        }
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break Ok(()),
+            Ok(read) => read,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => break Err(error),
+        };
+        if let Err(error) = target.write_all(&buffer[..read]) {
+            break Err(error);
+        }
+        written += read as u64;
+        progress(written);
+    };
+    if result.is_err() && control.is_cancelled() {
+        let _ = fs::remove_file(to);
+    }
+    result?;
+
+    if let Some(mode) = entry.mode {
+        target.set_permissions(Permissions::from_mode(mode))?;
+    }
+    if let Some(modified) = entry.modified {
+        target.set_modified(modified)?;
+    }
+    Ok(())
+}
+
+/// Creates a symbolic link on `to` pointing to `link_target`, an existing file is replaced.
+pub fn extract_symlink(link_target: &Path, to: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(to).is_ok_and(|metadata| !metadata.is_dir()) {
+        fs::remove_file(to)?;
+    }
+    symlink(link_target, to)
+}
+
+/// Inserts the `entry` along with its missing parent directories,
+/// a member stored more than once is replaced by the latest one.
+fn insert_entry(entries: &mut BTreeMap<PathBuf, ArchiveEntry>, entry: ArchiveEntry) {
+    for parent in entry.path.ancestors().skip(1) {
+        if parent.as_os_str().is_empty() {
+            break;
+        }
+        entries
+            .entry(PathBuf::from(parent))
+            .or_insert_with(|| ArchiveEntry::dir(PathBuf::from(parent)));
+    }
+    entries.insert(entry.path.clone(), entry);
+}
+
+fn for_each_zip_member<F>(file: File, mut visit: F) -> io::Result<()>
+where
+    F: FnMut(ArchiveEntry, &mut dyn Read) -> ControlFlow<()>,
+{
+    let mut archive = zip::ZipArchive::new(BufReader::new(file))?;
+    for index in 0..archive.len() {
+        let mut member = archive.by_index(index)?;
+        let path = match member_path(Path::new(member.name())) {
+            Some(path) => path,
+            None => continue,
+        };
+        let mode = member.unix_mode();
+        let kind = if member.is_dir() {
+            EntryKind::Dir
+        } else if mode.is_some_and(|mode| mode & libc::S_IFMT == libc::S_IFLNK) {
+            // the content of a link is the path it points to
+            let mut target = String::new();
+            member.read_to_string(&mut target)?;
+            EntryKind::Symlink(PathBuf::from(target))
+        } else {
+            EntryKind::File
+        };
+        let modified = member.last_modified();
+        let modified = NaiveDate::from_ymd_opt(
+            modified.year().into(),
+            modified.month().into(),
+            modified.day().into(),
+        )
+        .and_then(|date| {
+            date.and_hms_opt(
+                modified.hour().into(),
+                modified.minute().into(),
+                modified.second().into(),
+            )
+        })
+        .and_then(|datetime| Local.from_local_datetime(&datetime).single())
+        .map(SystemTime::from);
+        let entry = ArchiveEntry {
+            path,
+            kind,
+            size: member.size(),
+            modified,
+            mode: mode.map(|mode| mode & 0o7777),
+        };
+        if visit(entry, &mut member).is_break() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the `ArchiveEntry` of a tar member, None if it is left out.
+fn tar_entry<R: Read>(member: &tar::Entry<R>) -> io::Result<Option<ArchiveEntry>> {
+    let path = match member_path(&member.path()?) {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    let header = member.header();
+    let link_name = || -> io::Result<PathBuf> {
+        Ok(member
+            .link_name()?
+            .map(|name| name.into_owned())
+            .unwrap_or_default())
+    };
+    let kind = match header.entry_type() {
+        tar::EntryType::Directory => EntryKind::Dir,
+        tar::EntryType::Regular | tar::EntryType::Continuous => EntryKind::File,
+        tar::EntryType::Symlink => EntryKind::Symlink(link_name()?),
+        tar::EntryType::Link => match member_path(&link_name()?) {
+            Some(target) => EntryKind::HardLink(target),
+            None => return Ok(None),
+        },
+        // devices, pipes and the like are not extracted
+        _ => return Ok(None),
+    };
+    Ok(Some(ArchiveEntry {
+        path,
+        kind,
+        size: member.size(),
+        modified: header
+            .mtime()
+            .ok()
+            .map(|mtime| UNIX_EPOCH + Duration::from_secs(mtime)),
+        mode: header.mode().ok().map(|mode| mode & 0o7777),
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{extraction_target, member_path, ArchiveFormat, ArchiveIndex, EntryKind};
+    use crate::core::{list_dir::FilterOptions, test_dir::TestDir, vfs::copy_to_temporary};
+    use std::{
+        fs::{self, File},
+        io::Write,
+        os::unix::fs::symlink,
+        path::{Path, PathBuf},
+    };
+
+    /// Creates a .tar.gz archive with `src/main.rs`, a link to it and an unsafe member.
+    fn create_tar_gz(path: &Path) {
+        let encoder = flate2::write::GzEncoder::new(
+            File::create(path).unwrap(),
+            flate2::Compression::default(),
+        );
+        let mut builder = tar::Builder::new(encoder);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(12);
+        header.set_mode(0o755);
+        builder
+            .append_data(&mut header, "src/main.rs", &b"fn main() {}"[..])
+            .unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        builder
+            .append_link(&mut header, "main", "src/main.rs")
+            .unwrap();
+        // the path cannot be set through the builder, it refuses `..`
+        let mut header = tar::Header::new_old();
+        header.as_old_mut().name[..12].copy_from_slice(b"../escape.sh");
+        header.set_size(0);
+        header.set_cksum();
+        builder.append(&header, &[][..]).unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+    }
+
+    #[test]
+    fn test_archive_format() {
+        let format = |name: &str| ArchiveFormat::from_path(Path::new(name));
+        assert_eq!(format("release.TAR.GZ"), Some(ArchiveFormat::TarGz));
+        assert_eq!(format("release.tar.zst"), Some(ArchiveFormat::TarZst));
+        assert_eq!(format("photos.zip"), Some(ArchiveFormat::Zip));
+        assert_eq!(format(".zip"), None);
+        assert_eq!(format("notes.txt"), None);
+    }
+
+    #[test]
+    fn test_member_paths_are_kept_inside_the_archive() {
+        assert_eq!(
+            member_path(Path::new("/./usr/bin")),
+            Some(PathBuf::from("usr/bin"))
+        );
+        assert_eq!(member_path(Path::new("usr/../../etc")), None);
+        assert_eq!(member_path(Path::new("./")), None);
+
+        let dir = TestDir::new("archive-target");
+        fs::create_dir(dir.join("outside")).unwrap();
+        fs::create_dir(dir.join("root")).unwrap();
+        symlink("../outside", dir.join("root/link")).unwrap();
+        assert!(extraction_target(&dir.join("root"), Path::new("link/file")).is_err());
+        // the link itself can be replaced
+        assert_eq!(
+            extraction_target(&dir.join("root"), Path::new("link")).unwrap(),
+            dir.join("root/link")
+        );
+    }
+
+    #[test]
+    fn test_browse_tar_gz() {
+        let dir = TestDir::new("archive-tar");
+        let path = dir.join("project.tar.gz");
+        create_tar_gz(&path);
+
+        let archive = ArchiveIndex::open(&path).unwrap();
+        let names = |dir: &str| -> Vec<String> {
+            archive
+                .list(Path::new(dir))
+                .map(|entry| entry.path.display().to_string())
+                .collect()
+        };
+        // the directory missing from the archive is added, the unsafe member is left out
+        assert_eq!(names(""), ["main", "src"]);
+        assert_eq!(names("src"), ["src/main.rs"]);
+        assert!(archive.is_dir(Path::new("src")));
+        assert_eq!(
+            archive.entry(Path::new("main")).unwrap().kind,
+            EntryKind::Symlink(PathBuf::from("src/main.rs"))
+        );
+        assert_eq!(
+            archive.member_path(&path.join("src/main.rs")),
+            Some(Path::new("src/main.rs"))
+        );
+
+        let files = archive.list_dir(Path::new("src"), &FilterOptions::default());
+        assert_eq!(files[0].name, "main.rs");
+        assert_eq!(files[0].size, Some(12));
+
+        let viewed = copy_to_temporary(&archive, &path.join("src/main.rs")).unwrap();
+        assert_eq!(fs::read_to_string(&viewed).unwrap(), "fn main() {}");
+        let _ = fs::remove_file(viewed);
+    }
+
+    #[test]
+    fn test_browse_zip() {
+        let dir = TestDir::new("archive-zip");
+        let path = dir.join("photos.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&path).unwrap());
+        let options = zip::write::FileOptions::default();
+        writer.add_directory("2024/", options).unwrap();
+        writer.start_file("2024/beach.jpg", options).unwrap();
+        writer.write_all(b"jpeg").unwrap();
+        writer.start_file("2024/.hidden", options).unwrap();
+        writer.finish().unwrap();
+
+        let archive = ArchiveIndex::open(&path).unwrap();
+        let files = archive.list_dir(Path::new("2024"), &FilterOptions::default());
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, "beach.jpg");
+        assert_eq!(files[0].size, Some(4));
+        assert!(archive
+            .list_dir(Path::new(""), &FilterOptions::default())
+            .iter()
+            .all(|file| file.is_dir));
+
+        let mut content = String::new();
+        archive
+            .read_member(Path::new("2024/beach.jpg"), |reader| {
+                reader.read_to_string(&mut content).map(|_| ())
+            })
+            .unwrap();
+        assert_eq!(content, "jpeg");
+    }
+}
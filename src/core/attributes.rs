@@ -0,0 +1,425 @@
+use super::copy::c_path;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use std::fs::{self, Permissions};
+use std::io;
+use std::os::unix::fs::{chown, MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+/// The file listing the user accounts of the system.
+pub const PASSWD_FILE_PATH: &str = "/etc/passwd";
+/// The file listing the groups of the system.
+pub const GROUP_FILE_PATH: &str = "/etc/group";
+/// Mask of the permission bits (including setuid, setgid and sticky) of a file mode.
+pub const MODE_MASK: u32 = 0o7777;
+/// The format of the timestamps the user can enter, e.g: "2023.11.11 21:30:00".
+pub const TIMESTAMP_FORMAT: &str = "%Y.%m.%d %H:%M:%S";
+
+/// The changes requested by the user to apply on the attributes of files and directories.
+/// `None` values leave the respective attribute untouched.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AttributeChange {
+    /// The permission bits to set on files.
+    pub file_mode: Option<u32>,
+    /// The permission bits to set on directories.
+    pub dir_mode: Option<u32>,
+    /// The user id of the new owner.
+    pub owner: Option<u32>,
+    /// The group id of the new group.
+    pub group: Option<u32>,
+    /// Apply the changes on the content of the directories too.
+    pub recursive: bool,
+}
+
+/// The changes requested by the user to apply on the timestamps of files and directories.
+/// `None` values leave the respective timestamp untouched.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TimestampChange {
+    /// The new last modification time.
+    pub modified: Option<SystemTime>,
+    /// The new last access time.
+    pub accessed: Option<SystemTime>,
+    /// Apply the changes on the content of the directories too.
+    pub recursive: bool,
+}
+
+/// An error encountered during changing the attributes of a single file.
+#[derive(Debug)]
+pub struct AttributeError {
+    pub path: PathBuf,
+    pub error: io::Error,
+}
+
+/// Applies the `change` on every path of `paths`.
+/// Symbolic links are never followed during a recursive change.
+/// An error on a file does not interrupt the process,
+/// instead it is collected and returned to the caller when every path has been processed.
+pub fn apply_attributes(paths: &[PathBuf], change: &AttributeChange) -> Vec<AttributeError> {
+    apply_on_paths(paths, change.recursive, |path| apply_to_file(path, change))
+}
+
+/// Applies the timestamp `change` on every path of `paths`.
+/// Errors are collected the same way as `apply_attributes()` does.
+pub fn apply_timestamps(paths: &[PathBuf], change: &TimestampChange) -> Vec<AttributeError> {
+    apply_on_paths(paths, change.recursive, |path| {
+        set_timestamps(path, change.modified, change.accessed)
+    })
+}
+
+/// Calls `apply` on every path, and on every entry of the directories if `recursive` is true.
+/// The content of a directory is processed before the directory itself,
+/// so a mode without the execute bit does not prevent descending into it.
+fn apply_on_paths<F>(paths: &[PathBuf], recursive: bool, apply: F) -> Vec<AttributeError>
+where
+    F: Fn(&Path) -> io::Result<()>,
+{
+    let mut errors = Vec::new();
+
+    for path in paths {
+        if recursive && path.is_dir() && !path.is_symlink() {
+            for entry in WalkDir::new(path).follow_links(false).contents_first(true) {
+                match entry {
+                    Ok(entry) => {
+                        if let Err(error) = apply(entry.path()) {
+                            errors.push(AttributeError {
+                                path: entry.path().to_path_buf(),
+                                error,
+                            });
+                        }
+                    }
+                    Err(error) => errors.push(AttributeError {
+                        path: error.path().unwrap_or(path).to_path_buf(),
+                        error: error.into(),
+                    }),
+                }
+            }
+        } else if let Err(error) = apply(path) {
+            errors.push(AttributeError {
+                path: path.clone(),
+                error,
+            });
+        }
+    }
+    errors
+}
+
+fn apply_to_file(path: &Path, change: &AttributeChange) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(path)?;
+    // permissions of symbolic links are meaningless, and changing them would change the target's
+    if metadata.file_type().is_symlink() {
+        return Ok(());
+    }
+
+    if change.owner.is_some() || change.group.is_some() {
+        chown(path, change.owner, change.group)?;
+    }
+
+    // directories are left alone without a directory mode, the file mode would make them untraversable
+    let mode = match metadata.is_dir() {
+        true => change.dir_mode,
+        false => change.file_mode,
+    };
+    if let Some(mode) = mode {
+        fs::set_permissions(path, Permissions::from_mode(mode & MODE_MASK))?;
+    }
+    Ok(())
+}
+
+/// Sets the timestamps of `path` itself, symbolic links are not followed.
+/// The file is not opened: it would block on a named pipe, and it would need the read permission.
+fn set_timestamps(
+    path: &Path,
+    modified: Option<SystemTime>,
+    accessed: Option<SystemTime>,
+) -> io::Result<()> {
+    let times = [timespec(accessed), timespec(modified)];
+    let path = c_path(path)?;
+    match unsafe {
+        libc::utimensat(
+            libc::AT_FDCWD,
+            path.as_ptr(),
+            times.as_ptr(),
+            libc::AT_SYMLINK_NOFOLLOW,
+        )
+    } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+/// Converts the time for `utimensat()`, a missing time is left unchanged.
+fn timespec(time: Option<SystemTime>) -> libc::timespec {
+    let (tv_sec, tv_nsec) = match time.map(|time| time.duration_since(UNIX_EPOCH)) {
+        None => (0, libc::UTIME_OMIT),
+        Some(Ok(since)) => (since.as_secs() as i64, since.subsec_nanos() as i64),
+        // before the epoch, the nanoseconds must still be positive
+        Some(Err(error)) => {
+            let before = error.duration();
+            match before.subsec_nanos() {
+                0 => (-(before.as_secs() as i64), 0),
+                nanos => (-(before.as_secs() as i64) - 1, 1_000_000_000 - nanos as i64),
+            }
+        }
+    };
+    libc::timespec {
+        tv_sec: tv_sec as libc::time_t,
+        tv_nsec: tv_nsec as _,
+    }
+}
+
+/// Returns the last modification and the last access time of the file.
+pub fn file_times(path: &Path) -> io::Result<(SystemTime, SystemTime)> {
+    let metadata = fs::metadata(path)?;
+    Ok((metadata.modified()?, metadata.accessed()?))
+}
+
+/// Parses a local time in the `TIMESTAMP_FORMAT` format.
+/// Seconds can be omitted, e.g: "2023.11.11 21:30".
+pub fn parse_timestamp(timestamp: &str) -> Option<SystemTime> {
+    let timestamp = timestamp.trim();
+    let datetime = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT)
+        .or_else(|_| NaiveDateTime::parse_from_str(timestamp, "%Y.%m.%d %H:%M"))
+        .ok()?;
+    Local
+        .from_local_datetime(&datetime)
+        .earliest()
+        .map(SystemTime::from)
+}
+
+/// Formats the time as a local time in the `TIMESTAMP_FORMAT` format.
+pub fn format_timestamp(time: SystemTime) -> String {
+    let datetime: DateTime<Local> = time.into();
+    datetime.format(TIMESTAMP_FORMAT).to_string()
+}
+
+/// Returns the permission bits of the file mode (without the file type bits).
+pub fn file_mode(path: &Path) -> io::Result<u32> {
+    Ok(fs::symlink_metadata(path)?.mode() & MODE_MASK)
+}
+
+/// Returns the user id and group id of the owner of the file.
+pub fn file_owner(path: &Path) -> io::Result<(u32, u32)> {
+    let metadata = fs::symlink_metadata(path)?;
+    Ok((metadata.uid(), metadata.gid()))
+}
+
+/// Parses an octal permission string, like "755" or "4755".
+/// Returns `None` if the string is not a valid octal number or it has bits outside of `MODE_MASK`.
+pub fn parse_mode(mode: &str) -> Option<u32> {
+    let mode = mode.trim();
+    if mode.is_empty() {
+        return None;
+    }
+
+    u32::from_str_radix(mode, 8)
+        .ok()
+        .filter(|mode| mode & !MODE_MASK == 0)
+}
+
+/// Resolves a user name or a numeric user id into a user id.
+pub fn user_id(user: &str) -> Option<u32> {
+    resolve_id(user, PASSWD_FILE_PATH)
+}
+
+/// Resolves a group name or a numeric group id into a group id.
+pub fn group_id(group: &str) -> Option<u32> {
+    resolve_id(group, GROUP_FILE_PATH)
+}
+
+/// Returns the name of the user with the `uid` user id if it has any.
+pub fn user_name(uid: u32) -> Option<String> {
+    resolve_name(uid, PASSWD_FILE_PATH)
+}
+
+/// Returns the name of the group with the `gid` group id if it has any.
+pub fn group_name(gid: u32) -> Option<String> {
+    resolve_name(gid, GROUP_FILE_PATH)
+}
+
+fn resolve_id(name: &str, database: &str) -> Option<u32> {
+    let name = name.trim();
+    if let Ok(id) = name.parse::<u32>() {
+        return Some(id);
+    }
+
+    let content = fs::read_to_string(database).ok()?;
+    find_id(&content, name)
+}
+
+fn resolve_name(id: u32, database: &str) -> Option<String> {
+    let content = fs::read_to_string(database).ok()?;
+    find_name(&content, id)
+}
+
+/// Looks up the id of `name` in the content of a passwd(5) or group(5) formatted file.
+/// Both of them have the name as the first and the id as the third field.
+fn find_id(content: &str, name: &str) -> Option<u32> {
+    content
+        .lines()
+        .map(|line| line.split(':').collect::<Vec<&str>>())
+        .filter(|fields| fields.len() > 2 && fields[0] == name)
+        .find_map(|fields| fields[2].parse().ok())
+}
+
+/// Looks up the name of `id` in the content of a passwd(5) or group(5) formatted file.
+fn find_name(content: &str, id: u32) -> Option<String> {
+    content
+        .lines()
+        .map(|line| line.split(':').collect::<Vec<&str>>())
+        .find(|fields| fields.len() > 2 && fields[2].parse() == Ok(id))
+        .map(|fields| String::from(fields[0]))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        apply_attributes, apply_timestamps, file_mode, file_times, find_id, find_name,
+        format_timestamp, parse_mode, parse_timestamp, AttributeChange, TimestampChange,
+    };
+    use crate::core::test_dir::TestDir;
+    use std::{
+        fs::{self, File},
+        os::unix::fs::{symlink, MetadataExt, PermissionsExt},
+    };
+
+    const PASSWD: &str = "root:x:0:0:root:/root:/bin/bash\n\
+        daemon:x:1:1:daemon:/usr/sbin:/usr/sbin/nologin\n\
+        user:x:1000:1000:User,,,:/home/user:/bin/bash\n";
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!(parse_mode("755"), Some(0o755));
+        assert_eq!(parse_mode("4755"), Some(0o4755));
+        assert_eq!(parse_mode(" 644 "), Some(0o644));
+        assert_eq!(parse_mode("799"), None);
+        assert_eq!(parse_mode("17777"), None);
+        assert_eq!(parse_mode(""), None);
+    }
+
+    #[test]
+    fn test_find_id_and_name() {
+        assert_eq!(find_id(PASSWD, "user"), Some(1000));
+        assert_eq!(find_id(PASSWD, "root"), Some(0));
+        assert_eq!(find_id(PASSWD, "nobody"), None);
+        assert_eq!(find_name(PASSWD, 1), Some(String::from("daemon")));
+        assert_eq!(find_name(PASSWD, 42), None);
+    }
+
+    #[test]
+    fn test_apply_attributes_recursive_with_separate_masks() {
+        let root = TestDir::new("attributes");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("sub/file.txt"), "content").unwrap();
+
+        let change = AttributeChange {
+            file_mode: Some(0o600),
+            dir_mode: Some(0o750),
+            recursive: true,
+            ..AttributeChange::default()
+        };
+        let errors = apply_attributes(&[root.to_path_buf()], &change);
+
+        assert!(errors.is_empty());
+        assert_eq!(file_mode(&root).unwrap(), 0o750);
+        assert_eq!(file_mode(&root.join("sub")).unwrap(), 0o750);
+        assert_eq!(file_mode(&root.join("sub/file.txt")).unwrap(), 0o600);
+    }
+
+    #[test]
+    fn test_apply_attributes_recursive_with_file_mode_only() {
+        let root = TestDir::new("attributes-file");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::set_permissions(root.join("sub"), fs::Permissions::from_mode(0o751)).unwrap();
+        fs::write(root.join("sub/file.txt"), "content").unwrap();
+
+        let change = AttributeChange {
+            file_mode: Some(0o600),
+            recursive: true,
+            ..AttributeChange::default()
+        };
+        let errors = apply_attributes(&[root.to_path_buf()], &change);
+
+        assert!(errors.is_empty());
+        assert_eq!(file_mode(&root.join("sub")).unwrap(), 0o751);
+        assert_eq!(file_mode(&root.join("sub/file.txt")).unwrap(), 0o600);
+    }
+
+    #[test]
+    fn test_apply_attributes_reports_errors_per_file() {
+        let dir = TestDir::new("attributes-missing");
+        let missing = dir.join("missing-file");
+        let change = AttributeChange {
+            file_mode: Some(0o644),
+            ..AttributeChange::default()
+        };
+        let errors = apply_attributes(std::slice::from_ref(&missing), &change);
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, missing);
+    }
+
+    #[test]
+    fn test_parse_and_format_timestamp() {
+        let time = parse_timestamp("2023.11.11 21:30:15").unwrap();
+        assert_eq!(format_timestamp(time), "2023.11.11 21:30:15");
+
+        let without_seconds = parse_timestamp("2023.11.11 21:30").unwrap();
+        assert_eq!(format_timestamp(without_seconds), "2023.11.11 21:30:00");
+
+        assert!(parse_timestamp("11/11/2023").is_none());
+    }
+
+    #[test]
+    fn test_apply_timestamps_recursive() {
+        let root = TestDir::new("timestamps");
+        fs::write(root.join("file.txt"), "content").unwrap();
+
+        let modified = parse_timestamp("2001.09.09 03:46:40").unwrap();
+        let accessed = parse_timestamp("2002.02.02 02:02:02").unwrap();
+        let change = TimestampChange {
+            modified: Some(modified),
+            accessed: Some(accessed),
+            recursive: true,
+        };
+        let errors = apply_timestamps(&[root.to_path_buf()], &change);
+
+        assert!(errors.is_empty());
+        assert_eq!(
+            file_times(&root.join("file.txt")).unwrap(),
+            (modified, accessed)
+        );
+        assert_eq!(file_times(&root).unwrap().0, modified);
+    }
+
+    #[test]
+    fn test_apply_timestamps_on_unreadable_files_and_links() {
+        let root = TestDir::new("timestamps-link");
+        File::create(root.join("target.txt")).unwrap();
+        fs::set_permissions(root.join("target.txt"), fs::Permissions::from_mode(0o200)).unwrap();
+        symlink("target.txt", root.join("link")).unwrap();
+        let (target_modified, _) = file_times(&root.join("target.txt")).unwrap();
+
+        let modified = parse_timestamp("1969.07.20 20:17:40").unwrap();
+        let change = TimestampChange {
+            modified: Some(modified),
+            ..TimestampChange::default()
+        };
+        let errors = apply_timestamps(&[root.join("link")], &change);
+
+        assert!(errors.is_empty());
+        let link = fs::symlink_metadata(root.join("link")).unwrap();
+        assert_eq!(link.modified().unwrap(), modified);
+        assert_eq!(
+            file_times(&root.join("target.txt")).unwrap().0,
+            target_modified
+        );
+
+        let errors = apply_timestamps(&[root.join("target.txt")], &change);
+        assert!(errors.is_empty());
+        assert_eq!(
+            fs::metadata(root.join("target.txt")).unwrap().mtime(),
+            link.mtime()
+        );
+    }
+}
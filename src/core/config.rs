@@ -1,5 +1,7 @@
+use super::conflict::ConflictResolution;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
 
 // Sometimes this language is a joke: it can't concatenate a string literal and a const str at compile time...
 
@@ -17,6 +19,9 @@ pub const TABLE_FALLBACK_PATH: &str = "/";
 pub const TABLE_FALLBACK_PREDICATE: &str = "name";
 /// A fallback sort direction value for the `TableSorter`, if the configuration file is missing the `sort_direction` key.
 pub const TABLE_FALLBACK_DIRECTION: &str = "asc";
+/// The maximum number of directories whose sort order is remembered.
+/// When the table is full, the least recently sorted directory is forgotten.
+pub const DIR_SORT_ORDERS_CAPACITY: usize = 256;
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
@@ -75,6 +80,68 @@ impl Default for TableConfiguration {
     }
 }
 
+/// The sort predicate and direction remembered for a single directory.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DirSortOrder {
+    path: PathBuf,
+    #[serde(default = "fallback_predicate")]
+    sort_predicate: String,
+    #[serde(default = "fallback_direction")]
+    sort_direction: String,
+}
+
+impl DirSortOrder {
+    /// Returns the String representation of the remembered `TableSortPredicate`.
+    pub fn sort_predicate(&self) -> &String {
+        &self.sort_predicate
+    }
+
+    /// Returns the String representation of the remembered `TableSortDirection`.
+    pub fn sort_direction(&self) -> &String {
+        &self.sort_direction
+    }
+}
+
+/// A bounded-size table of sort orders keyed by directory path.
+/// The most recently sorted directory is kept at the front,
+/// and the table never holds more than `DIR_SORT_ORDERS_CAPACITY` entries.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct DirSortOrders {
+    entries: VecDeque<DirSortOrder>,
+}
+
+impl DirSortOrders {
+    /// Returns the sort order remembered for `path` if it has any.
+    pub fn get(&self, path: &Path) -> Option<&DirSortOrder> {
+        self.entries.iter().find(|entry| entry.path == path)
+    }
+
+    /// Remembers the sort order for `path`, replacing the previous one if it had.
+    /// If the table is full, the least recently inserted entry will be dropped.
+    pub fn insert(&mut self, path: PathBuf, sort_predicate: String, sort_direction: String) {
+        self.entries.retain(|entry| entry.path != path);
+        self.entries.push_front(DirSortOrder {
+            path,
+            sort_predicate,
+            sort_direction,
+        });
+        self.entries.truncate(DIR_SORT_ORDERS_CAPACITY);
+    }
+
+    /// Returns the number of remembered sort orders.
+    #[allow(unused)]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if no sort order is remembered.
+    #[allow(unused)]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
 /// A collection of runtime variables that alters the behavior of the application.
 #[derive(Serialize, Default, Deserialize)]
 pub struct Configuration {
@@ -85,6 +152,20 @@ pub struct Configuration {
 
     #[serde(default = "bool::default")]
     show_hidden_files: bool,
+
+    /// When enabled, every directory remembers its own sort order,
+    /// instead of using the sort order of the panel.
+    #[serde(default = "bool::default")]
+    remember_sort_per_dir: bool,
+
+    /// The sort orders remembered for each directory.
+    #[serde(default)]
+    dir_sort_orders: DirSortOrders,
+
+    /// How to handle the existing targets of copied and moved files.
+    /// The user will be asked for every file if it is not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    conflict_resolution: Option<ConflictResolution>,
 }
 
 impl Configuration {
@@ -113,6 +194,30 @@ impl Configuration {
     pub fn set_show_hidden_files(&mut self, show: bool) {
         self.show_hidden_files = show
     }
+
+    /// Returns true if the sort order should be remembered for each directory.
+    pub fn remember_sort_per_dir(&self) -> bool {
+        self.remember_sort_per_dir
+    }
+
+    pub fn set_remember_sort_per_dir(&mut self, remember: bool) {
+        self.remember_sort_per_dir = remember
+    }
+
+    /// Returns the sort orders remembered for each directory.
+    pub fn dir_sort_orders(&self) -> &DirSortOrders {
+        &self.dir_sort_orders
+    }
+
+    pub fn dir_sort_orders_mut(&mut self) -> &mut DirSortOrders {
+        &mut self.dir_sort_orders
+    }
+
+    /// Returns the default handling of existing targets during file transfers,
+    /// None if the user should be asked.
+    pub fn conflict_resolution(&self) -> Option<ConflictResolution> {
+        self.conflict_resolution
+    }
 }
 
 /// Attempts to deserialize a `Configuration` from a configuration file.
@@ -208,10 +313,11 @@ fn fallback_direction() -> String {
 #[cfg(test)]
 mod test {
     use super::{
-        Configuration, TableConfiguration, TABLE_FALLBACK_DIRECTION, TABLE_FALLBACK_PATH,
+        Configuration, ConflictResolution, DirSortOrders, TableConfiguration,
+        DIR_SORT_ORDERS_CAPACITY, TABLE_FALLBACK_DIRECTION, TABLE_FALLBACK_PATH,
         TABLE_FALLBACK_PREDICATE,
     };
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
 
     #[test]
     fn test_table_configuration_default() {
@@ -230,4 +336,69 @@ mod test {
         assert_eq!(*config.left_table_config(), table_config);
         assert_eq!(*config.right_table_config(), table_config);
     }
+
+    #[test]
+    fn test_dir_sort_orders_insert_replaces_previous() {
+        let mut orders = DirSortOrders::default();
+        orders.insert("/tmp".into(), "name".into(), "asc".into());
+        orders.insert("/tmp".into(), "modified".into(), "desc".into());
+
+        let order = orders.get(Path::new("/tmp")).unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(order.sort_predicate(), "modified");
+        assert_eq!(order.sort_direction(), "desc");
+        assert!(orders.get(Path::new("/home")).is_none());
+    }
+
+    #[test]
+    fn test_dir_sort_orders_is_bounded() {
+        let mut orders = DirSortOrders::default();
+        for i in 0..=DIR_SORT_ORDERS_CAPACITY {
+            orders.insert(
+                PathBuf::from(format!("/{}", i)),
+                "size".into(),
+                "asc".into(),
+            );
+        }
+
+        assert_eq!(orders.len(), DIR_SORT_ORDERS_CAPACITY);
+        // the least recently inserted directory is forgotten
+        assert!(orders.get(Path::new("/0")).is_none());
+        assert!(orders.get(Path::new("/1")).is_some());
+    }
+
+    #[test]
+    fn test_dir_sort_orders_serialization() {
+        let mut config = Configuration::default();
+        config.set_remember_sort_per_dir(true);
+        config.dir_sort_orders_mut().insert(
+            "/home/user/Downloads".into(),
+            "modified".into(),
+            "desc".into(),
+        );
+
+        let serialized = toml::to_string(&config).unwrap();
+        let deserialized = toml::from_str::<Configuration>(&serialized).unwrap();
+
+        assert!(deserialized.remember_sort_per_dir());
+        assert_eq!(deserialized.dir_sort_orders(), config.dir_sort_orders());
+    }
+
+    #[test]
+    fn test_conflict_resolution_serialization() {
+        let config = Configuration::default();
+        let serialized = toml::to_string(&config).unwrap();
+        let deserialized = toml::from_str::<Configuration>(&serialized).unwrap();
+        assert_eq!(deserialized.conflict_resolution(), None);
+
+        let serialized = format!(
+            "conflict_resolution = \"overwrite_if_newer\"\n{}",
+            serialized
+        );
+        let deserialized = toml::from_str::<Configuration>(&serialized).unwrap();
+        assert_eq!(
+            deserialized.conflict_resolution(),
+            Some(ConflictResolution::OverwriteIfNewer)
+        );
+    }
 }
@@ -0,0 +1,213 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// What to do when the target of a transferred file already exists.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictResolution {
+    Overwrite,
+    Skip,
+    /// Transfer the file under a new name with a numbered suffix, e.g. "notes (1).txt".
+    Rename,
+    OverwriteIfNewer,
+    OverwriteIfSizeDiffers,
+    /// Continue an interrupted transfer: copy the part of the source
+    /// which is missing from the end of the target.
+    Append,
+}
+
+impl ConflictResolution {
+    pub const ALL: [ConflictResolution; 6] = [
+        ConflictResolution::Overwrite,
+        ConflictResolution::Skip,
+        ConflictResolution::Rename,
+        ConflictResolution::OverwriteIfNewer,
+        ConflictResolution::OverwriteIfSizeDiffers,
+        ConflictResolution::Append,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            ConflictResolution::Overwrite => "Overwrite",
+            ConflictResolution::Skip => "Skip",
+            ConflictResolution::Rename => "Rename",
+            ConflictResolution::OverwriteIfNewer => "If newer",
+            ConflictResolution::OverwriteIfSizeDiffers => "If size differs",
+            ConflictResolution::Append => "Append",
+        }
+    }
+}
+
+/// The action taken on a conflicting file, decided by a `ConflictResolution`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConflictAction {
+    Skip,
+    /// Write the file to the path, overwriting it if it exists.
+    WriteTo(PathBuf),
+    /// Append the missing part of the source to the target.
+    Append,
+}
+
+/// A source file whose target already exists, with the properties the user decides by.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileConflict {
+    pub source: PathBuf,
+    pub source_size: u64,
+    pub source_modified: Option<SystemTime>,
+    pub target: PathBuf,
+    pub target_size: u64,
+    pub target_modified: Option<SystemTime>,
+}
+
+impl FileConflict {
+    pub fn new(source: &Path, target: &Path) -> Self {
+        let (source_size, source_modified) = size_and_modified(source);
+        let (target_size, target_modified) = size_and_modified(target);
+        FileConflict {
+            source: PathBuf::from(source),
+            source_size,
+            source_modified,
+            target: PathBuf::from(target),
+            target_size,
+            target_modified,
+        }
+    }
+
+    /// Decides the action to take on the conflicting file by the `resolution`.
+    pub fn action(&self, resolution: ConflictResolution) -> ConflictAction {
+        let overwrite = ConflictAction::WriteTo(self.target.clone());
+        match resolution {
+            ConflictResolution::Overwrite => overwrite,
+            ConflictResolution::Skip => ConflictAction::Skip,
+            ConflictResolution::Rename => ConflictAction::WriteTo(unique_path(&self.target)),
+            ConflictResolution::OverwriteIfNewer => {
+                match self.source_modified > self.target_modified {
+                    true => overwrite,
+                    false => ConflictAction::Skip,
+                }
+            }
+            ConflictResolution::OverwriteIfSizeDiffers => {
+                match self.source_size != self.target_size {
+                    true => overwrite,
+                    false => ConflictAction::Skip,
+                }
+            }
+            ConflictResolution::Append => ConflictAction::Append,
+        }
+    }
+}
+
+fn size_and_modified(path: &Path) -> (u64, Option<SystemTime>) {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) => (metadata.len(), metadata.modified().ok()),
+        Err(_) => (0, None),
+    }
+}
+
+/// Returns the first path not existing yet by numbering the file name of `path`,
+/// e.g. "notes (1).txt", "notes (2).txt" and so on.
+pub fn unique_path(path: &Path) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+
+    (1..)
+        .map(|number| path.with_file_name(format!("{} ({}){}", stem, number, extension)))
+        .find(|candidate| fs::symlink_metadata(candidate).is_err())
+        .unwrap_or_else(|| PathBuf::from(path))
+}
+
+/// Appends the part of the `source` file missing from the end of the `target`.
+/// Returns the number of bytes appended.
+pub fn append_missing(source: &Path, target: &Path) -> io::Result<u64> {
+    let mut source = File::open(source)?;
+    let mut target = OpenOptions::new().append(true).open(target)?;
+    let target_size = target.metadata()?.len();
+    if source.metadata()?.len() <= target_size {
+        return Ok(0);
+    }
+    source.seek(SeekFrom::Start(target_size))?;
+    io::copy(&mut source, &mut target)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{append_missing, unique_path, ConflictAction, ConflictResolution, FileConflict};
+    use crate::core::test_dir::TestDir;
+    use std::{
+        fs,
+        path::PathBuf,
+        time::{Duration, SystemTime},
+    };
+
+    #[test]
+    fn test_conflict_actions() {
+        let now = SystemTime::now();
+        let conflict = FileConflict {
+            source: PathBuf::from("/a/notes.txt"),
+            source_size: 10,
+            source_modified: Some(now),
+            target: PathBuf::from("/b/notes.txt"),
+            target_size: 10,
+            target_modified: Some(now - Duration::from_secs(60)),
+        };
+        let overwrite = ConflictAction::WriteTo(PathBuf::from("/b/notes.txt"));
+
+        assert_eq!(conflict.action(ConflictResolution::Overwrite), overwrite);
+        assert_eq!(
+            conflict.action(ConflictResolution::Skip),
+            ConflictAction::Skip
+        );
+        assert_eq!(
+            conflict.action(ConflictResolution::OverwriteIfNewer),
+            overwrite
+        );
+        assert_eq!(
+            conflict.action(ConflictResolution::OverwriteIfSizeDiffers),
+            ConflictAction::Skip
+        );
+    }
+
+    #[test]
+    fn test_unique_path() {
+        let dir = TestDir::new("conflict-unique");
+        fs::write(dir.join("notes.txt"), "").unwrap();
+        fs::write(dir.join("notes (1).txt"), "").unwrap();
+
+        assert_eq!(
+            unique_path(&dir.join("notes.txt")),
+            dir.join("notes (2).txt")
+        );
+        assert_eq!(unique_path(&dir.join("README")), dir.join("README (1)"));
+    }
+
+    #[test]
+    fn test_append_missing() {
+        let dir = TestDir::new("conflict-append");
+        fs::write(dir.join("source"), "hello world").unwrap();
+        fs::write(dir.join("target"), "hello").unwrap();
+
+        assert_eq!(
+            append_missing(&dir.join("source"), &dir.join("target")).unwrap(),
+            6
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("target")).unwrap(),
+            "hello world"
+        );
+        assert_eq!(
+            append_missing(&dir.join("source"), &dir.join("target")).unwrap(),
+            0
+        );
+    }
+}
@@ -0,0 +1,563 @@
+use std::{
+    ffi::CString,
+    fs::{self, File, Metadata, OpenOptions},
+    io::{self, Read},
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{chown, symlink, FileExt, FileTypeExt, MetadataExt, OpenOptionsExt},
+        io::AsRawFd,
+    },
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+use xxhash_rust::xxh3::Xxh3;
+
+const CHUNK_SIZE: u64 = 8 * 1024 * 1024; // TODO: configurable buffer
+
+/// Shared between a transfer and whoever started it to pause or cancel it.
+#[derive(Debug, Default)]
+pub struct TransferControl {
+    cancelled: AtomicBool,
+    paused: AtomicBool,
+    unattended: AtomicBool,
+}
+
+impl TransferControl {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::SeqCst);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Marks the transfer as running in the background, where nobody answers its questions:
+    /// the conflicting files are skipped unless a resolution was chosen for all of them,
+    /// and the files failed to transfer are skipped.
+    pub fn set_unattended(&self) {
+        self.unattended.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_unattended(&self) -> bool {
+        self.unattended.load(Ordering::SeqCst)
+    }
+
+    /// Blocks while the transfer is paused.
+    /// Returns false if the transfer was cancelled and it must stop.
+    pub fn proceed(&self) -> bool {
+        while self.is_paused() && !self.is_cancelled() {
+            thread::sleep(Duration::from_millis(100));
+        }
+        !self.is_cancelled()
+    }
+}
+
+/// The progress of a transfer of a file or a directory tree.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CopyProgress {
+    /// The files transferred of the whole operation.
+    pub copied_files: u64,
+    pub total_files: u64,
+    /// The bytes transferred of the whole operation.
+    pub copied_bytes: u64,
+    pub total_bytes: u64,
+    /// The bytes transferred of the current file.
+    pub file_bytes_copied: u64,
+    pub file_total_bytes: u64,
+    pub file_name: String,
+}
+
+/// The transfer rate smoothed by an exponential moving average,
+/// so the estimated remaining time does not jump with every chunk.
+#[derive(Debug, Default)]
+pub struct TransferRate {
+    bytes_per_sec: Option<f64>,
+    last_sample: Option<(Instant, u64)>,
+}
+
+impl TransferRate {
+    /// The weight of the latest sample in the average.
+    const SMOOTHING: f64 = 0.3;
+    const SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+    /// Takes a sample of the bytes copied so far, at most once in every `SAMPLE_INTERVAL`.
+    pub fn update(&mut self, now: Instant, copied_bytes: u64) {
+        let (time, bytes) = match self.last_sample {
+            Some(sample) => sample,
+            None => {
+                self.last_sample = Some((now, copied_bytes));
+                return;
+            }
+        };
+        let elapsed = now.saturating_duration_since(time);
+        if elapsed < Self::SAMPLE_INTERVAL {
+            return;
+        }
+        let rate = copied_bytes.saturating_sub(bytes) as f64 / elapsed.as_secs_f64();
+        self.bytes_per_sec = Some(match self.bytes_per_sec {
+            Some(average) => average + Self::SMOOTHING * (rate - average),
+            None => rate,
+        });
+        self.last_sample = Some((now, copied_bytes));
+    }
+
+    pub fn bytes_per_sec(&self) -> u64 {
+        self.bytes_per_sec.unwrap_or(0.0) as u64
+    }
+
+    /// Returns the estimated time to copy the `remaining_bytes`,
+    /// or None until the rate is known.
+    pub fn remaining_time(&self, remaining_bytes: u64) -> Option<Duration> {
+        match self.bytes_per_sec {
+            Some(rate) if rate >= 1.0 => {
+                Some(Duration::from_secs_f64(remaining_bytes as f64 / rate))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Copies the file `from` to `to` with its permissions, timestamps, and its ownership
+/// if the user is allowed to change it. An existing `to` file is overwritten.
+///
+/// The content of a regular file is copied chunk by chunk, calling `progress` with
+/// the number of the bytes copied so far after every chunk. The holes of sparse files
+/// are kept, and the data is copied by the kernel with `copy_file_range` if it is possible.
+/// The copy waits while the transfer is paused, and the partially written `to` is removed
+/// if the transfer is cancelled.
+///
+/// A symbolic link `from` is followed, see `copy_symlink` for copying the link itself.
+/// Named pipes are re-created, devices and sockets cannot be copied.
+pub fn copy_file<F>(
+    from: &Path,
+    to: &Path,
+    control: &TransferControl,
+    mut progress: F,
+) -> io::Result<()>
+where
+    F: FnMut(u64),
+{
+    let metadata = fs::metadata(from)?;
+    let file_type = metadata.file_type();
+    if file_type.is_fifo() {
+        remove_file_if_exists(to)?;
+        make_fifo(to, metadata.mode())?;
+    } else if file_type.is_file() {
+        let result = copy_content(from, to, metadata.len(), control, &mut progress);
+        if result.is_err() && control.is_cancelled() {
+            let _ = fs::remove_file(to);
+        }
+        result?;
+    } else {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Devices and sockets cannot be copied",
+        ));
+    }
+    preserve_attributes(to, &metadata)
+}
+
+/// Re-reads the copied file `to` and its source `from`, and compares their checksums.
+/// Returns an `InvalidData` error if the copy is not identical to the source.
+/// Only regular files are compared, there is nothing to read from a named pipe.
+pub fn verify_copy(from: &Path, to: &Path, control: &TransferControl) -> io::Result<()> {
+    if !fs::metadata(from)?.is_file() {
+        return Ok(());
+    }
+    match checksum(from, control)? == checksum(to, control)? {
+        true => Ok(()),
+        false => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "The copy differs from the source",
+        )),
+    }
+}
+
+fn checksum(path: &Path, control: &TransferControl) -> io::Result<u128> {
+    let mut file = File::open(path)?;
+    let mut hasher = Xxh3::new();
+    let mut buffer = vec![0; CHUNK_SIZE as usize];
+    loop {
+        if !control.proceed() {
+            return Err(io::Error::new(
+                io::ErrorKind::Interrupted,
+                "The transfer was cancelled",
+            ));
+        }
+        match file.read(&mut buffer)? {
+            0 => return Ok(hasher.digest128()),
+            read => hasher.update(&buffer[..read]),
+        }
+    }
+}
+
+/// Creates a symbolic link on `to` pointing to the same path as the link `from` does.
+/// An existing `to` file or link is replaced.
+pub fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    let link_target = fs::read_link(from)?;
+    remove_file_if_exists(to)?;
+    symlink(link_target, to)
+}
+
+fn remove_file_if_exists(path: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if !metadata.is_dir() => fs::remove_file(path),
+        _ => Ok(()),
+    }
+}
+
+fn copy_content<F>(
+    from: &Path,
+    to: &Path,
+    length: u64,
+    control: &TransferControl,
+    progress: &mut F,
+) -> io::Result<()>
+where
+    F: FnMut(u64),
+{
+    let source = File::open(from)?;
+    // only the user can read the copy until it gets the mode of the source
+    remove_file_if_exists(to)?;
+    let target = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(to)?;
+    let mut copier = RangeCopier::new(&source, &target);
+    let mut position = 0;
+
+    while position < length {
+        let (data_start, data_end) = data_segment(&source, position, length);
+        let mut offset = data_start;
+        while offset < data_end {
+            if !control.proceed() {
+                return Err(io::Error::new(
+                    io::ErrorKind::Interrupted,
+                    "The transfer was cancelled",
+                ));
+            }
+            let copied = copier.copy(offset, CHUNK_SIZE.min(data_end - offset))?;
+            if copied == 0 {
+                // the source was truncated while copying it
+                return Ok(());
+            }
+            offset += copied;
+            progress(offset);
+        }
+        position = data_end;
+    }
+    progress(length);
+    // the trailing hole of a sparse file
+    target.set_len(length)
+}
+
+/// Returns the range of the next data segment of the `file` starting from `position`,
+/// skipping the holes of a sparse file.
+#[cfg(target_os = "linux")]
+fn data_segment(file: &File, position: u64, length: u64) -> (u64, u64) {
+    let fd = file.as_raw_fd();
+    let start = unsafe { libc::lseek(fd, position as libc::off_t, libc::SEEK_DATA) };
+    if start < 0 {
+        return match io::Error::last_os_error().raw_os_error() {
+            // there is no more data, only a hole until the end of the file
+            Some(libc::ENXIO) => (length, length),
+            // the file system cannot tell where the holes are
+            _ => (position, length),
+        };
+    }
+    let end = unsafe { libc::lseek(fd, start, libc::SEEK_HOLE) };
+    match end < 0 {
+        true => (start as u64, length),
+        false => (start as u64, (end as u64).min(length)),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn data_segment(_file: &File, position: u64, length: u64) -> (u64, u64) {
+    (position, length)
+}
+
+/// Copies ranges of a file to the same offsets of another file,
+/// falling back to reading and writing when the kernel cannot copy between them.
+struct RangeCopier<'file> {
+    buffer: Vec<u8>,
+    kernel_copy: bool,
+    source: &'file File,
+    target: &'file File,
+}
+
+impl<'file> RangeCopier<'file> {
+    fn new(source: &'file File, target: &'file File) -> Self {
+        RangeCopier {
+            buffer: Vec::new(),
+            kernel_copy: cfg!(target_os = "linux"),
+            source,
+            target,
+        }
+    }
+
+    /// Copies at most `length` bytes from the `offset`, returns the number of the bytes copied.
+    fn copy(&mut self, offset: u64, length: u64) -> io::Result<u64> {
+        if self.kernel_copy {
+            match self.kernel_copy(offset, length) {
+                Ok(copied) => return Ok(copied),
+                Err(error) if is_kernel_copy_unsupported(&error) => self.kernel_copy = false,
+                Err(error) => return Err(error),
+            }
+        }
+
+        self.buffer.resize(length as usize, 0);
+        let read = self.source.read_at(&mut self.buffer, offset)?;
+        self.target.write_all_at(&self.buffer[..read], offset)?;
+        Ok(read as u64)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn kernel_copy(&self, offset: u64, length: u64) -> io::Result<u64> {
+        let mut offset_in = offset as libc::loff_t;
+        let mut offset_out = offset as libc::loff_t;
+        let copied = unsafe {
+            libc::copy_file_range(
+                self.source.as_raw_fd(),
+                &mut offset_in,
+                self.target.as_raw_fd(),
+                &mut offset_out,
+                length as usize,
+                0,
+            )
+        };
+        match copied < 0 {
+            true => Err(io::Error::last_os_error()),
+            false => Ok(copied as u64),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn kernel_copy(&self, _offset: u64, _length: u64) -> io::Result<u64> {
+        Err(io::Error::from(io::ErrorKind::Unsupported))
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn is_kernel_copy_unsupported(error: &io::Error) -> bool {
+    matches!(
+        error.raw_os_error(),
+        Some(libc::EXDEV | libc::ENOSYS | libc::EINVAL | libc::EOPNOTSUPP | libc::EPERM)
+    )
+}
+
+#[cfg(not(target_os = "linux"))]
+fn is_kernel_copy_unsupported(_error: &io::Error) -> bool {
+    true
+}
+
+pub(super) fn c_path(path: &Path) -> io::Result<CString> {
+    CString::new(path.as_os_str().as_bytes())
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))
+}
+
+fn make_fifo(path: &Path, mode: u32) -> io::Result<()> {
+    let path = c_path(path)?;
+    match unsafe { libc::mkfifo(path.as_ptr(), mode as libc::mode_t) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+/// Sets the ownership, the permissions and the timestamps of `path` to the ones in `metadata`.
+/// The ownership is changed only if the user is allowed to do it.
+pub fn preserve_attributes(path: &Path, metadata: &Metadata) -> io::Result<()> {
+    // changing the owner clears the setuid and setgid bits, it must be done first
+    let _ = chown(path, Some(metadata.uid()), Some(metadata.gid()));
+    fs::set_permissions(path, metadata.permissions())?;
+
+    // set by path, opening a named pipe would block
+    let times = [
+        libc::timespec {
+            tv_sec: metadata.atime() as libc::time_t,
+            tv_nsec: metadata.atime_nsec() as _,
+        },
+        libc::timespec {
+            tv_sec: metadata.mtime() as libc::time_t,
+            tv_nsec: metadata.mtime_nsec() as _,
+        },
+    ];
+    let path = c_path(path)?;
+    match unsafe { libc::utimensat(libc::AT_FDCWD, path.as_ptr(), times.as_ptr(), 0) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{copy_file, copy_symlink, verify_copy, TransferControl, TransferRate};
+    use crate::core::test_dir::TestDir;
+    use std::{
+        fs::{self, File, FileTimes, Permissions},
+        io::ErrorKind,
+        os::unix::fs::{symlink, FileExt, FileTypeExt, MetadataExt, PermissionsExt},
+        path::PathBuf,
+        time::{Duration, Instant, SystemTime},
+    };
+
+    #[test]
+    fn test_transfer_rate() {
+        let start = Instant::now();
+        let mut rate = TransferRate::default();
+        rate.update(start, 0);
+        assert_eq!(rate.remaining_time(1000), None);
+
+        rate.update(start + Duration::from_secs(1), 1000);
+        assert_eq!(rate.bytes_per_sec(), 1000);
+        assert_eq!(rate.remaining_time(5000), Some(Duration::from_secs(5)));
+
+        // too frequent samples are ignored, then a slower second is smoothed
+        rate.update(start + Duration::from_millis(1100), 5000);
+        rate.update(start + Duration::from_secs(2), 1500);
+        assert_eq!(rate.bytes_per_sec(), 850);
+    }
+
+    #[test]
+    fn test_copy_file_preserves_attributes() {
+        let dir = TestDir::new("copy-attributes");
+        let source = dir.join("source.sh");
+        fs::write(&source, "#!/bin/sh").unwrap();
+        fs::set_permissions(&source, Permissions::from_mode(0o750)).unwrap();
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        File::options()
+            .write(true)
+            .open(&source)
+            .unwrap()
+            .set_times(
+                FileTimes::new()
+                    .set_modified(modified)
+                    .set_accessed(modified),
+            )
+            .unwrap();
+
+        let mut reported = Vec::new();
+        let target = dir.join("target.sh");
+        copy_file(&source, &target, &TransferControl::default(), |copied| {
+            reported.push(copied)
+        })
+        .unwrap();
+
+        assert_eq!(fs::read_to_string(&target).unwrap(), "#!/bin/sh");
+        let metadata = fs::metadata(&target).unwrap();
+        assert_eq!(metadata.mode() & 0o777, 0o750);
+        assert_eq!(metadata.modified().unwrap(), modified);
+        assert_eq!(reported.last(), Some(&9));
+    }
+
+    #[test]
+    fn test_copy_file_replaces_target_link() {
+        let dir = TestDir::new("copy-replace-link");
+        fs::write(dir.join("source"), "content").unwrap();
+        fs::set_permissions(dir.join("source"), Permissions::from_mode(0o644)).unwrap();
+        fs::write(dir.join("linked"), "linked").unwrap();
+        symlink("linked", dir.join("target")).unwrap();
+
+        copy_file(
+            &dir.join("source"),
+            &dir.join("target"),
+            &TransferControl::default(),
+            |_| {},
+        )
+        .unwrap();
+
+        // the link is replaced, the file it pointed to is not written through it
+        assert!(!dir.join("target").is_symlink());
+        assert_eq!(fs::read_to_string(dir.join("target")).unwrap(), "content");
+        assert_eq!(fs::read_to_string(dir.join("linked")).unwrap(), "linked");
+        let metadata = fs::metadata(dir.join("target")).unwrap();
+        assert_eq!(metadata.mode() & 0o777, 0o644);
+    }
+
+    #[test]
+    fn test_verify_copy() {
+        let dir = TestDir::new("copy-verify");
+        let control = TransferControl::default();
+        fs::write(dir.join("source"), "content").unwrap();
+        copy_file(&dir.join("source"), &dir.join("copy"), &control, |_| {}).unwrap();
+        assert!(verify_copy(&dir.join("source"), &dir.join("copy"), &control).is_ok());
+
+        fs::write(dir.join("copy"), "c0ntent").unwrap();
+        let result = verify_copy(&dir.join("source"), &dir.join("copy"), &control);
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_copy_sparse_file() {
+        let dir = TestDir::new("copy-sparse");
+        let source = dir.join("sparse");
+        let file = File::create(&source).unwrap();
+        file.write_all_at(b"start", 0).unwrap();
+        file.write_all_at(b"end", 64 * 1024 * 1024).unwrap();
+        file.set_len(128 * 1024 * 1024).unwrap();
+
+        let target = dir.join("copy");
+        copy_file(&source, &target, &TransferControl::default(), |_| {}).unwrap();
+
+        let source_metadata = fs::metadata(&source).unwrap();
+        let target_metadata = fs::metadata(&target).unwrap();
+        assert_eq!(target_metadata.len(), source_metadata.len());
+        // the holes are not filled with zeros
+        assert!(target_metadata.blocks() <= source_metadata.blocks() + 16);
+        let mut end = [0; 3];
+        File::open(&target)
+            .unwrap()
+            .read_exact_at(&mut end, 64 * 1024 * 1024)
+            .unwrap();
+        assert_eq!(&end, b"end");
+    }
+
+    #[test]
+    fn test_copy_special_files() {
+        let dir = TestDir::new("copy-special");
+        let fifo = dir.join("fifo");
+        super::make_fifo(&fifo, 0o600).unwrap();
+        copy_file(
+            &fifo,
+            &dir.join("fifo copy"),
+            &TransferControl::default(),
+            |_| {},
+        )
+        .unwrap();
+        assert!(fs::metadata(dir.join("fifo copy"))
+            .unwrap()
+            .file_type()
+            .is_fifo());
+
+        symlink("fifo", dir.join("link")).unwrap();
+        fs::write(dir.join("link copy"), "replaced").unwrap();
+        copy_symlink(&dir.join("link"), &dir.join("link copy")).unwrap();
+        assert_eq!(
+            fs::read_link(dir.join("link copy")).unwrap(),
+            PathBuf::from("fifo")
+        );
+    }
+
+    #[test]
+    fn test_cancelled_copy_removes_partial_file() {
+        let dir = TestDir::new("copy-cancel");
+        fs::write(dir.join("source"), "content").unwrap();
+        let control = TransferControl::default();
+        control.cancel();
+
+        let result = copy_file(&dir.join("source"), &dir.join("target"), &control, |_| {});
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::Interrupted);
+        assert!(!dir.join("target").exists());
+    }
+}
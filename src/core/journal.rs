@@ -0,0 +1,456 @@
+use super::{
+    copy::{copy_file, copy_symlink, TransferControl},
+    rename::RenameBatch,
+    trash::TrashEntry,
+};
+use chrono::{DateTime, Local};
+use std::{
+    collections::VecDeque,
+    fs, io,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use walkdir::WalkDir;
+
+/// The number of the operations kept in the `Journal`, the oldest ones are dropped.
+const JOURNAL_LENGTH: usize = 100;
+
+/// A file operation which can be reverted.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Operation {
+    /// A file renamed or moved from `from` to `to`.
+    Move { from: PathBuf, to: PathBuf },
+    /// A file or a directory tree copied to `to`, which did not exist before,
+    /// with the files created by the copy.
+    Copy { to: PathBuf, created: CreatedFiles },
+    /// A new file created at `path`.
+    NewFile { path: PathBuf },
+    /// A symbolic or hard link created at `path`.
+    Link { path: PathBuf },
+    /// A new archive packed at `archive`.
+    Pack { archive: PathBuf },
+    /// Directories created, the parents first.
+    MkDir { paths: Vec<PathBuf> },
+    /// Files moved to the trash, with the entries they got there.
+    Trash { entries: Vec<TrashEntry> },
+    /// Files renamed together in a directory by the multi-rename.
+    MultiRename { batch: RenameBatch },
+}
+
+impl Operation {
+    pub fn title(&self) -> String {
+        match self {
+            Operation::Move { from, to } => format!("Move {} to {}", from.display(), to.display()),
+            Operation::Copy { to, .. } => format!("Copy to {}", to.display()),
+            Operation::NewFile { path } => format!("Create file {}", path.display()),
+            Operation::Link { path } => format!("Create link {}", path.display()),
+            Operation::Pack { archive } => format!("Pack {}", archive.display()),
+            Operation::MkDir { paths } => match paths.as_slice() {
+                [path] => format!("Create directory {}", path.display()),
+                paths => format!("Create {} directories", paths.len()),
+            },
+            Operation::Trash { entries } => match entries.as_slice() {
+                [entry] => format!("Trash {}", entry.original_path.display()),
+                entries => format!("Trash {} items", entries.len()),
+            },
+            Operation::MultiRename { batch } => {
+                format!("Rename {} files in {}", batch.len(), batch.dir().display())
+            }
+        }
+    }
+
+    /// Returns the files the operation produced, which must not change until it is undone.
+    /// Every file of a copied directory tree is included, the parents first.
+    fn results(&self) -> Vec<PathBuf> {
+        match self {
+            Operation::Move { to, .. } => vec![to.clone()],
+            Operation::Copy { created, .. } => {
+                created.files.iter().map(|(path, _)| path.clone()).collect()
+            }
+            Operation::NewFile { path } | Operation::Link { path } => vec![path.clone()],
+            Operation::Pack { archive } => vec![archive.clone()],
+            Operation::MkDir { paths } => paths.clone(),
+            Operation::Trash { .. } => Vec::new(),
+            Operation::MultiRename { batch } => batch.renamed_paths(),
+        }
+    }
+}
+
+/// Identifies the state of a file: it is the same file, and it was not modified.
+/// The modification time of directories is ignored, it changes with their content.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileStamp {
+    device: u64,
+    inode: u64,
+    modified: Option<SystemTime>,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<Self> {
+        fs::symlink_metadata(path).ok().map(|metadata| FileStamp {
+            device: metadata.dev(),
+            inode: metadata.ino(),
+            modified: match metadata.is_dir() {
+                true => None,
+                false => metadata.modified().ok(),
+            },
+        })
+    }
+}
+
+/// The files created by a copy, the parents first, with their stamps.
+/// They are collected by the worker thread while it writes them,
+/// so the copied tree is not read again when the copy is recorded.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CreatedFiles {
+    files: Vec<(PathBuf, Option<FileStamp>)>,
+}
+
+impl CreatedFiles {
+    /// Adds the file or directory just written to `path`.
+    pub fn push(&mut self, path: &Path) {
+        self.files.push((PathBuf::from(path), FileStamp::of(path)));
+    }
+}
+
+/// An operation recorded in the `Journal`.
+#[derive(Clone, Debug)]
+pub struct JournalEntry {
+    pub operation: Operation,
+    pub time: DateTime<Local>,
+    /// The results of the operation with their stamps when it was recorded.
+    stamps: Vec<(PathBuf, Option<FileStamp>)>,
+}
+
+/// The recent file operations, the latest one can be undone.
+#[derive(Default)]
+pub struct Journal {
+    entries: VecDeque<JournalEntry>,
+}
+
+impl Journal {
+    /// Records an operation which was just done.
+    pub fn record(&mut self, operation: Operation) {
+        let stamps = match &operation {
+            Operation::Copy { created, .. } => created.files.clone(),
+            operation => operation
+                .results()
+                .into_iter()
+                .map(|path| {
+                    let stamp = FileStamp::of(&path);
+                    (path, stamp)
+                })
+                .collect(),
+        };
+        if self.entries.len() == JOURNAL_LENGTH {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(JournalEntry {
+            operation,
+            time: Local::now(),
+            stamps,
+        });
+    }
+
+    /// Returns the recorded operations, the latest first.
+    pub fn entries(&self) -> impl Iterator<Item = &JournalEntry> {
+        self.entries.iter().rev()
+    }
+
+    /// Reverts the latest operation and removes it from the journal.
+    /// Nothing is changed if the files were modified since the operation,
+    /// the operation is kept in the journal if it failed. Only the files failed to be restored
+    /// are kept of a trash operation.
+    pub fn undo(&mut self) -> Result<Operation, String> {
+        let entry = self
+            .entries
+            .back_mut()
+            .ok_or_else(|| String::from("There is nothing to undo"))?;
+        for (path, stamp) in &entry.stamps {
+            if stamp.is_none() || FileStamp::of(path) != *stamp {
+                return Err(format!(
+                    "{} was changed since the operation, it cannot be undone",
+                    path.display()
+                ));
+            }
+        }
+        // the files added to a copied directory since the copy would be removed with it
+        if let Operation::Copy { to, .. } = &entry.operation {
+            if let Some(added) = WalkDir::new(to)
+                .into_iter()
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.into_path())
+                .find(|path| entry.stamps.iter().all(|(stamped, _)| stamped != path))
+            {
+                return Err(format!(
+                    "{} was added since the operation, it cannot be undone",
+                    added.display()
+                ));
+            }
+        }
+        undo(entry).map_err(|error| error.to_string())?;
+        Ok(self
+            .entries
+            .pop_back()
+            .map(|entry| entry.operation)
+            .unwrap())
+    }
+}
+
+/// Reverts the operation of the `entry`. The entries of a trash failed to be restored
+/// are left in the operation, so it can be tried again.
+fn undo(entry: &mut JournalEntry) -> io::Result<()> {
+    match &mut entry.operation {
+        Operation::Move { from, to } => {
+            if fs::symlink_metadata(&from).is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} already exists", from.display()),
+                ));
+            }
+            move_back(to, from)
+        }
+        // only the copied files are removed, the contents first
+        Operation::Copy { .. } => entry.stamps.iter().rev().try_for_each(|(path, _)| {
+            match fs::symlink_metadata(path)?.is_dir() {
+                true => fs::remove_dir(path),
+                false => fs::remove_file(path),
+            }
+        }),
+        Operation::NewFile { path } | Operation::Link { path } => fs::remove_file(path),
+        Operation::Pack { archive } => fs::remove_file(archive),
+        // the directories are removed only if they are still empty
+        Operation::MkDir { paths } => paths.iter().rev().try_for_each(fs::remove_dir),
+        Operation::Trash { entries } => {
+            // every entry is checked before any of them is restored
+            for trashed in entries.iter() {
+                if fs::symlink_metadata(trashed.path()).is_err() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!(
+                            "{} is not in the trash anymore",
+                            trashed.original_path.display()
+                        ),
+                    ));
+                }
+                if fs::symlink_metadata(&trashed.original_path).is_ok() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        format!("{} already exists", trashed.original_path.display()),
+                    ));
+                }
+            }
+            let count = entries.len();
+            let mut error = None;
+            entries.retain(|trashed| match trashed.restore() {
+                Ok(()) => false,
+                Err(restore_error) => {
+                    error.get_or_insert(restore_error);
+                    true
+                }
+            });
+            match error {
+                Some(error) => Err(io::Error::new(
+                    error.kind(),
+                    format!(
+                        "{} of {} files could not be restored: {}",
+                        entries.len(),
+                        count,
+                        error
+                    ),
+                )),
+                None => Ok(()),
+            }
+        }
+        Operation::MultiRename { batch } => batch
+            .undo()
+            .map(|_| ())
+            .map_err(|rename_error| rename_error.error),
+    }
+}
+
+/// Moves `from` to `to`, copying it then removing the source if they are
+/// on different file systems.
+fn move_back(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(error) if error.raw_os_error() == Some(libc::EXDEV) => {}
+        result => return result,
+    }
+    let control = TransferControl::default();
+    for entry in WalkDir::new(from) {
+        let entry = entry?;
+        let target = to.join(entry.path().strip_prefix(from).unwrap_or(entry.path()));
+        match entry.file_type() {
+            file_type if file_type.is_dir() => fs::create_dir_all(&target)?,
+            file_type if file_type.is_symlink() => copy_symlink(entry.path(), &target)?,
+            _ => copy_file(entry.path(), &target, &control, |_| {})?,
+        }
+    }
+    match fs::symlink_metadata(from)?.is_dir() {
+        true => fs::remove_dir_all(from),
+        false => fs::remove_file(from),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CreatedFiles, Journal, Operation};
+    use crate::core::{rename::rename_all, test_dir::TestDir, trash::Trash};
+    use std::{ffi::OsString, fs, path::PathBuf};
+
+    /// Makes the copy creating the `paths`, the first one is the copied file or directory.
+    fn copy(paths: &[PathBuf]) -> Operation {
+        let mut created = CreatedFiles::default();
+        paths.iter().for_each(|path| created.push(path));
+        Operation::Copy {
+            to: paths[0].clone(),
+            created,
+        }
+    }
+
+    #[test]
+    fn test_undo_operations_in_reverse_order() {
+        let dir = TestDir::new("journal-undo");
+        let mut journal = Journal::default();
+
+        fs::create_dir(dir.join("new")).unwrap();
+        journal.record(Operation::MkDir {
+            paths: vec![dir.join("new")],
+        });
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        fs::copy(dir.join("a.txt"), dir.join("new/a.txt")).unwrap();
+        journal.record(copy(&[dir.join("new/a.txt")]));
+        fs::rename(dir.join("a.txt"), dir.join("b.txt")).unwrap();
+        journal.record(Operation::Move {
+            from: dir.join("a.txt"),
+            to: dir.join("b.txt"),
+        });
+        assert_eq!(journal.entries().count(), 3);
+
+        assert!(matches!(journal.undo(), Ok(Operation::Move { .. })));
+        assert!(dir.join("a.txt").exists() && !dir.join("b.txt").exists());
+        assert!(matches!(journal.undo(), Ok(Operation::Copy { .. })));
+        assert!(!dir.join("new/a.txt").exists());
+        assert!(matches!(journal.undo(), Ok(Operation::MkDir { .. })));
+        assert!(!dir.join("new").exists());
+        assert!(journal.undo().is_err());
+    }
+
+    #[test]
+    fn test_changed_files_are_not_undone() {
+        let dir = TestDir::new("journal-changed");
+        let mut journal = Journal::default();
+        fs::write(dir.join("copy.txt"), "copied").unwrap();
+        journal.record(copy(&[dir.join("copy.txt")]));
+
+        // replaced by another file since the copy
+        fs::remove_file(dir.join("copy.txt")).unwrap();
+        fs::write(dir.join("other.txt"), "other").unwrap();
+        fs::rename(dir.join("other.txt"), dir.join("copy.txt")).unwrap();
+
+        assert!(journal.undo().is_err());
+        assert!(dir.join("copy.txt").exists());
+        assert_eq!(journal.entries().count(), 1);
+    }
+
+    #[test]
+    fn test_copied_tree_with_added_files_is_not_undone() {
+        let dir = TestDir::new("journal-tree");
+        let mut journal = Journal::default();
+        fs::create_dir_all(dir.join("copy/sub")).unwrap();
+        fs::write(dir.join("copy/sub/a.txt"), "a").unwrap();
+        journal.record(copy(&[
+            dir.join("copy"),
+            dir.join("copy/sub"),
+            dir.join("copy/sub/a.txt"),
+        ]));
+
+        fs::write(dir.join("copy/sub/added.txt"), "added").unwrap();
+        assert!(journal.undo().is_err());
+        assert!(dir.join("copy/sub/added.txt").exists());
+
+        fs::remove_file(dir.join("copy/sub/added.txt")).unwrap();
+        assert!(matches!(journal.undo(), Ok(Operation::Copy { .. })));
+        assert!(!dir.join("copy").exists());
+    }
+
+    #[test]
+    fn test_undo_trash_restores_the_trashed_entries() {
+        let dir = TestDir::new("journal-trash");
+        let mut journal = Journal::default();
+        let trash = Trash::new(dir.join(".Trash"), Some(dir.to_path_buf()));
+        fs::write(dir.join("a.txt"), "first").unwrap();
+        let earlier = trash.put(&dir.join("a.txt")).unwrap();
+        fs::write(dir.join("a.txt"), "second").unwrap();
+        let entries = vec![trash.put(&dir.join("a.txt")).unwrap()];
+        journal.record(Operation::Trash { entries });
+
+        assert!(matches!(journal.undo(), Ok(Operation::Trash { .. })));
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "second");
+        // the file trashed earlier from the same path is left in the trash
+        assert_eq!(trash.entries(), [earlier]);
+    }
+
+    #[test]
+    fn test_undo_trash_keeps_the_entries_failed_to_restore() {
+        let dir = TestDir::new("journal-trash-failed");
+        let mut journal = Journal::default();
+        let trash = Trash::new(dir.join(".Trash"), Some(dir.to_path_buf()));
+        fs::create_dir(dir.join("sub")).unwrap();
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        fs::write(dir.join("sub/b.txt"), "b").unwrap();
+        let entries = vec![
+            trash.put(&dir.join("a.txt")).unwrap(),
+            trash.put(&dir.join("sub/b.txt")).unwrap(),
+        ];
+        journal.record(Operation::Trash { entries });
+
+        // nothing is restored if an original path is taken
+        fs::write(dir.join("a.txt"), "other").unwrap();
+        assert!(journal.undo().is_err());
+        assert_eq!(trash.entries().len(), 2);
+        fs::remove_file(dir.join("a.txt")).unwrap();
+
+        // the parent directory of b.txt cannot be re-created
+        fs::remove_dir(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub"), "").unwrap();
+        assert_eq!(
+            journal.undo().unwrap_err().split(':').next(),
+            Some("1 of 2 files could not be restored")
+        );
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "a");
+        let kept = &journal.entries().next().unwrap().operation;
+        assert_eq!(
+            kept.title(),
+            format!("Trash {}", dir.join("sub/b.txt").display())
+        );
+
+        fs::remove_file(dir.join("sub")).unwrap();
+        assert!(matches!(journal.undo(), Ok(Operation::Trash { .. })));
+        assert_eq!(fs::read_to_string(dir.join("sub/b.txt")).unwrap(), "b");
+        assert!(trash.entries().is_empty());
+    }
+
+    #[test]
+    fn test_undo_multi_rename() {
+        let dir = TestDir::new("journal-multi-rename");
+        let mut journal = Journal::default();
+        fs::write(dir.join("a"), "a").unwrap();
+        fs::write(dir.join("b"), "b").unwrap();
+        let batch = rename_all(
+            &dir,
+            &[
+                (OsString::from("a"), OsString::from("b")),
+                (OsString::from("b"), OsString::from("a")),
+            ],
+        )
+        .unwrap();
+        journal.record(Operation::MultiRename { batch });
+        assert_eq!(fs::read_to_string(dir.join("a")).unwrap(), "b");
+
+        assert!(matches!(journal.undo(), Ok(Operation::MultiRename { .. })));
+        assert_eq!(fs::read_to_string(dir.join("a")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(dir.join("b")).unwrap(), "b");
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    fs, io,
+    os::unix::fs::symlink,
+    path::{Component, Path, PathBuf},
+};
+
+/// The kind of a link to create.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LinkKind {
+    Symbolic,
+    Hard,
+}
+
+/// Returns the path of `target` relative to the directory `from_dir`, both must be absolute.
+/// The paths are compared as they are, the symbolic links in them are not resolved.
+pub fn relative_path(from_dir: &Path, target: &Path) -> PathBuf {
+    let from: Vec<Component> = from_dir.components().collect();
+    let to: Vec<Component> = target.components().collect();
+    let common = from
+        .iter()
+        .zip(&to)
+        .take_while(|(from, to)| from == to)
+        .count();
+
+    let mut path: PathBuf = from[common..]
+        .iter()
+        .map(|_| Component::ParentDir)
+        .collect();
+    path.extend(&to[common..]);
+    if path.as_os_str().is_empty() {
+        path.push(Component::CurDir);
+    }
+    path
+}
+
+/// Creates a link at `link` pointing to the existing `target`.
+/// A symbolic link points to the path of `target` relative to the link's directory
+/// if `relative` is set, otherwise to its absolute path.
+pub fn create_link(target: &Path, link: &Path, kind: LinkKind, relative: bool) -> io::Result<()> {
+    match kind {
+        LinkKind::Symbolic => {
+            let link_target = match (relative, link.parent()) {
+                (true, Some(link_dir)) => relative_path(link_dir, target),
+                _ => PathBuf::from(target),
+            };
+            symlink(link_target, link)
+        }
+        LinkKind::Hard => fs::hard_link(target, link).map_err(|error| match error.raw_os_error() {
+            Some(libc::EXDEV) => io::Error::new(
+                error.kind(),
+                "A hard link cannot be created across file systems",
+            ),
+            Some(libc::EPERM) if target.is_dir() => {
+                io::Error::new(error.kind(), "A hard link cannot be created to a directory")
+            }
+            _ => error,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{create_link, relative_path, LinkKind};
+    use crate::core::test_dir::TestDir;
+    use std::{fs, path::Path};
+
+    #[test]
+    fn test_relative_path() {
+        let relative = |from: &str, to: &str| relative_path(Path::new(from), Path::new(to));
+        assert_eq!(
+            relative("/srv/app", "/srv/releases/1.2"),
+            Path::new("../releases/1.2")
+        );
+        assert_eq!(
+            relative("/srv", "/srv/releases/1.2"),
+            Path::new("releases/1.2")
+        );
+        assert_eq!(
+            relative("/srv/a/b", "/etc/hosts"),
+            Path::new("../../../etc/hosts")
+        );
+        assert_eq!(relative("/srv", "/srv"), Path::new("."));
+    }
+
+    #[test]
+    fn test_create_links() {
+        let dir = TestDir::new("link");
+        fs::create_dir_all(dir.join("releases/1.2")).unwrap();
+        fs::create_dir_all(dir.join("app")).unwrap();
+        fs::write(dir.join("releases/1.2/run"), "run").unwrap();
+
+        let current = dir.join("app/current");
+        create_link(
+            &dir.join("releases/1.2"),
+            &current,
+            LinkKind::Symbolic,
+            true,
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_link(&current).unwrap(),
+            Path::new("../releases/1.2")
+        );
+        assert_eq!(fs::read_to_string(current.join("run")).unwrap(), "run");
+
+        let absolute = dir.join("app/absolute");
+        create_link(
+            &dir.join("releases/1.2"),
+            &absolute,
+            LinkKind::Symbolic,
+            false,
+        )
+        .unwrap();
+        assert_eq!(fs::read_link(&absolute).unwrap(), dir.join("releases/1.2"));
+
+        let hard = dir.join("app/run");
+        create_link(&dir.join("releases/1.2/run"), &hard, LinkKind::Hard, false).unwrap();
+        assert_eq!(fs::read_to_string(&hard).unwrap(), "run");
+        // an existing link is not replaced
+        assert!(create_link(&dir.join("releases/1.2/run"), &hard, LinkKind::Hard, false).is_err());
+        assert!(create_link(
+            &dir.join("releases"),
+            &dir.join("dir"),
+            LinkKind::Hard,
+            false
+        )
+        .is_err());
+    }
+}
@@ -1,31 +1,90 @@
 use chrono::{DateTime, Local};
+use std::ffi::{OsStr, OsString};
 use std::fs::{self, DirEntry};
 use std::io::Error;
-use std::path::Path;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Component, Path, PathBuf};
 
 /// A structure representing one file with its metadata collected from listing files in a directory
 #[derive(Clone, Debug, PartialEq)]
 pub struct DirContent {
-    pub name: String,
+    /// The original name of the file, which is not necessarily valid UTF-8.
+    /// Use `display_name()` for rendering.
+    pub name: OsString,
+    /// True for directories and for symbolic links pointing to a directory.
     pub is_dir: bool,
     pub size: Option<u64>,
     pub date: String,
     pub attrs: String,
+    /// True if the entry itself is a symbolic link.
+    pub is_symlink: bool,
+    /// The path the symbolic link points to, as it is stored in the link.
+    pub link_target: Option<PathBuf>,
+    /// True if the entry is a symbolic link pointing to a non-existent file.
+    pub is_broken_link: bool,
+}
+
+impl DirContent {
+    /// Returns the name to display for the entry, escaped by `escape_file_name()`.
+    /// Symbolic links are displayed with their target, e.g: "name -> target".
+    pub fn display_name(&self) -> String {
+        match &self.link_target {
+            Some(target) => format!(
+                "{} -> {}",
+                escape_file_name(&self.name),
+                escape_file_name(target.as_os_str())
+            ),
+            None => escape_file_name(&self.name),
+        }
+    }
+}
+
+/// Converts a file name into a printable String.
+/// Bytes that are not valid UTF-8 are replaced with their `\xNN` hexadecimal escape sequence,
+/// and control characters (like new lines) are escaped too,
+/// so the name can be rendered without breaking the terminal.
+pub fn escape_file_name(name: &OsStr) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for chunk in name.as_bytes().utf8_chunks() {
+        for char in chunk.valid().chars() {
+            if char.is_control() {
+                escaped.extend(char.escape_default());
+            } else {
+                escaped.push(char);
+            }
+        }
+        for byte in chunk.invalid() {
+            escaped.push_str(&format!("\\x{:02X}", byte));
+        }
+    }
+    escaped
 }
 
 impl From<DirEntry> for DirContent {
     fn from(entry: DirEntry) -> Self {
-        let name = match entry.file_name().into_string() {
-            Ok(fname) => fname,
-            Err(error) => {
-                eprintln!("NOTICE: cannot read filename: {:?}", error);
-                "N/A".to_string()
-            }
+        let name = entry.file_name();
+
+        // DirEntry::metadata() does not traverse symbolic links,
+        // the metadata of the link's target is queried separately.
+        let is_symlink = entry
+            .file_type()
+            .map(|file_type| file_type.is_symlink())
+            .unwrap_or(false);
+        let (link_target, target_metadata) = if is_symlink {
+            let path = entry.path();
+            (fs::read_link(&path).ok(), Some(fs::metadata(&path)))
+        } else {
+            (None, None)
+        };
+        let is_broken_link = matches!(target_metadata, Some(Err(_)));
+        let metadata = match target_metadata {
+            Some(Ok(metadata)) => Ok(metadata),
+            _ => entry.metadata(),
         };
 
         let mut is_dir = true;
         let mut size = None;
-        let (is_dir, size, date, attrs) = match entry.metadata() {
+        let (is_dir, size, date, attrs) = match metadata {
             Ok(metadata) => {
                 if !metadata.is_dir() {
                     is_dir = false;
@@ -41,7 +100,8 @@ impl From<DirEntry> for DirContent {
                     Err(e) => {
                         eprintln!(
                             "NOTICE: cannot read last modification date for {}, error: {}",
-                            &name, e
+                            escape_file_name(&name),
+                            e
                         );
                         "N/A".to_string()
                     }
@@ -63,6 +123,9 @@ impl From<DirEntry> for DirContent {
             size,
             date,
             attrs,
+            is_symlink,
+            link_target,
+            is_broken_link,
         }
     }
 }
@@ -105,3 +168,168 @@ pub fn list_dir(dir: &Path, filter_options: &FilterOptions) -> Result<Vec<DirCon
 
     Ok(result)
 }
+
+/// Completes the last component of the `partial` path with the longest common prefix
+/// of the matching file names. A relative `partial` path is resolved against `base`.
+/// A slash is appended if the only match is a directory.
+/// Returns None if no file name matches.
+pub fn complete_path(base: &Path, partial: &str) -> Option<String> {
+    let (dir, prefix) = match partial.rfind('/') {
+        Some(index) => (&partial[..=index], &partial[index + 1..]),
+        None => ("", partial),
+    };
+    let mut matches: Vec<(String, bool)> = fs::read_dir(base.join(dir))
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let is_dir = entry.path().is_dir();
+            name.starts_with(prefix).then_some((name, is_dir))
+        })
+        .collect();
+    matches.sort();
+
+    let (first, first_is_dir) = matches.first()?;
+    let common_prefix = matches.iter().fold(first.as_str(), |common, (name, _)| {
+        let length = common
+            .char_indices()
+            .zip(name.chars())
+            .find(|((_, a), b)| a != b)
+            .map(|((index, _), _)| index)
+            .unwrap_or_else(|| common.len().min(name.len()));
+        &common[..length]
+    });
+    let mut completed = format!("{}{}", dir, common_prefix);
+    if matches.len() == 1 && *first_is_dir {
+        completed.push('/');
+    }
+    Some(completed)
+}
+
+/// Returns the absolute path the absolute `path` points to, even if it does not exist:
+/// the symbolic links of its existing part are resolved, and `.` and `..` are removed from the rest.
+pub fn real_path(path: &Path) -> PathBuf {
+    let mut real = PathBuf::from("/");
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => {
+                real.push(name);
+                // `..` after a link leads to the parent of the link's target, like in the kernel
+                if let Ok(canonical) = fs::canonicalize(&real) {
+                    real = canonical;
+                }
+            }
+            Component::ParentDir => {
+                real.pop();
+            }
+            Component::RootDir | Component::CurDir | Component::Prefix(_) => {}
+        }
+    }
+    real
+}
+
+#[cfg(test)]
+mod test {
+    use super::{complete_path, escape_file_name, list_dir, real_path, FilterOptions};
+    use crate::core::test_dir::TestDir;
+    use std::ffi::OsStr;
+    use std::fs;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::symlink;
+    use std::path::{Path, PathBuf};
+
+    fn setup(name: &str) -> TestDir {
+        let dir = TestDir::new(name);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("file.txt"), "content").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_list_dir_symlinks() {
+        let dir = setup("symlinks");
+        symlink("sub", dir.join("link_to_dir")).unwrap();
+        symlink("file.txt", dir.join("link_to_file")).unwrap();
+        symlink("missing", dir.join("broken_link")).unwrap();
+
+        let files = list_dir(&dir, &FilterOptions::default()).unwrap();
+        let find = |name: &str| files.iter().find(|file| file.name == name).unwrap();
+
+        let link_to_dir = find("link_to_dir");
+        assert!(link_to_dir.is_symlink);
+        assert!(link_to_dir.is_dir);
+        assert!(!link_to_dir.is_broken_link);
+        assert_eq!(link_to_dir.display_name(), "link_to_dir -> sub");
+
+        let link_to_file = find("link_to_file");
+        assert!(link_to_file.is_symlink);
+        assert!(!link_to_file.is_dir);
+        assert_eq!(link_to_file.size, Some(7));
+
+        let broken_link = find("broken_link");
+        assert!(broken_link.is_symlink);
+        assert!(broken_link.is_broken_link);
+        assert!(!broken_link.is_dir);
+
+        let file = find("file.txt");
+        assert!(!file.is_symlink);
+        assert_eq!(file.link_target, None);
+        assert_eq!(file.display_name(), "file.txt");
+    }
+
+    #[test]
+    fn test_list_dir_non_utf8_names() {
+        let dir = setup("non-utf8");
+        let name = OsStr::from_bytes(b"caf\xE9.txt");
+        fs::write(dir.join(name), "content").unwrap();
+
+        let files = list_dir(&dir, &FilterOptions::default()).unwrap();
+        let file = files.iter().find(|file| file.name == name).unwrap();
+
+        assert_eq!(file.display_name(), "caf\\xE9.txt");
+        assert!(dir.join(&file.name).exists());
+    }
+
+    #[test]
+    fn test_escape_file_name() {
+        assert_eq!(escape_file_name(OsStr::new("plain.txt")), "plain.txt");
+        assert_eq!(escape_file_name(OsStr::new("ékezet")), "ékezet");
+        assert_eq!(escape_file_name(OsStr::new("new\nline")), "new\\nline");
+        assert_eq!(
+            escape_file_name(OsStr::from_bytes(b"\xFF\xFEbom")),
+            "\\xFF\\xFEbom"
+        );
+    }
+
+    #[test]
+    fn test_complete_path() {
+        let dir = setup("complete");
+        fs::create_dir_all(dir.join("documents")).unwrap();
+        fs::write(dir.join("notes-2022.txt"), "").unwrap();
+        fs::write(dir.join("notes-2023.txt"), "").unwrap();
+
+        assert_eq!(complete_path(&dir, "doc"), Some(String::from("documents/")));
+        assert_eq!(complete_path(&dir, "no"), Some(String::from("notes-202")));
+        assert_eq!(complete_path(&dir, "x"), None);
+        let absolute = format!("{}/doc", dir.display());
+        assert_eq!(
+            complete_path(Path::new("/"), &absolute),
+            Some(format!("{}/documents/", dir.display()))
+        );
+    }
+
+    #[test]
+    fn test_real_path() {
+        let dir = setup("real-path");
+        fs::create_dir_all(dir.join("a/b")).unwrap();
+        symlink(dir.join("a/b"), dir.join("link")).unwrap();
+
+        assert_eq!(
+            real_path(&dir.join("a/./b/../b/new/x")),
+            dir.join("a/b/new/x")
+        );
+        assert_eq!(real_path(&dir.join("link/new")), dir.join("a/b/new"));
+        assert_eq!(real_path(&dir.join("link/../c")), dir.join("a/c"));
+        assert_eq!(real_path(Path::new("/..")), PathBuf::from("/"));
+    }
+}
@@ -1,5 +1,18 @@
+pub mod archive;
+pub mod attributes;
 pub mod config;
+pub mod conflict;
+pub mod copy;
+pub mod journal;
+pub mod link;
 pub mod list_dir;
+pub mod pack;
+pub mod rename;
+pub mod template;
+#[cfg(test)]
+pub mod test_dir;
+pub mod trash;
+pub mod vfs;
 
 pub fn calculate_progress_percentage(partial_bytes: u64, total_bytes: u64) -> u64 {
     if partial_bytes != 0 && total_bytes != 0 {
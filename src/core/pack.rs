@@ -0,0 +1,427 @@
+use super::{archive::ArchiveFormat, copy::TransferControl};
+use chrono::{DateTime, Datelike, Local, Timelike};
+use std::{
+    convert::TryFrom,
+    fs::{File, Metadata},
+    io::{self, BufWriter, PipeWriter, Read, Write},
+    os::unix::fs::MetadataExt,
+    path::{Component, Path, PathBuf},
+    thread::{self, JoinHandle},
+};
+
+/// The highest compression level, 0 stores the files without compressing them.
+pub const MAX_LEVEL: u32 = 9;
+
+/// How to create a new archive.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PackOptions {
+    pub format: ArchiveFormat,
+    /// The compression level from 0 to `MAX_LEVEL`, ignored by the plain `.tar` format.
+    /// Zstandard compresses on the fastest level, unless the level is 0.
+    pub level: u32,
+    /// Store the full paths of the files instead of their paths
+    /// relative to the directory they are packed from.
+    pub absolute_paths: bool,
+}
+
+/// Returns the name of the `path` stored in an archive: relative to `base_dir`,
+/// or the full path without the leading `/` if `absolute_paths` is set, like tar does.
+pub fn member_name(path: &Path, base_dir: &Path, absolute_paths: bool) -> PathBuf {
+    let path = match absolute_paths {
+        true => path,
+        false => path.strip_prefix(base_dir).unwrap_or(path),
+    };
+    path.components()
+        .filter(|component| !matches!(component, Component::RootDir | Component::Prefix(_)))
+        .collect()
+}
+
+/// Writes a new archive member by member.
+/// Nothing is readable until the archive is completed by `finish()`.
+pub struct ArchiveWriter {
+    inner: Writer,
+    level: u32,
+}
+
+enum Writer {
+    Zip(zip::ZipWriter<BufWriter<File>>),
+    Tar(tar::Builder<Compressor>),
+}
+
+impl ArchiveWriter {
+    /// Creates the `archive` file, an existing file is overwritten.
+    pub fn create(archive: &Path, options: PackOptions) -> io::Result<Self> {
+        let file = BufWriter::new(File::create(archive)?);
+        let level = options.level.min(MAX_LEVEL);
+        let compressor = match options.format {
+            ArchiveFormat::Zip => {
+                return Ok(ArchiveWriter {
+                    inner: Writer::Zip(zip::ZipWriter::new(file)),
+                    level,
+                })
+            }
+            ArchiveFormat::Tar => Compressor::Plain(file),
+            ArchiveFormat::TarGz => Compressor::Gz(flate2::write::GzEncoder::new(
+                file,
+                flate2::Compression::new(level),
+            )),
+            ArchiveFormat::TarXz => Compressor::Xz(Box::new(lzma_rust2::XzWriter::new(
+                file,
+                lzma_rust2::XzOptions::with_preset(level),
+            )?)),
+            ArchiveFormat::TarZst => Compressor::Zst(ZstdWriter::new(file, level)?),
+        };
+        Ok(ArchiveWriter {
+            inner: Writer::Tar(tar::Builder::new(compressor)),
+            level,
+        })
+    }
+
+    /// Adds an empty directory `name` with the permissions and the modification time of `metadata`.
+    pub fn add_dir(&mut self, name: &Path, metadata: &Metadata) -> io::Result<()> {
+        match &mut self.inner {
+            Writer::Zip(zip) => Ok(zip.add_directory(zip_name(name), zip_options(metadata))?),
+            Writer::Tar(builder) => {
+                let mut header = tar_header(metadata);
+                builder.append_data(&mut header, name, io::empty())
+            }
+        }
+    }
+
+    /// Adds a symbolic link `name` pointing to `target`.
+    pub fn add_symlink(
+        &mut self,
+        name: &Path,
+        target: &Path,
+        metadata: &Metadata,
+    ) -> io::Result<()> {
+        match &mut self.inner {
+            Writer::Zip(zip) => Ok(zip.add_symlink(
+                zip_name(name),
+                target.to_string_lossy(),
+                zip_options(metadata),
+            )?),
+            Writer::Tar(builder) => {
+                let mut header = tar_header(metadata);
+                builder.append_link(&mut header, name, target)
+            }
+        }
+    }
+
+    /// Adds the file `name` with its content read from `reader`,
+    /// which must be exactly as long as `metadata` tells.
+    /// The archive is unusable if this fails.
+    pub fn add_file(
+        &mut self,
+        name: &Path,
+        metadata: &Metadata,
+        reader: &mut dyn Read,
+    ) -> io::Result<()> {
+        let mut reader = CountingReader {
+            inner: reader.take(metadata.len()),
+            read: 0,
+        };
+        match &mut self.inner {
+            Writer::Zip(zip) => {
+                let options = match self.level {
+                    0 => zip_options(metadata),
+                    level => zip_options(metadata)
+                        .compression_method(zip::CompressionMethod::Deflated)
+                        .compression_level(Some(level as i32)),
+                };
+                zip.start_file(
+                    zip_name(name),
+                    options.large_file(metadata.len() > u32::MAX as u64),
+                )?;
+                io::copy(&mut reader, zip)?;
+            }
+            Writer::Tar(builder) => {
+                let mut header = tar_header(metadata);
+                builder.append_data(&mut header, name, &mut reader)?;
+            }
+        }
+        match reader.read == metadata.len() {
+            true => Ok(()),
+            false => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "The file was changed while it was packed",
+            )),
+        }
+    }
+
+    /// Completes the archive.
+    pub fn finish(self) -> io::Result<()> {
+        let file = match self.inner {
+            Writer::Zip(mut zip) => zip.finish()?,
+            Writer::Tar(builder) => builder.into_inner()?.finish()?,
+        };
+        file.into_inner()
+            .map_err(io::IntoInnerError::into_error)?
+            .sync_all()
+    }
+}
+
+/// Reads the content of a packed file while the transfer is not cancelled,
+/// calling `progress` with the number of the bytes read so far after every read.
+/// The reading waits while the transfer is paused.
+pub struct PackReader<'a, R, F> {
+    inner: R,
+    control: &'a TransferControl,
+    progress: F,
+    read: u64,
+}
+
+impl<'a, R: Read, F: FnMut(u64)> PackReader<'a, R, F> {
+    pub fn new(inner: R, control: &'a TransferControl, progress: F) -> Self {
+        PackReader {
+            inner,
+            control,
+            progress,
+            read: 0,
+        }
+    }
+}
+
+impl<R: Read, F: FnMut(u64)> Read for PackReader<'_, R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.control.proceed() {
+            return Err(io::Error::other("The transfer was cancelled"));
+        }
+        let read = self.inner.read(buf)?;
+        self.read += read as u64;
+        (self.progress)(self.read);
+        Ok(read)
+    }
+}
+
+struct CountingReader<R> {
+    inner: R,
+    read: u64,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.read += read as u64;
+        Ok(read)
+    }
+}
+
+/// Compresses the tar stream written to it.
+enum Compressor {
+    Plain(BufWriter<File>),
+    Gz(flate2::write::GzEncoder<BufWriter<File>>),
+    Xz(Box<lzma_rust2::XzWriter<BufWriter<File>>>),
+    Zst(ZstdWriter),
+}
+
+impl Compressor {
+    fn finish(self) -> io::Result<BufWriter<File>> {
+        match self {
+            Compressor::Plain(file) => Ok(file),
+            Compressor::Gz(encoder) => encoder.finish(),
+            Compressor::Xz(writer) => Ok(writer.finish()?),
+            Compressor::Zst(writer) => writer.finish(),
+        }
+    }
+}
+
+impl Write for Compressor {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Compressor::Plain(file) => file.write(buf),
+            Compressor::Gz(encoder) => encoder.write(buf),
+            Compressor::Xz(writer) => writer.write(buf),
+            Compressor::Zst(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Compressor::Plain(file) => file.flush(),
+            Compressor::Gz(encoder) => encoder.flush(),
+            Compressor::Xz(writer) => writer.flush(),
+            Compressor::Zst(writer) => writer.flush(),
+        }
+    }
+}
+
+/// The Zstandard encoder reads the data to compress, it is fed through a pipe
+/// and it compresses on its own thread.
+struct ZstdWriter {
+    pipe: PipeWriter,
+    encoder: JoinHandle<io::Result<BufWriter<File>>>,
+}
+
+impl ZstdWriter {
+    fn new(file: BufWriter<File>, level: u32) -> io::Result<Self> {
+        let level = match level {
+            0 => ruzstd::encoding::CompressionLevel::Uncompressed,
+            _ => ruzstd::encoding::CompressionLevel::Fastest,
+        };
+        let (reader, pipe) = io::pipe()?;
+        let encoder = thread::spawn(move || {
+            let mut file = FailedWriter {
+                inner: file,
+                error: None,
+            };
+            ruzstd::encoding::compress(reader, &mut file, level);
+            match file.error {
+                Some(error) => Err(error),
+                None => Ok(file.inner),
+            }
+        });
+        Ok(ZstdWriter { pipe, encoder })
+    }
+
+    fn finish(self) -> io::Result<BufWriter<File>> {
+        // the encoder completes the frame when the pipe is closed
+        drop(self.pipe);
+        self.encoder
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("The Zstandard encoder failed")))
+    }
+}
+
+impl Write for ZstdWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pipe.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.pipe.flush()
+    }
+}
+
+/// Keeps the first error of the writes instead of failing them,
+/// the Zstandard encoder panics on a failed write.
+struct FailedWriter<W> {
+    inner: W,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> Write for FailedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.error.is_none() {
+            if let Err(error) = self.inner.write_all(buf) {
+                self.error = Some(error);
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.error.is_none() {
+            if let Err(error) = self.inner.flush() {
+                self.error = Some(error);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn tar_header(metadata: &Metadata) -> tar::Header {
+    let mut header = tar::Header::new_gnu();
+    header.set_metadata(metadata);
+    header
+}
+
+/// Zip stores the paths as strings with `/` separators.
+fn zip_name(name: &Path) -> String {
+    name.to_string_lossy().to_string()
+}
+
+fn zip_options(metadata: &Metadata) -> zip::write::FileOptions {
+    let options = zip::write::FileOptions::default()
+        .compression_method(zip::CompressionMethod::Stored)
+        .unix_permissions(metadata.mode());
+    // zip stores the local time, from 1980 on
+    let modified = metadata
+        .modified()
+        .ok()
+        .map(DateTime::<Local>::from)
+        .and_then(|modified| {
+            zip::DateTime::from_date_and_time(
+                u16::try_from(modified.year()).ok()?,
+                modified.month() as u8,
+                modified.day() as u8,
+                modified.hour() as u8,
+                modified.minute() as u8,
+                modified.second() as u8,
+            )
+            .ok()
+        });
+    match modified {
+        Some(modified) => options.last_modified_time(modified),
+        None => options,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{member_name, ArchiveWriter, PackOptions};
+    use crate::core::{
+        archive::{ArchiveFormat, ArchiveIndex, EntryKind},
+        test_dir::TestDir,
+    };
+    use std::{
+        fs::{self, File},
+        os::unix::fs::symlink,
+        path::Path,
+    };
+
+    #[test]
+    fn test_member_name() {
+        let file = Path::new("/home/user/src/main.rs");
+        let base_dir = Path::new("/home/user");
+        assert_eq!(member_name(file, base_dir, false), Path::new("src/main.rs"));
+        assert_eq!(
+            member_name(file, base_dir, true),
+            Path::new("home/user/src/main.rs")
+        );
+    }
+
+    #[test]
+    fn test_pack_every_format() {
+        let dir = TestDir::new("pack");
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/main.rs"), "fn main() {}\n".repeat(100)).unwrap();
+        symlink("main.rs", dir.join("src/link")).unwrap();
+
+        for format in ArchiveFormat::ALL {
+            let archive = dir.join(format!("packed{}", format.extension()));
+            let options = PackOptions {
+                format,
+                level: 6,
+                absolute_paths: false,
+            };
+            let mut writer = ArchiveWriter::create(&archive, options).unwrap();
+            let metadata = fs::symlink_metadata(dir.join("src")).unwrap();
+            writer.add_dir(Path::new("src"), &metadata).unwrap();
+            let metadata = fs::metadata(dir.join("src/main.rs")).unwrap();
+            let mut file = File::open(dir.join("src/main.rs")).unwrap();
+            writer
+                .add_file(Path::new("src/main.rs"), &metadata, &mut file)
+                .unwrap();
+            let metadata = fs::symlink_metadata(dir.join("src/link")).unwrap();
+            writer
+                .add_symlink(Path::new("src/link"), Path::new("main.rs"), &metadata)
+                .unwrap();
+            writer.finish().unwrap();
+
+            let index = ArchiveIndex::open(&archive).unwrap();
+            assert!(index.is_dir(Path::new("src")), "{:?}", format);
+            assert_eq!(
+                index.entry(Path::new("src/link")).unwrap().kind,
+                EntryKind::Symlink("main.rs".into())
+            );
+            let mut content = String::new();
+            index
+                .read_member(Path::new("src/main.rs"), |reader| {
+                    reader.read_to_string(&mut content).map(|_| ())
+                })
+                .unwrap();
+            assert_eq!(content, "fn main() {}\n".repeat(100), "{:?}", format);
+        }
+    }
+}
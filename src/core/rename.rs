@@ -0,0 +1,569 @@
+use super::list_dir::escape_file_name;
+use chrono::{DateTime, Local};
+use regex::Regex;
+use std::{
+    collections::HashSet,
+    ffi::{OsStr, OsString},
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Prefix of the temporary names used for breaking rename cycles.
+const TEMPORARY_NAME_PREFIX: &str = ".twc-rename-";
+
+/// Checks that `name` can be used as a file name.
+pub fn validate_file_name(name: &OsStr) -> Result<(), String> {
+    if name.is_empty() {
+        return Err(String::from("The name cannot be empty"));
+    }
+    if name == "." || name == ".." {
+        return Err(format!("Invalid name: {}", escape_file_name(name)));
+    }
+    if name.to_string_lossy().contains(['/', '\0']) {
+        return Err(format!(
+            "The name cannot contain '/': {}",
+            escape_file_name(name)
+        ));
+    }
+    Ok(())
+}
+
+/// Case conversion applied on the new names.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CaseConversion {
+    #[default]
+    Unchanged,
+    Upper,
+    Lower,
+    /// Capitalizes the first letter of every word.
+    Title,
+}
+
+impl CaseConversion {
+    fn apply(&self, name: &str) -> String {
+        match self {
+            CaseConversion::Unchanged => name.to_string(),
+            CaseConversion::Upper => name.to_uppercase(),
+            CaseConversion::Lower => name.to_lowercase(),
+            CaseConversion::Title => {
+                let mut title = String::with_capacity(name.len());
+                let mut word_start = true;
+                for char in name.chars() {
+                    if word_start {
+                        title.extend(char.to_uppercase());
+                    } else {
+                        title.extend(char.to_lowercase());
+                    }
+                    word_start = !char.is_alphanumeric();
+                }
+                title
+            }
+        }
+    }
+}
+
+/// The widest counter, a `u64` has at most 20 digits and more would only pad it with zeros.
+pub const MAX_COUNTER_WIDTH: usize = 20;
+
+/// Describes how the new names are made from the old ones.
+///
+/// The name and the extension templates can contain the following placeholders:
+/// * `[N]`: the name of the file without the extension
+/// * `[E]`: the extension of the file
+/// * `[C]`: the counter, starting from `counter_start` increased by `counter_step` for every file,
+///   padded with zeros to `counter_width` digits
+/// * `[YMD]`: the last modification date of the file
+///
+/// The matches of the `search` regular expression will be replaced with `replace` in the result,
+/// which can refer to the capture groups as `$1` or `${name}`, then the case conversion is applied.
+#[derive(Clone, Debug)]
+pub struct RenameRule {
+    pub name_template: String,
+    pub extension_template: String,
+    pub search: Option<Regex>,
+    pub replace: String,
+    pub case: CaseConversion,
+    pub counter_start: u64,
+    pub counter_step: u64,
+    pub counter_width: usize,
+}
+
+impl Default for RenameRule {
+    fn default() -> Self {
+        RenameRule {
+            name_template: String::from("[N]"),
+            extension_template: String::from("[E]"),
+            search: None,
+            replace: String::new(),
+            case: CaseConversion::Unchanged,
+            counter_start: 1,
+            counter_step: 1,
+            counter_width: 1,
+        }
+    }
+}
+
+impl RenameRule {
+    /// Compiles the search `pattern` of a rule, an empty pattern does not search.
+    pub fn search_pattern(pattern: &str) -> Result<Option<Regex>, String> {
+        if pattern.is_empty() {
+            return Ok(None);
+        }
+        Regex::new(pattern).map(Some).map_err(|error| {
+            // the last line of a syntax error describes it, the others point to its position
+            let message = error.to_string();
+            format!(
+                "Invalid search pattern: {}",
+                message
+                    .lines()
+                    .last()
+                    .unwrap_or_default()
+                    .trim_start_matches("error: ")
+            )
+        })
+    }
+
+    /// Makes the new name of the `index`th file.
+    /// Returns an error if the counter of the file does not fit in a `u64`.
+    pub fn new_name(&self, file: &RenameSource, index: usize) -> Result<String, String> {
+        let path = Path::new(&file.name);
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy())
+            .unwrap_or_default();
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy())
+            .unwrap_or_default();
+        let counter = match self.uses_counter() {
+            true => {
+                let counter = (index as u64)
+                    .checked_mul(self.counter_step)
+                    .and_then(|offset| offset.checked_add(self.counter_start))
+                    .ok_or_else(|| String::from("Counter overflow"))?;
+                format!("{:0width$}", counter, width = self.counter_width)
+            }
+            false => String::new(),
+        };
+        let date = file
+            .modified
+            .map(|modified| {
+                DateTime::<Local>::from(modified)
+                    .format("%Y-%m-%d")
+                    .to_string()
+            })
+            .unwrap_or_default();
+        let fill = |template: &str| {
+            template
+                .replace("[N]", &name)
+                .replace("[E]", &extension)
+                .replace("[C]", &counter)
+                .replace("[YMD]", &date)
+        };
+
+        let mut new_name = fill(&self.name_template);
+        let new_extension = fill(&self.extension_template);
+        if !new_extension.is_empty() {
+            new_name.push('.');
+            new_name.push_str(&new_extension);
+        }
+        if let Some(search) = &self.search {
+            new_name = search
+                .replace_all(&new_name, self.replace.as_str())
+                .into_owned();
+        }
+        Ok(self.case.apply(&new_name))
+    }
+
+    fn uses_counter(&self) -> bool {
+        self.name_template.contains("[C]") || self.extension_template.contains("[C]")
+    }
+}
+
+/// A file to be renamed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenameSource {
+    pub name: OsString,
+    pub modified: Option<SystemTime>,
+}
+
+impl RenameSource {
+    /// Reads the properties of the file on `path` used by the placeholders.
+    pub fn from_path(path: &Path) -> Self {
+        RenameSource {
+            name: path.file_name().unwrap_or_default().to_os_string(),
+            modified: fs::symlink_metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok(),
+        }
+    }
+}
+
+/// The old and the new name of a file, and the reason why it cannot be renamed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlannedRename {
+    pub from: OsString,
+    pub to: OsString,
+    pub conflict: Option<String>,
+}
+
+impl PlannedRename {
+    /// Returns true if the name of the file does not change.
+    pub fn is_unchanged(&self) -> bool {
+        self.from == self.to
+    }
+}
+
+/// Makes the new names of the `files` found in the `dir` directory by the `rule`,
+/// and checks them for invalid names, duplicates and collisions with the other files of the directory.
+pub fn plan_renames(dir: &Path, files: &[RenameSource], rule: &RenameRule) -> Vec<PlannedRename> {
+    let mut planned: Vec<PlannedRename> = files
+        .iter()
+        .enumerate()
+        .map(|(index, file)| match rule.new_name(file, index) {
+            Ok(new_name) => {
+                // the lossy name of a non UTF-8 file is kept as it is
+                let to = match new_name == file.name.to_string_lossy() {
+                    true => file.name.clone(),
+                    false => OsString::from(new_name),
+                };
+                PlannedRename {
+                    from: file.name.clone(),
+                    to,
+                    conflict: None,
+                }
+            }
+            Err(message) => PlannedRename {
+                from: file.name.clone(),
+                to: file.name.clone(),
+                conflict: Some(message),
+            },
+        })
+        .collect();
+
+    let sources: HashSet<OsString> = files.iter().map(|file| file.name.clone()).collect();
+    let mut targets = HashSet::new();
+    for rename in planned
+        .iter_mut()
+        .filter(|rename| rename.conflict.is_none())
+    {
+        rename.conflict = if let Err(message) = validate_file_name(&rename.to) {
+            Some(message)
+        } else if !targets.insert(rename.to.clone()) {
+            Some(String::from("Duplicate name"))
+        } else if !sources.contains(&rename.to)
+            && fs::symlink_metadata(dir.join(&rename.to)).is_ok()
+        {
+            Some(String::from("Already exists"))
+        } else {
+            None
+        };
+    }
+    planned
+}
+
+/// The renames executed in a directory, which can be reverted.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RenameBatch {
+    dir: PathBuf,
+    renames: Vec<(OsString, OsString)>,
+}
+
+impl RenameBatch {
+    pub fn is_empty(&self) -> bool {
+        self.renames.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.renames.len()
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the paths of the renamed files.
+    pub fn renamed_paths(&self) -> Vec<PathBuf> {
+        self.renames
+            .iter()
+            .map(|(_, to)| self.dir.join(to))
+            .collect()
+    }
+
+    /// Renames the files back to their original names.
+    /// Nothing is renamed if an original name was taken by an other file since.
+    pub fn undo(&self) -> Result<RenameBatch, RenameError> {
+        let reverted: Vec<(OsString, OsString)> = self
+            .renames
+            .iter()
+            .map(|(from, to)| (to.clone(), from.clone()))
+            .collect();
+        if let Some((_, taken)) = reverted.iter().find(|(_, original)| {
+            !reverted.iter().any(|(current, _)| current == original)
+                && fs::symlink_metadata(self.dir.join(original)).is_ok()
+        }) {
+            return Err(RenameError {
+                completed: RenameBatch {
+                    dir: self.dir.clone(),
+                    renames: Vec::new(),
+                },
+                error: io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} already exists", self.dir.join(taken).display()),
+                ),
+            });
+        }
+        rename_all(&self.dir, &reverted)
+    }
+}
+
+/// An error occurred during renaming a batch of files.
+#[derive(Debug)]
+pub struct RenameError {
+    /// The renames executed before the error.
+    pub completed: RenameBatch,
+    pub error: io::Error,
+}
+
+/// Renames the files in the `dir` directory by the `(from, to)` pairs.
+///
+/// The files are renamed in an order which does not overwrite a file before it was renamed itself,
+/// so names can be swapped or rotated: cycles are broken by a temporary name.
+/// The targets must be distinct, and a target must not exist unless it is renamed too.
+pub fn rename_all(
+    dir: &Path,
+    renames: &[(OsString, OsString)],
+) -> Result<RenameBatch, RenameError> {
+    let mut pending: Vec<(OsString, OsString, OsString)> = renames
+        .iter()
+        .filter(|(from, to)| from != to)
+        .map(|(from, to)| (from.clone(), from.clone(), to.clone()))
+        .collect();
+    let mut completed = RenameBatch {
+        dir: PathBuf::from(dir),
+        renames: Vec::new(),
+    };
+    let mut temporary_names = 0;
+
+    while !pending.is_empty() {
+        let free = pending
+            .iter()
+            .position(|(_, _, to)| !pending.iter().any(|(_, current, _)| current == to));
+        let result = match free {
+            Some(index) => {
+                let (original, current, to) = pending.remove(index);
+                fs::rename(dir.join(&current), dir.join(&to)).map(|_| {
+                    completed.renames.push((original, to));
+                })
+            }
+            // every remaining target is occupied by an other pending file: move one out of the way
+            None => {
+                let temporary_name = loop {
+                    temporary_names += 1;
+                    let name = OsString::from(format!(
+                        "{}{}-{}",
+                        TEMPORARY_NAME_PREFIX,
+                        std::process::id(),
+                        temporary_names
+                    ));
+                    if fs::symlink_metadata(dir.join(&name)).is_err() {
+                        break name;
+                    }
+                };
+                let current = &mut pending[0].1;
+                fs::rename(dir.join(&*current), dir.join(&temporary_name))
+                    .map(|_| *current = temporary_name)
+            }
+        };
+        if let Err(error) = result {
+            return Err(RenameError { completed, error });
+        }
+    }
+    Ok(completed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        plan_renames, rename_all, validate_file_name, CaseConversion, RenameRule, RenameSource,
+    };
+    use crate::core::test_dir::TestDir;
+    use std::{
+        ffi::{OsStr, OsString},
+        fs,
+    };
+
+    fn source(name: &str) -> RenameSource {
+        RenameSource {
+            name: OsString::from(name),
+            modified: None,
+        }
+    }
+
+    #[test]
+    fn test_validate_file_name() {
+        assert!(validate_file_name(OsStr::new("")).is_err());
+        assert!(validate_file_name(OsStr::new("..")).is_err());
+        assert!(validate_file_name(OsStr::new("a/b")).is_err());
+        assert!(validate_file_name(OsStr::new(".hidden")).is_ok());
+    }
+
+    #[test]
+    fn test_new_name_placeholders() {
+        let rule = RenameRule {
+            name_template: String::from("photo_[C]_[N]"),
+            counter_start: 8,
+            counter_step: 2,
+            counter_width: 3,
+            ..RenameRule::default()
+        };
+        assert_eq!(
+            rule.new_name(&source("img.jpg"), 0).unwrap(),
+            "photo_008_img.jpg"
+        );
+        assert_eq!(
+            rule.new_name(&source("img.jpg"), 2).unwrap(),
+            "photo_012_img.jpg"
+        );
+        assert_eq!(
+            rule.new_name(&source("README"), 0).unwrap(),
+            "photo_008_README"
+        );
+    }
+
+    #[test]
+    fn test_counter_overflow() {
+        let rule = RenameRule {
+            name_template: String::from("[N]_[C]"),
+            counter_start: u64::MAX - 1,
+            ..RenameRule::default()
+        };
+        assert_eq!(
+            rule.new_name(&source("a.txt"), 1).unwrap(),
+            format!("a_{}.txt", u64::MAX)
+        );
+        assert_eq!(
+            rule.new_name(&source("a.txt"), 2).unwrap_err(),
+            "Counter overflow"
+        );
+
+        // the counter is not made if it is not used
+        let rule = RenameRule {
+            counter_start: 0,
+            counter_step: u64::MAX,
+            ..RenameRule::default()
+        };
+        assert_eq!(rule.new_name(&source("a.txt"), 2).unwrap(), "a.txt");
+
+        let dir = TestDir::new("rename-counter-overflow");
+        let rule = RenameRule {
+            name_template: String::from("[C]"),
+            ..rule
+        };
+        let files = [source("a.txt"), source("b.txt"), source("c.txt")];
+        let planned = plan_renames(&dir, &files, &rule);
+        assert_eq!(planned[1].conflict, None);
+        assert_eq!(planned[2].conflict, Some(String::from("Counter overflow")));
+    }
+
+    #[test]
+    fn test_new_name_search_replace_and_case() {
+        let rule = RenameRule {
+            search: RenameRule::search_pattern("_").unwrap(),
+            replace: String::from(" "),
+            case: CaseConversion::Title,
+            ..RenameRule::default()
+        };
+        assert_eq!(
+            rule.new_name(&source("my_HOLIDAY_trip.txt"), 0).unwrap(),
+            "My Holiday Trip.Txt"
+        );
+
+        let rule = RenameRule {
+            extension_template: String::from("[E]"),
+            case: CaseConversion::Lower,
+            ..RenameRule::default()
+        };
+        assert_eq!(rule.new_name(&source("IMG.JPG"), 0).unwrap(), "img.jpg");
+    }
+
+    #[test]
+    fn test_new_name_regex_captures() {
+        let rule = RenameRule {
+            search: RenameRule::search_pattern(r"^(\w+)-(\d+)").unwrap(),
+            replace: String::from("${2}_$1"),
+            ..RenameRule::default()
+        };
+        assert_eq!(
+            rule.new_name(&source("scan-042.png"), 0).unwrap(),
+            "042_scan.png"
+        );
+        assert_eq!(rule.new_name(&source("notes.txt"), 0).unwrap(), "notes.txt");
+
+        assert!(matches!(RenameRule::search_pattern(""), Ok(None)));
+        assert_eq!(
+            RenameRule::search_pattern("(a").unwrap_err(),
+            "Invalid search pattern: unclosed group"
+        );
+    }
+
+    #[test]
+    fn test_plan_renames_flags_conflicts() {
+        let dir = TestDir::new("rename-plan-renames");
+        fs::write(dir.join("a.txt"), "").unwrap();
+        fs::write(dir.join("b.txt"), "").unwrap();
+        fs::write(dir.join("existing.txt"), "").unwrap();
+
+        let rule = RenameRule {
+            name_template: String::from("existing"),
+            ..RenameRule::default()
+        };
+        let planned = plan_renames(&dir, &[source("a.txt"), source("b.txt")], &rule);
+        assert_eq!(planned[0].conflict, Some(String::from("Already exists")));
+        assert_eq!(planned[1].conflict, Some(String::from("Duplicate name")));
+
+        let rule = RenameRule {
+            name_template: String::from("[N]x"),
+            ..RenameRule::default()
+        };
+        let planned = plan_renames(&dir, &[source("a.txt"), source("b.txt")], &rule);
+        assert!(planned.iter().all(|rename| rename.conflict.is_none()));
+    }
+
+    #[test]
+    fn test_rename_all_swaps_and_undo() {
+        let dir = TestDir::new("rename-all");
+        fs::write(dir.join("a"), "a").unwrap();
+        fs::write(dir.join("b"), "b").unwrap();
+        fs::write(dir.join("c"), "c").unwrap();
+
+        // a -> b -> c -> a cycle
+        let renames = vec![
+            (OsString::from("a"), OsString::from("b")),
+            (OsString::from("b"), OsString::from("c")),
+            (OsString::from("c"), OsString::from("a")),
+        ];
+        let batch = rename_all(&dir, &renames).unwrap();
+        assert_eq!(batch.len(), 3);
+        assert_eq!(fs::read_to_string(dir.join("b")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(dir.join("c")).unwrap(), "b");
+        assert_eq!(fs::read_to_string(dir.join("a")).unwrap(), "c");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+
+        fs::write(dir.join("d"), "d").unwrap();
+        let renamed = rename_all(&dir, &[(OsString::from("d"), OsString::from("e"))]).unwrap();
+        fs::write(dir.join("d"), "new").unwrap();
+        // the original name was taken since the rename
+        assert!(renamed.undo().is_err());
+        assert_eq!(fs::read_to_string(dir.join("d")).unwrap(), "new");
+        assert_eq!(fs::read_to_string(dir.join("e")).unwrap(), "d");
+        fs::remove_file(dir.join("d")).unwrap();
+        fs::remove_file(dir.join("e")).unwrap();
+
+        batch.undo().unwrap();
+        assert_eq!(fs::read_to_string(dir.join("a")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(dir.join("b")).unwrap(), "b");
+        assert_eq!(fs::read_to_string(dir.join("c")).unwrap(), "c");
+    }
+}
@@ -0,0 +1,147 @@
+use super::config::CONFIG_DIR;
+use chrono::{Local, NaiveDate};
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    os::unix::fs::{OpenOptionsExt, PermissionsExt},
+    path::{Path, PathBuf},
+};
+
+/// The directory of the templates in the configuration directory.
+const TEMPLATES_DIR: &str = "templates";
+
+/// A file the new files can be created from.
+/// The placeholders in its content are substituted, see `fill_placeholders()`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template {
+    pub name: String,
+    path: PathBuf,
+}
+
+impl Template {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+        Template {
+            name: path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            path: PathBuf::from(path),
+        }
+    }
+}
+
+/// Returns the templates directory: `~/.config/twc/templates`.
+pub fn templates_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(CONFIG_DIR).join(TEMPLATES_DIR))
+}
+
+/// Lists the templates in `dir` sorted by name, an empty list if it does not exist.
+pub fn list_templates(dir: &Path) -> Vec<Template> {
+    let mut templates: Vec<Template> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| entry.path().is_file())
+                .map(|entry| Template::new(entry.path()))
+                .collect()
+        })
+        .unwrap_or_default();
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    templates
+}
+
+/// Substitutes the placeholders of a template:
+/// * `{name}`: the name of the new file
+/// * `{stem}`: the name of the new file without its extension
+/// * `{date}`: the current date as YYYY-MM-DD
+/// * `{year}`: the current year
+pub fn fill_placeholders(content: &str, file_name: &str, date: NaiveDate) -> String {
+    let stem = Path::new(file_name)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    content
+        .replace("{name}", file_name)
+        .replace("{stem}", &stem)
+        .replace("{date}", &date.format("%Y-%m-%d").to_string())
+        .replace("{year}", &date.format("%Y").to_string())
+}
+
+/// Creates the new file at `path`, failing if it exists.
+/// It is empty, or it is filled from the `template` keeping its permissions,
+/// e.g. a shell script stays executable.
+/// A template which is not valid UTF-8 is copied without substituting the placeholders.
+pub fn create_file(path: &Path, template: Option<&Template>) -> io::Result<()> {
+    let (content, mode) = match template {
+        Some(template) => {
+            let content = fs::read(&template.path)?;
+            let mode = fs::metadata(&template.path)?.permissions().mode();
+            let content = match String::from_utf8(content) {
+                Ok(text) => {
+                    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+                    fill_placeholders(&text, &file_name, Local::now().date_naive()).into_bytes()
+                }
+                Err(error) => error.into_bytes(),
+            };
+            (content, mode)
+        }
+        None => (Vec::new(), 0o666),
+    };
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(mode & 0o7777)
+        .open(path)?
+        .write_all(&content)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{create_file, fill_placeholders, list_templates};
+    use crate::core::test_dir::TestDir;
+    use chrono::NaiveDate;
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    #[test]
+    fn test_fill_placeholders() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 9).unwrap();
+        assert_eq!(
+            fill_placeholders("// {name}: {stem} {date} (c) {year}", "main.rs", date),
+            "// main.rs: main 2024-03-09 (c) 2024"
+        );
+    }
+
+    #[test]
+    fn test_create_file_from_template() {
+        let dir = TestDir::new("template");
+        fs::create_dir_all(dir.join("templates")).unwrap();
+        fs::write(dir.join("templates/script.sh"), "#!/bin/sh\n# {name}\n").unwrap();
+        fs::set_permissions(
+            dir.join("templates/script.sh"),
+            fs::Permissions::from_mode(0o755),
+        )
+        .unwrap();
+
+        let templates = list_templates(&dir.join("templates"));
+        assert_eq!(templates.len(), 1);
+        assert_eq!(templates[0].name, "script.sh");
+
+        create_file(&dir.join("run.sh"), templates.first()).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("run.sh")).unwrap(),
+            "#!/bin/sh\n# run.sh\n"
+        );
+        let mode = fs::metadata(dir.join("run.sh"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o111, 0o111);
+
+        create_file(&dir.join("empty"), None).unwrap();
+        assert_eq!(fs::read(dir.join("empty")).unwrap(), b"");
+        // an existing file is never overwritten
+        assert!(create_file(&dir.join("run.sh"), None).is_err());
+    }
+}
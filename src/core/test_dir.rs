@@ -0,0 +1,60 @@
+use std::{
+    fs, io,
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Counts the directories created by the tests, for unique names.
+static CREATED: AtomicUsize = AtomicUsize::new(0);
+
+/// An empty directory for the files of a test, it is removed with its content when it is dropped,
+/// even if the test panics.
+pub struct TestDir {
+    path: PathBuf,
+}
+
+impl TestDir {
+    /// Creates a new directory in the temporary directory of the system,
+    /// its name starts with `name` and it is unique among the test runs.
+    pub fn new(name: &str) -> Self {
+        loop {
+            let path = std::env::temp_dir().join(format!(
+                "twc-test-{}-{}-{}",
+                name,
+                std::process::id(),
+                CREATED.fetch_add(1, Ordering::Relaxed)
+            ));
+            match fs::create_dir(&path) {
+                // the tests compare paths, the temporary directory can be a link
+                Ok(()) => {
+                    return TestDir {
+                        path: fs::canonicalize(&path).unwrap(),
+                    }
+                }
+                Err(error) if error.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(error) => panic!("{}: {}", path.display(), error),
+            }
+        }
+    }
+}
+
+impl Deref for TestDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for TestDir {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,373 @@
+use chrono::{Local, NaiveDateTime, Timelike};
+use std::{
+    ffi::{OsStr, OsString},
+    fs::{self, DirBuilder, OpenOptions},
+    io::{self, Write},
+    os::unix::{
+        ffi::{OsStrExt, OsStringExt},
+        fs::{DirBuilderExt, MetadataExt},
+    },
+    path::{Path, PathBuf},
+};
+
+const INFO_EXTENSION: &str = ".trashinfo";
+const DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// A trash directory of the freedesktop.org Trash specification:
+/// the trashed files are kept under `files`, and their original paths
+/// and deletion dates under `info`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trash {
+    dir: PathBuf,
+    /// The top directory of the volume the trash belongs to, the original paths
+    /// are stored relative to it. None for the home trash, which stores absolute paths.
+    topdir: Option<PathBuf>,
+}
+
+/// A file in a `Trash`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrashEntry {
+    pub deletion_date: Option<NaiveDateTime>,
+    /// The name of the file in the trash, unique in its trash directory.
+    pub name: OsString,
+    pub original_path: PathBuf,
+    trash: Trash,
+}
+
+impl Trash {
+    pub fn new(dir: PathBuf, topdir: Option<PathBuf>) -> Self {
+        Trash { dir, topdir }
+    }
+
+    /// Returns the trash in the user's home, `$XDG_DATA_HOME/Trash`.
+    pub fn home() -> Option<Self> {
+        dirs::data_dir().map(|data_dir| Trash::new(data_dir.join("Trash"), None))
+    }
+
+    /// Returns the trash `path` has to be moved to: the home trash if the file is on
+    /// the same file system as the home trash, otherwise the `.Trash-$uid` directory
+    /// in the top directory of the file's volume.
+    pub fn for_path(path: &Path) -> io::Result<Self> {
+        let device = fs::symlink_metadata(path)?.dev();
+        if let Some(home) = Trash::home() {
+            if device_of(&home.dir) == Some(device) {
+                return Ok(home);
+            }
+        }
+        let topdir = mount_point(path, device);
+        Ok(Trash::new(
+            topdir.join(format!(".Trash-{}", current_uid())),
+            Some(topdir),
+        ))
+    }
+
+    /// Returns the home trash and the trash directories of the mounted volumes which exist.
+    pub fn all() -> Vec<Self> {
+        let mut trashes: Vec<Trash> = Trash::home().into_iter().collect();
+        let mounts = fs::read_to_string("/proc/self/mounts").unwrap_or_default();
+        for mount_point in mounts.lines().filter_map(|line| line.split(' ').nth(1)) {
+            let topdir = PathBuf::from(decode_mount_point(mount_point));
+            let dir = topdir.join(format!(".Trash-{}", current_uid()));
+            if dir.is_dir() && trashes.iter().all(|trash| trash.dir != dir) {
+                trashes.push(Trash::new(dir, Some(topdir)));
+            }
+        }
+        trashes
+    }
+
+    /// Moves `path` into the trash, and returns the entry it got in the trash.
+    /// The file is renamed, so the trash must be on the same file system as the file.
+    pub fn put(&self, path: &Path) -> io::Result<TrashEntry> {
+        let path = match path.is_absolute() {
+            true => PathBuf::from(path),
+            false => std::env::current_dir()?.join(path),
+        };
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Cannot trash this path"))?;
+        self.create_dirs()?;
+
+        let original_path = match &self.topdir {
+            Some(topdir) => path.strip_prefix(topdir).unwrap_or(&path),
+            None => &path,
+        };
+        // the date is stored in whole seconds
+        let deletion_date = Local::now().naive_local().with_nanosecond(0);
+        let info = format!(
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            encode_path(original_path),
+            deletion_date.unwrap_or_default().format(DATE_FORMAT)
+        );
+
+        // the info file is created exclusively to reserve the name in the trash
+        let (name, mut info_file) = (1..)
+            .map(|counter| match counter {
+                1 => file_name.to_os_string(),
+                _ => {
+                    let mut name = file_name.to_os_string();
+                    name.push(format!(".{}", counter));
+                    name
+                }
+            })
+            .find_map(|name| {
+                match OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(self.info_path(&name))
+                {
+                    Ok(file) => Some(Ok((name, file))),
+                    Err(error) if error.kind() == io::ErrorKind::AlreadyExists => None,
+                    Err(error) => Some(Err(error)),
+                }
+            })
+            .unwrap_or_else(|| Err(io::Error::from(io::ErrorKind::AlreadyExists)))?;
+
+        let result = info_file
+            .write_all(info.as_bytes())
+            .and_then(|_| fs::rename(&path, self.dir.join("files").join(&name)));
+        if let Err(error) = result {
+            let _ = fs::remove_file(self.info_path(&name));
+            return Err(error);
+        }
+        Ok(TrashEntry {
+            deletion_date,
+            name,
+            original_path: path.clone(),
+            trash: self.clone(),
+        })
+    }
+
+    /// Lists the files in the trash, the most recently deleted first.
+    pub fn entries(&self) -> Vec<TrashEntry> {
+        let read_dir = match fs::read_dir(self.dir.join("info")) {
+            Ok(read_dir) => read_dir,
+            Err(_) => return Vec::new(),
+        };
+        let mut entries: Vec<TrashEntry> = read_dir
+            .flatten()
+            .filter_map(|entry| {
+                let file_name = entry.file_name();
+                let name = file_name
+                    .as_bytes()
+                    .strip_suffix(INFO_EXTENSION.as_bytes())?;
+                let info = fs::read_to_string(entry.path()).ok()?;
+                self.parse_info(OsStr::from_bytes(name), &info)
+            })
+            .collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.deletion_date));
+        entries
+    }
+
+    fn parse_info(&self, name: &OsStr, info: &str) -> Option<TrashEntry> {
+        let mut original_path = None;
+        let mut deletion_date = None;
+        for line in info.lines() {
+            if let Some(path) = line.strip_prefix("Path=") {
+                let path = PathBuf::from(decode_path(path));
+                original_path = Some(match (&self.topdir, path.is_absolute()) {
+                    (Some(topdir), false) => topdir.join(path),
+                    _ => path,
+                });
+            } else if let Some(date) = line.strip_prefix("DeletionDate=") {
+                deletion_date = NaiveDateTime::parse_from_str(date, DATE_FORMAT).ok();
+            }
+        }
+        Some(TrashEntry {
+            deletion_date,
+            name: name.to_os_string(),
+            original_path: original_path?,
+            trash: self.clone(),
+        })
+    }
+
+    fn create_dirs(&self) -> io::Result<()> {
+        let mut builder = DirBuilder::new();
+        builder.recursive(true).mode(0o700);
+        builder.create(self.dir.join("files"))?;
+        builder.create(self.dir.join("info"))
+    }
+
+    fn info_path(&self, name: &OsStr) -> PathBuf {
+        let mut info_name = name.to_os_string();
+        info_name.push(INFO_EXTENSION);
+        self.dir.join("info").join(info_name)
+    }
+}
+
+impl TrashEntry {
+    /// Returns the path of the trashed file.
+    pub fn path(&self) -> PathBuf {
+        self.trash.dir.join("files").join(&self.name)
+    }
+
+    /// Moves the file back to its original path, re-creating its parent directories.
+    /// Nothing is overwritten, the restore fails if the original path exists.
+    pub fn restore(&self) -> io::Result<()> {
+        if fs::symlink_metadata(&self.original_path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", self.original_path.display()),
+            ));
+        }
+        if let Some(parent) = self.original_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(self.path(), &self.original_path)?;
+        fs::remove_file(self.trash.info_path(&self.name))
+    }
+
+    /// Deletes the file from the trash permanently.
+    pub fn purge(&self) -> io::Result<()> {
+        let path = self.path();
+        match fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(&path)?,
+            Ok(_) => fs::remove_file(&path)?,
+            // the file is gone already, only its info is left
+            Err(_) => {}
+        }
+        fs::remove_file(self.trash.info_path(&self.name))
+    }
+}
+
+fn current_uid() -> u32 {
+    unsafe { libc::getuid() }
+}
+
+/// Returns the device of `path`, or of its closest existing ancestor.
+fn device_of(path: &Path) -> Option<u64> {
+    path.ancestors()
+        .find_map(|ancestor| fs::metadata(ancestor).ok())
+        .map(|metadata| metadata.dev())
+}
+
+/// Returns the top directory of the volume on the `device` containing `path`.
+fn mount_point(path: &Path, device: u64) -> PathBuf {
+    let mut mount_point = path.parent().unwrap_or(path);
+    while let Some(parent) = mount_point.parent() {
+        match fs::metadata(parent) {
+            Ok(metadata) if metadata.dev() == device => mount_point = parent,
+            _ => break,
+        }
+    }
+    PathBuf::from(mount_point)
+}
+
+/// Escapes the path like in URLs, as the spec requires.
+fn encode_path(path: &Path) -> String {
+    path.as_os_str()
+        .as_bytes()
+        .iter()
+        .map(|&byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn decode_path(encoded: &str) -> OsString {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = bytes
+            .get(index + 1..index + 3)
+            .filter(|_| bytes[index] == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    OsString::from_vec(decoded)
+}
+
+/// The spaces and the special characters of the mount points are escaped as octal numbers
+/// in /proc/self/mounts.
+fn decode_mount_point(escaped: &str) -> OsString {
+    let bytes = escaped.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = bytes
+            .get(index + 1..index + 4)
+            .filter(|_| bytes[index] == b'\\')
+            .and_then(|octal| u8::from_str_radix(std::str::from_utf8(octal).ok()?, 8).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 4;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    OsString::from_vec(decoded)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode_mount_point, decode_path, encode_path, Trash};
+    use crate::core::test_dir::TestDir;
+    use std::{ffi::OsString, fs, path::Path};
+
+    #[test]
+    fn test_encode_path() {
+        let path = Path::new("/home/user/my file%.txt");
+        assert_eq!(encode_path(path), "/home/user/my%20file%25.txt");
+        assert_eq!(decode_path(&encode_path(path)), path.as_os_str());
+        assert_eq!(
+            decode_mount_point("/media/my\\040disk"),
+            OsString::from("/media/my disk")
+        );
+    }
+
+    #[test]
+    fn test_trash_put_restore_and_purge() {
+        let dir = TestDir::new("trash");
+        fs::create_dir_all(dir.join("work/sub")).unwrap();
+        fs::write(dir.join("work/a.txt"), "first").unwrap();
+        let trash = Trash::new(dir.join(".Trash-1000"), Some(dir.to_path_buf()));
+
+        let first = trash.put(&dir.join("work/a.txt")).unwrap();
+        assert_eq!(first.name, "a.txt");
+        fs::write(dir.join("work/a.txt"), "second").unwrap();
+        assert_eq!(trash.put(&dir.join("work/a.txt")).unwrap().name, "a.txt.2");
+        trash.put(&dir.join("work/sub")).unwrap();
+        assert!(!dir.join("work/a.txt").exists());
+        let info = fs::read_to_string(dir.join(".Trash-1000/info/a.txt.trashinfo")).unwrap();
+        assert!(info.starts_with("[Trash Info]\nPath=work/a.txt\nDeletionDate="));
+
+        let entries = trash.entries();
+        assert_eq!(entries.len(), 3);
+        // the entry returned by put is the one listed
+        assert!(entries.contains(&first));
+        assert_eq!(first.original_path, dir.join("work/a.txt"));
+        first.restore().unwrap();
+        assert_eq!(fs::read_to_string(dir.join("work/a.txt")).unwrap(), "first");
+
+        // nothing is overwritten by a restore
+        let second = entries
+            .iter()
+            .find(|entry| entry.name == "a.txt.2")
+            .unwrap();
+        assert!(second.restore().is_err());
+        second.purge().unwrap();
+        let sub = entries.iter().find(|entry| entry.name == "sub").unwrap();
+        sub.purge().unwrap();
+        assert!(trash.entries().is_empty());
+        assert!(fs::read_dir(dir.join(".Trash-1000/files"))
+            .unwrap()
+            .next()
+            .is_none());
+    }
+}
@@ -0,0 +1,556 @@
+use crate::core::{
+    archive::temporary_dir,
+    list_dir::{list_dir, DirContent, FilterOptions},
+};
+use std::{
+    fs::{self, File, Metadata, OpenOptions, Permissions},
+    io::{self, Read, Write},
+    os::unix::fs::{OpenOptionsExt, PermissionsExt},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// The kind of a file on a `Vfs`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FileKind {
+    File,
+    Dir,
+    Symlink,
+    /// A device, a socket or a named pipe.
+    Other,
+}
+
+/// The metadata of a file on a `Vfs`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileStat {
+    pub kind: FileKind,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    /// The Unix permission bits, if the file system stores them.
+    pub mode: Option<u32>,
+}
+
+impl FileStat {
+    pub fn is_dir(&self) -> bool {
+        self.kind == FileKind::Dir
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.kind == FileKind::File
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.kind == FileKind::Symlink
+    }
+}
+
+impl From<&Metadata> for FileStat {
+    fn from(metadata: &Metadata) -> Self {
+        let file_type = metadata.file_type();
+        let kind = if file_type.is_dir() {
+            FileKind::Dir
+        } else if file_type.is_file() {
+            FileKind::File
+        } else if file_type.is_symlink() {
+            FileKind::Symlink
+        } else {
+            FileKind::Other
+        };
+        FileStat {
+            kind,
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+            mode: Some(metadata.permissions().mode() & 0o7777),
+        }
+    }
+}
+
+/// A file system the panels browse, and the delete, mkdir, rename and transfer dialogs work on.
+/// The transfers between local files use the native copy engine, which preserves
+/// the ownership, permissions and holes of the files, other files are streamed
+/// through `open_read()` and `open_write()`.
+/// The files are addressed by absolute paths, like on the local file system,
+/// so a backend can be mounted under a path, e.g. an archive under the archive file.
+pub trait Vfs: Send + Sync {
+    /// Lists the content of the `dir` directory like `list_dir()`.
+    fn list(&self, dir: &Path, filter_options: &FilterOptions) -> io::Result<Vec<DirContent>>;
+
+    /// Returns the metadata of the file on `path`, following symbolic links.
+    fn stat(&self, path: &Path) -> io::Result<FileStat>;
+
+    /// Returns the metadata of the file on `path` itself, without following symbolic links.
+    fn symlink_stat(&self, path: &Path) -> io::Result<FileStat>;
+
+    /// Opens the file on `path` for reading.
+    fn open_read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>>;
+
+    /// Creates the new file on `path` for writing, it fails if the path exists, even as a symbolic link.
+    fn open_write(&self, path: &Path) -> io::Result<Box<dyn Write + Send>>;
+
+    /// Creates the `dir` directory, its parent must exist.
+    /// The `mode` is set exactly, without the umask.
+    fn mkdir(&self, dir: &Path, mode: Option<u32>) -> io::Result<()>;
+
+    /// Removes a file, a symbolic link or an empty directory.
+    fn remove(&self, path: &Path) -> io::Result<()>;
+
+    /// Renames the file or directory `from` to `to`.
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+
+    /// Returns the path of the file on the local file system, if it is there,
+    /// so it can be opened by other programs.
+    fn local_path(&self, _path: &Path) -> Option<PathBuf> {
+        None
+    }
+
+    /// Returns true if there is a file on `path`, a broken symbolic link included.
+    fn exists(&self, path: &Path) -> bool {
+        self.symlink_stat(path).is_ok()
+    }
+
+    /// Returns true if `path` is a directory or a symbolic link pointing to one.
+    fn is_dir(&self, path: &Path) -> bool {
+        self.stat(path).is_ok_and(|stat| stat.is_dir())
+    }
+
+    /// Creates the `dir` directory along with its missing parents,
+    /// succeeds if it already exists.
+    fn mkdir_all(&self, dir: &Path) -> io::Result<()> {
+        if self.is_dir(dir) {
+            return Ok(());
+        }
+        if let Some(parent) = dir.parent() {
+            self.mkdir_all(parent)?;
+        }
+        match self.mkdir(dir, None) {
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists && self.is_dir(dir) => {
+                Ok(())
+            }
+            result => result,
+        }
+    }
+}
+
+/// The local file system.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LocalFs;
+
+impl Vfs for LocalFs {
+    fn list(&self, dir: &Path, filter_options: &FilterOptions) -> io::Result<Vec<DirContent>> {
+        list_dir(dir, filter_options)
+    }
+
+    fn stat(&self, path: &Path) -> io::Result<FileStat> {
+        fs::metadata(path).map(|metadata| FileStat::from(&metadata))
+    }
+
+    fn symlink_stat(&self, path: &Path) -> io::Result<FileStat> {
+        fs::symlink_metadata(path).map(|metadata| FileStat::from(&metadata))
+    }
+
+    fn open_read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        Ok(Box::new(File::open(path)?))
+    }
+
+    fn open_write(&self, path: &Path) -> io::Result<Box<dyn Write + Send>> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .custom_flags(libc::O_NOFOLLOW)
+            .open(path)?;
+        Ok(Box::new(file))
+    }
+
+    fn mkdir(&self, dir: &Path, mode: Option<u32>) -> io::Result<()> {
+        fs::create_dir(dir)?;
+        match mode {
+            Some(mode) => fs::set_permissions(dir, Permissions::from_mode(mode)),
+            None => Ok(()),
+        }
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        match fs::symlink_metadata(path)?.is_dir() {
+            true => fs::remove_dir(path),
+            false => fs::remove_file(path),
+        }
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn local_path(&self, path: &Path) -> Option<PathBuf> {
+        Some(PathBuf::from(path))
+    }
+}
+
+/// Copies the file on `path` to the temporary directory of the application,
+/// e.g. for viewing a file which is not on the local file system.
+/// Returns the path of the copy, a previous copy of a file with the same name is replaced.
+pub fn copy_to_temporary(vfs: &dyn Vfs, path: &Path) -> io::Result<PathBuf> {
+    copy_into(vfs, path, &temporary_dir()?)
+}
+
+/// Copies the file on `path` into the local directory `dir`, replacing a file with its name.
+fn copy_into(vfs: &dyn Vfs, path: &Path, dir: &Path) -> io::Result<PathBuf> {
+    let file = dir.join(path.file_name().unwrap_or_default());
+    match fs::remove_file(&file) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+        _ => {}
+    }
+    io::copy(&mut vfs.open_read(path)?, &mut LocalFs.open_write(&file)?)?;
+    Ok(file)
+}
+
+#[cfg(test)]
+pub use self::memory::MemoryFs;
+
+#[cfg(test)]
+mod memory {
+    use super::{FileKind, FileStat, Vfs};
+    use crate::core::list_dir::{DirContent, FilterOptions};
+    use chrono::{DateTime, Local};
+    use std::{
+        collections::BTreeMap,
+        io::{self, Cursor, Read, Write},
+        path::{Path, PathBuf},
+        sync::{Arc, Mutex},
+        time::SystemTime,
+    };
+
+    struct Node {
+        /// The content of a file, None for a directory.
+        content: Option<Vec<u8>>,
+        modified: SystemTime,
+        mode: u32,
+    }
+
+    impl Node {
+        fn stat(&self) -> FileStat {
+            FileStat {
+                kind: match self.content {
+                    Some(_) => FileKind::File,
+                    None => FileKind::Dir,
+                },
+                size: self
+                    .content
+                    .as_ref()
+                    .map_or(0, |content| content.len() as u64),
+                modified: Some(self.modified),
+                mode: Some(self.mode),
+            }
+        }
+    }
+
+    type Nodes = Arc<Mutex<BTreeMap<PathBuf, Node>>>;
+
+    /// A file system kept in memory for testing, it has files and directories only.
+    /// The clones share the same files.
+    #[derive(Clone)]
+    pub struct MemoryFs {
+        nodes: Nodes,
+    }
+
+    impl MemoryFs {
+        /// Creates a file system with the empty root directory.
+        pub fn new() -> Self {
+            let mut nodes = BTreeMap::new();
+            nodes.insert(PathBuf::from("/"), dir_node(None));
+            MemoryFs {
+                nodes: Arc::new(Mutex::new(nodes)),
+            }
+        }
+
+        /// Creates the file on `path` with the `content`, along with its missing parents.
+        pub fn write(&self, path: &str, content: &str) {
+            let path = Path::new(path);
+            self.mkdir_all(path.parent().unwrap()).unwrap();
+            self.open_write(path)
+                .unwrap()
+                .write_all(content.as_bytes())
+                .unwrap();
+        }
+
+        /// Returns the content of the file on `path`.
+        pub fn read(&self, path: &str) -> String {
+            let mut content = String::new();
+            self.open_read(Path::new(path))
+                .unwrap()
+                .read_to_string(&mut content)
+                .unwrap();
+            content
+        }
+
+        /// Fails unless the parent of `path` is a directory.
+        fn check_parent(nodes: &BTreeMap<PathBuf, Node>, path: &Path) -> io::Result<()> {
+            match path.parent().and_then(|parent| nodes.get(parent)) {
+                Some(Node { content: None, .. }) => Ok(()),
+                Some(_) => Err(io::ErrorKind::NotADirectory.into()),
+                None => Err(io::ErrorKind::NotFound.into()),
+            }
+        }
+    }
+
+    fn dir_node(mode: Option<u32>) -> Node {
+        Node {
+            content: None,
+            modified: SystemTime::now(),
+            mode: mode.unwrap_or(0o755),
+        }
+    }
+
+    /// Appends the written bytes to the file right away.
+    struct MemoryWriter {
+        nodes: Nodes,
+        path: PathBuf,
+    }
+
+    impl Write for MemoryWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let mut nodes = self.nodes.lock().unwrap();
+            match nodes.get_mut(&self.path) {
+                Some(Node {
+                    content: Some(content),
+                    modified,
+                    ..
+                }) => {
+                    content.extend_from_slice(buf);
+                    *modified = SystemTime::now();
+                    Ok(buf.len())
+                }
+                _ => Err(io::ErrorKind::NotFound.into()),
+            }
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Vfs for MemoryFs {
+        fn list(&self, dir: &Path, filter_options: &FilterOptions) -> io::Result<Vec<DirContent>> {
+            if !self.stat(dir)?.is_dir() {
+                return Err(io::ErrorKind::NotADirectory.into());
+            }
+            let nodes = self.nodes.lock().unwrap();
+            let files = nodes
+                .iter()
+                .filter(|(path, _)| path.parent() == Some(dir))
+                .filter_map(|(path, node)| Some((path.file_name()?, node.stat())))
+                .filter(|(name, _)| {
+                    filter_options.show_hidden_files || !name.to_string_lossy().starts_with('.')
+                })
+                .map(|(name, stat)| DirContent {
+                    name: name.to_os_string(),
+                    is_dir: stat.is_dir(),
+                    size: (!stat.is_dir()).then_some(stat.size),
+                    date: stat
+                        .modified
+                        .map(|modified| {
+                            DateTime::<Local>::from(modified)
+                                .format("%Y.%m.%d %H:%M")
+                                .to_string()
+                        })
+                        .unwrap_or_default(),
+                    attrs: String::new(),
+                    is_symlink: false,
+                    link_target: None,
+                    is_broken_link: false,
+                })
+                .collect();
+            Ok(files)
+        }
+
+        fn stat(&self, path: &Path) -> io::Result<FileStat> {
+            self.nodes
+                .lock()
+                .unwrap()
+                .get(path)
+                .map(Node::stat)
+                .ok_or_else(|| io::ErrorKind::NotFound.into())
+        }
+
+        fn symlink_stat(&self, path: &Path) -> io::Result<FileStat> {
+            self.stat(path)
+        }
+
+        fn open_read(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+            match self.nodes.lock().unwrap().get(path) {
+                Some(Node {
+                    content: Some(content),
+                    ..
+                }) => Ok(Box::new(Cursor::new(content.clone()))),
+                Some(_) => Err(io::ErrorKind::IsADirectory.into()),
+                None => Err(io::ErrorKind::NotFound.into()),
+            }
+        }
+
+        fn open_write(&self, path: &Path) -> io::Result<Box<dyn Write + Send>> {
+            let mut nodes = self.nodes.lock().unwrap();
+            MemoryFs::check_parent(&nodes, path)?;
+            if nodes.contains_key(path) {
+                return Err(io::ErrorKind::AlreadyExists.into());
+            }
+            let node = Node {
+                content: Some(Vec::new()),
+                modified: SystemTime::now(),
+                mode: 0o644,
+            };
+            nodes.insert(PathBuf::from(path), node);
+            Ok(Box::new(MemoryWriter {
+                nodes: Arc::clone(&self.nodes),
+                path: PathBuf::from(path),
+            }))
+        }
+
+        fn mkdir(&self, dir: &Path, mode: Option<u32>) -> io::Result<()> {
+            let mut nodes = self.nodes.lock().unwrap();
+            if nodes.contains_key(dir) {
+                return Err(io::ErrorKind::AlreadyExists.into());
+            }
+            MemoryFs::check_parent(&nodes, dir)?;
+            nodes.insert(PathBuf::from(dir), dir_node(mode));
+            Ok(())
+        }
+
+        fn remove(&self, path: &Path) -> io::Result<()> {
+            let mut nodes = self.nodes.lock().unwrap();
+            if !nodes.contains_key(path) {
+                return Err(io::ErrorKind::NotFound.into());
+            }
+            if nodes.keys().any(|other| other.parent() == Some(path)) {
+                return Err(io::ErrorKind::DirectoryNotEmpty.into());
+            }
+            nodes.remove(path);
+            Ok(())
+        }
+
+        fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+            let mut nodes = self.nodes.lock().unwrap();
+            if !nodes.contains_key(from) {
+                return Err(io::ErrorKind::NotFound.into());
+            }
+            MemoryFs::check_parent(&nodes, to)?;
+            if to.starts_with(from) {
+                return Err(io::ErrorKind::InvalidInput.into());
+            }
+            if nodes.keys().any(|other| other.parent() == Some(to)) {
+                return Err(io::ErrorKind::DirectoryNotEmpty.into());
+            }
+            let moved: Vec<PathBuf> = nodes
+                .keys()
+                .filter(|path| path.starts_with(from))
+                .cloned()
+                .collect();
+            for path in moved {
+                let node = nodes.remove(&path).unwrap();
+                let relative = path.strip_prefix(from).unwrap();
+                nodes.insert(to.join(relative), node);
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{copy_into, FileKind, LocalFs, MemoryFs, Vfs};
+    use crate::core::{archive::temporary_dir, list_dir::FilterOptions, test_dir::TestDir};
+    use std::{
+        fs,
+        io::{self, Read, Write},
+        os::unix::fs::{symlink, PermissionsExt},
+        path::Path,
+    };
+
+    /// Runs the same operations on every `Vfs` rooted in `root`.
+    fn check_operations(vfs: &dyn Vfs, root: &Path) {
+        let filter_options = FilterOptions {
+            show_hidden_files: true,
+        };
+        vfs.mkdir_all(&root.join("a/b")).unwrap();
+        vfs.mkdir_all(&root.join("a/b")).unwrap();
+        vfs.mkdir(&root.join("mode"), Some(0o700)).unwrap();
+        assert_eq!(vfs.stat(&root.join("mode")).unwrap().mode, Some(0o700));
+        vfs.open_write(&root.join("a/file.txt"))
+            .unwrap()
+            .write_all(b"content")
+            .unwrap();
+        assert!(vfs.open_write(&root.join("a/file.txt")).is_err());
+
+        let mut names: Vec<_> = vfs
+            .list(&root.join("a"), &filter_options)
+            .unwrap()
+            .into_iter()
+            .map(|file| (file.name, file.is_dir, file.size))
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                ("b".into(), true, None),
+                ("file.txt".into(), false, Some(7))
+            ]
+        );
+        let stat = vfs.stat(&root.join("a/file.txt")).unwrap();
+        assert_eq!((stat.kind, stat.size), (FileKind::File, 7));
+        assert!(vfs.is_dir(&root.join("a")));
+        assert!(!vfs.is_dir(&root.join("a/file.txt")));
+
+        vfs.rename(&root.join("a"), &root.join("c")).unwrap();
+        assert!(!vfs.exists(&root.join("a")));
+        let mut content = String::new();
+        vfs.open_read(&root.join("c/file.txt"))
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "content");
+
+        let error = vfs.remove(&root.join("c")).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::DirectoryNotEmpty);
+        vfs.remove(&root.join("c/file.txt")).unwrap();
+        vfs.remove(&root.join("c/b")).unwrap();
+        vfs.remove(&root.join("c")).unwrap();
+        assert!(!vfs.exists(&root.join("c")));
+        assert!(vfs.mkdir(&root.join("missing/d"), None).is_err());
+    }
+
+    #[test]
+    fn test_local_fs() {
+        let dir = TestDir::new("vfs");
+
+        check_operations(&LocalFs, &dir);
+    }
+
+    #[test]
+    fn test_memory_fs() {
+        let vfs = MemoryFs::new();
+        check_operations(&vfs, Path::new("/"));
+        vfs.write("/x/y.txt", "y");
+        assert_eq!(vfs.read("/x/y.txt"), "y");
+    }
+
+    #[test]
+    fn test_copy_to_temporary() {
+        let vfs = MemoryFs::new();
+        vfs.write("/docs/notes.txt", "notes");
+        let temporary = temporary_dir().unwrap();
+        assert_eq!(temporary_dir().unwrap(), temporary);
+        assert_eq!(
+            fs::metadata(&temporary).unwrap().permissions().mode() & 0o777,
+            0o700
+        );
+
+        // a link planted on the name of the copy is replaced, not written through
+        let dir = TestDir::new("vfs-temporary");
+        let target = dir.join("target.txt");
+        fs::write(&target, "target").unwrap();
+        symlink(&target, dir.join("notes.txt")).unwrap();
+        let copy = copy_into(&vfs, Path::new("/docs/notes.txt"), &dir).unwrap();
+        assert_eq!(copy, dir.join("notes.txt"));
+        assert!(!copy.is_symlink());
+        assert_eq!(fs::read_to_string(&copy).unwrap(), "notes");
+        assert_eq!(fs::read_to_string(&target).unwrap(), "target");
+    }
+}
@@ -1,13 +1,23 @@
 use std::{
-    io,
-    sync::mpsc::{self},
+    io::{self, Read},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self},
+        Arc,
+    },
     thread,
     time::Duration,
 };
-use termion::{event::Key, input::TermRead};
+use termion::{
+    event::{self as termion_event, Key},
+    input::TermRead,
+};
 
 pub const DEFAULT_TICK_RATE: u64 = 250;
 
+/// How long the input is waited for before checking if the reading is paused, in milliseconds.
+const INPUT_POLL_TIMEOUT: u64 = 100;
+
 /// Represents an event consumed by the application.
 /// Event source is the termion backend.
 pub enum Event<I> {
@@ -23,6 +33,7 @@ pub enum Event<I> {
 /// and produces Events through a channel that can be consumed by the application.
 pub struct Events {
     //_tx: mpsc::Sender<Event<Key>>,
+    paused: Arc<AtomicBool>,
     rx: mpsc::Receiver<Event<Key>>,
 }
 
@@ -37,11 +48,23 @@ impl Events {
 
         let (tx, rx) = mpsc::channel();
         let event_tx = tx.clone();
+        let paused = Arc::new(AtomicBool::new(false));
+        let stdin = PausableStdin {
+            paused: Arc::clone(&paused),
+        };
 
         thread::spawn(move || {
-            let stdin = io::stdin();
-
-            for key in stdin.keys().flatten() {
+            for event in stdin.events().flatten() {
+                let key = match event {
+                    termion_event::Event::Key(key) => key,
+                    termion_event::Event::Unsupported(sequence) => {
+                        match modified_function_key(&sequence) {
+                            Some(key) => key,
+                            None => continue,
+                        }
+                    }
+                    termion_event::Event::Mouse(_) => continue,
+                };
                 if let Err(error) = tx.send(Event::Input(key)) {
                     // TODO: proper logging
                     eprintln!("Error during sending a key press event: {}", error);
@@ -56,7 +79,17 @@ impl Events {
             }
             thread::sleep(tick_rate);
         });
-        Events { rx }
+        Events { paused, rx }
+    }
+
+    /// Stops reading the key presses, e.g. while an external program runs in the terminal.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    /// Continues reading the key presses after `pause()`.
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
     }
 
     /// Attempts to read an event from the channel in a blocking way.
@@ -64,3 +97,112 @@ impl Events {
         self.rx.recv()
     }
 }
+
+/// Reads the standard input without buffering, nothing is read ahead while the reading is paused:
+/// the key presses belong to the program running in the terminal meanwhile.
+struct PausableStdin {
+    paused: Arc<AtomicBool>,
+}
+
+impl Read for PausableStdin {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.paused.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_millis(INPUT_POLL_TIMEOUT));
+            } else if wait_for_input() && !self.paused.load(Ordering::SeqCst) {
+                break;
+            }
+        }
+        let read = unsafe { libc::read(libc::STDIN_FILENO, buf.as_mut_ptr().cast(), buf.len()) };
+        match read {
+            -1 => Err(io::Error::last_os_error()),
+            read => Ok(read as usize),
+        }
+    }
+}
+
+/// Waits until the standard input can be read, at most for `INPUT_POLL_TIMEOUT`.
+fn wait_for_input() -> bool {
+    let mut stdin = libc::pollfd {
+        fd: libc::STDIN_FILENO,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    unsafe { libc::poll(&mut stdin, 1, INPUT_POLL_TIMEOUT as libc::c_int) > 0 }
+}
+
+/// Translates the escape sequences of the shifted and the Alt modified function keys
+/// which are not recognized by termion. They are reported like xterm's kf13-kf24
+/// and kf49-kf60 capabilities: Shift+F1..Shift+F12 as `Key::F(13)..Key::F(24)`,
+/// Alt+F1..Alt+F12 as `Key::F(49)..Key::F(60)`, e.g. Shift+F6 is `Key::F(18)`.
+fn modified_function_key(sequence: &[u8]) -> Option<Key> {
+    let sequence = std::str::from_utf8(sequence).ok()?;
+    let csi = sequence.strip_prefix("\x1B[")?;
+    // xterm: ESC [ 1 ; modifier P..S for F1..F4
+    if let Some(code) = csi.strip_prefix("1;") {
+        return match code.as_bytes() {
+            [modifier, code @ b'P'..=b'S'] => {
+                let offset = modifier_offset(char::from(*modifier))?;
+                Some(Key::F(offset + 1 + code - b'P'))
+            }
+            _ => None,
+        };
+    }
+
+    let code = csi.strip_suffix('~')?;
+    let key = match code.split_once(';') {
+        // xterm: ESC [ code ; modifier ~ for F5..F12
+        Some((code, modifier)) => {
+            let key = match code.parse::<u8>().ok()? {
+                15 => 5,
+                code @ 17..=21 => code - 11,
+                code @ 23..=24 => code - 12,
+                _ => return None,
+            };
+            let mut modifier = modifier.chars();
+            match (modifier.next(), modifier.next()) {
+                (Some(modifier), None) => modifier_offset(modifier)? + key,
+                _ => return None,
+            }
+        }
+        // linux console: ESC [ code ~ for Shift+F3..F10
+        None => match code.parse::<u8>().ok()? {
+            code @ 25..=26 => code - 10,
+            code @ 28..=29 => code - 11,
+            code @ 31..=34 => code - 12,
+            _ => return None,
+        },
+    };
+    Some(Key::F(key))
+}
+
+/// Returns how far the function keys with the xterm `modifier` are numbered
+/// from the plain ones: Shift is 2, Alt is 3.
+fn modifier_offset(modifier: char) -> Option<u8> {
+    match modifier {
+        '2' => Some(12),
+        '3' => Some(48),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::modified_function_key;
+    use termion::event::Key;
+
+    #[test]
+    fn test_modified_function_key() {
+        assert_eq!(modified_function_key(b"\x1B[1;2P"), Some(Key::F(13)));
+        assert_eq!(modified_function_key(b"\x1B[15;2~"), Some(Key::F(17)));
+        assert_eq!(modified_function_key(b"\x1B[17;2~"), Some(Key::F(18)));
+        assert_eq!(modified_function_key(b"\x1B[24;2~"), Some(Key::F(24)));
+        assert_eq!(modified_function_key(b"\x1B[29~"), Some(Key::F(18)));
+        assert_eq!(modified_function_key(b"\x1B[1;3Q"), Some(Key::F(50)));
+        assert_eq!(modified_function_key(b"\x1B[15;3~"), Some(Key::F(53)));
+        assert_eq!(modified_function_key(b"\x1B[20;3~"), Some(Key::F(57)));
+        assert_eq!(modified_function_key(b"\x1B[17;5~"), None);
+        assert_eq!(modified_function_key(b"\x1B[17;23~"), None);
+        assert_eq!(modified_function_key(b"\x1B[99~"), None);
+    }
+}
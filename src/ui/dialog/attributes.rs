@@ -0,0 +1,442 @@
+use super::{display_errors, handle_input_keys};
+use crate::core::attributes::{
+    apply_attributes, file_mode, file_owner, group_id, group_name, parse_mode, user_id, user_name,
+    AttributeChange,
+};
+use std::{io::Stdout, path::PathBuf};
+use termion::{event::Key, raw::RawTerminal};
+use tui::{
+    backend::TermionBackend,
+    layout::{Alignment, Rect},
+    style::{Color, Style},
+    text::{Span, Spans, Text},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+use tui_input::Input;
+
+/// The permission bits in the order of the checkboxes:
+/// user, group and other read-write-execute, then setuid, setgid and sticky.
+const PERMISSION_BITS: [u32; 12] = [
+    0o400, 0o200, 0o100, 0o040, 0o020, 0o010, 0o004, 0o002, 0o001, 0o4000, 0o2000, 0o1000,
+];
+const PERMISSION_ROWS: [&str; 4] = ["User   ", "Group  ", "Other  ", "Special"];
+const PERMISSION_COLUMNS: [[&str; 3]; 2] = [["Read", "Write", "Exec"], ["SUID", "SGID", "Sticky"]];
+
+enum Buttons {
+    Ok,
+    Cancel,
+}
+
+impl Buttons {
+    fn next(&mut self) {
+        match *self {
+            Buttons::Ok => *self = Buttons::Cancel,
+            Buttons::Cancel => *self = Buttons::Ok,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Components {
+    Permissions,
+    Mode,
+    DirMode,
+    Owner,
+    Group,
+    Recursive,
+    Buttons,
+}
+
+impl Components {
+    fn next(&mut self) {
+        *self = match self {
+            Components::Permissions => Components::Mode,
+            Components::Mode => Components::DirMode,
+            Components::DirMode => Components::Owner,
+            Components::Owner => Components::Group,
+            Components::Group => Components::Recursive,
+            Components::Recursive => Components::Buttons,
+            Components::Buttons => Components::Permissions,
+        }
+    }
+
+    fn previous(&mut self) {
+        *self = match self {
+            Components::Permissions => Components::Buttons,
+            Components::Mode => Components::Permissions,
+            Components::DirMode => Components::Mode,
+            Components::Owner => Components::DirMode,
+            Components::Group => Components::Owner,
+            Components::Recursive => Components::Group,
+            Components::Buttons => Components::Recursive,
+        }
+    }
+}
+
+/// Represents the state of the dialog
+pub enum AttributesDialogState {
+    WaitingForInput,
+    DisplayErrorMessages(Vec<String>),
+}
+
+/// A dialog for changing the permissions and the ownership of the selected or marked files.
+///
+/// ## Key controls
+/// * Tab and Shift+Tab: focus the next/previous component
+/// * Arrow keys: select a permission checkbox or a button
+/// * Space: toggle the selected checkbox
+/// * Enter: apply the changes or cancel, depending on the focused button
+/// * Esc: closes the dialog without applying the changes
+pub struct AttributesDialog {
+    button: Buttons,
+    cursor: usize,
+    dir_mode: Input,
+    files: Vec<PathBuf>,
+    focused: Components,
+    group: Input,
+    initial_group: String,
+    initial_mode: String,
+    initial_owner: String,
+    mode: Input,
+    owner: Input,
+    permissions: u32,
+    recursive: bool,
+    should_quit: bool,
+    state: AttributesDialogState,
+}
+
+impl AttributesDialog {
+    /// Creates a new dialog for the `files`.
+    /// The permissions and the ownership of the first file will be displayed initially,
+    /// they are applied on the files only if the user changes them.
+    pub fn new(files: Vec<PathBuf>) -> Self {
+        let (permissions, owner, group) = match files.first() {
+            Some(file) => {
+                let permissions = file_mode(file).unwrap_or_default();
+                let (owner, group) = match file_owner(file) {
+                    Ok((uid, gid)) => (
+                        user_name(uid).unwrap_or_else(|| uid.to_string()),
+                        group_name(gid).unwrap_or_else(|| gid.to_string()),
+                    ),
+                    Err(_) => (String::new(), String::new()),
+                };
+                (permissions, owner, group)
+            }
+            None => (0, String::new(), String::new()),
+        };
+
+        let mode = format!("{:04o}", permissions);
+        AttributesDialog {
+            button: Buttons::Ok,
+            cursor: 0,
+            dir_mode: Input::default(),
+            files,
+            focused: Components::Permissions,
+            group: Input::new(group.clone()),
+            initial_group: group,
+            initial_mode: mode.clone(),
+            initial_owner: owner.clone(),
+            mode: Input::new(mode),
+            owner: Input::new(owner),
+            permissions,
+            recursive: false,
+            should_quit: false,
+            state: AttributesDialogState::WaitingForInput,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: Key) {
+        if let AttributesDialogState::DisplayErrorMessages(_) = self.state {
+            if key == Key::Char('\n') {
+                self.should_quit = true;
+            }
+            return;
+        }
+
+        match key {
+            Key::Char('\n') => match self.button {
+                Buttons::Ok => self.apply(),
+                Buttons::Cancel => self.should_quit = true,
+            },
+            Key::Char('\t') => self.focused.next(),
+            Key::BackTab => self.focused.previous(),
+            _ => match self.focused {
+                Components::Permissions => self.handle_permission_keys(key),
+                Components::Mode => {
+                    handle_input_keys(&mut self.mode, key, |char| char.is_digit(8));
+                    if let Some(mode) = parse_mode(self.mode.value()) {
+                        self.permissions = mode;
+                    }
+                }
+                Components::DirMode => {
+                    handle_input_keys(&mut self.dir_mode, key, |char| char.is_digit(8))
+                }
+                Components::Owner => handle_input_keys(&mut self.owner, key, is_name_char),
+                Components::Group => handle_input_keys(&mut self.group, key, is_name_char),
+                Components::Recursive => {
+                    if key == Key::Char(' ') {
+                        self.recursive = !self.recursive;
+                    }
+                }
+                Components::Buttons => {
+                    if let Key::Left | Key::Right = key {
+                        self.button.next()
+                    }
+                }
+            },
+        }
+    }
+
+    pub fn render(&self, frame: &mut Frame<TermionBackend<RawTerminal<Stdout>>>, area: Rect) {
+        match &self.state {
+            AttributesDialogState::WaitingForInput => {
+                frame.render_widget(self.display_input(), area)
+            }
+            AttributesDialogState::DisplayErrorMessages(messages) => {
+                frame.render_widget(display_errors(messages), area)
+            }
+        }
+    }
+
+    /// Signals that the dialog should be closed or not
+    pub fn should_quit(&self) -> bool {
+        self.should_quit
+    }
+
+    fn handle_permission_keys(&mut self, key: Key) {
+        match key {
+            Key::Left if !self.cursor.is_multiple_of(3) => self.cursor -= 1,
+            Key::Right if self.cursor % 3 < 2 => self.cursor += 1,
+            Key::Up if self.cursor >= 3 => self.cursor -= 3,
+            Key::Down if self.cursor < 9 => self.cursor += 3,
+            Key::Char(' ') => {
+                self.permissions ^= PERMISSION_BITS[self.cursor];
+                self.mode = Input::new(format!("{:04o}", self.permissions));
+            }
+            _ => {}
+        }
+    }
+
+    /// Collects the requested changes and applies them on the files.
+    /// The dialog will be closed if there were no errors, otherwise the errors will be displayed.
+    fn apply(&mut self) {
+        match self.attribute_change() {
+            Ok(change) => {
+                let errors = apply_attributes(&self.files, &change);
+                if errors.is_empty() {
+                    self.should_quit = true;
+                } else {
+                    let messages = errors
+                        .iter()
+                        .map(|error| format!("{}: {}", error.path.display(), error.error))
+                        .collect();
+                    self.state = AttributesDialogState::DisplayErrorMessages(messages);
+                }
+            }
+            Err(message) => {
+                self.state = AttributesDialogState::DisplayErrorMessages(vec![message]);
+            }
+        }
+    }
+
+    fn attribute_change(&self) -> Result<AttributeChange, String> {
+        // the mode of the first file is not forced on the others unless the user edited it
+        let file_mode = match self.mode.value().trim() {
+            value if value == self.initial_mode || value.is_empty() => None,
+            value => match parse_mode(value) {
+                Some(mode) => Some(mode),
+                None => return Err(format!("Invalid mode: {}", value)),
+            },
+        };
+        let dir_mode = match self.dir_mode.value().trim() {
+            "" => None,
+            value => match parse_mode(value) {
+                Some(mode) => Some(mode),
+                None => return Err(format!("Invalid directory mode: {}", value)),
+            },
+        };
+        // ownership is changed only if the user edited it
+        let owner = match self.owner.value().trim() {
+            value if value == self.initial_owner || value.is_empty() => None,
+            value => match user_id(value) {
+                Some(uid) => Some(uid),
+                None => return Err(format!("Unknown user: {}", value)),
+            },
+        };
+        let group = match self.group.value().trim() {
+            value if value == self.initial_group || value.is_empty() => None,
+            value => match group_id(value) {
+                Some(gid) => Some(gid),
+                None => return Err(format!("Unknown group: {}", value)),
+            },
+        };
+
+        // a selected directory gets the edited mode, the directories found in it only the directory mode
+        let dir_mode = match self.recursive {
+            true => dir_mode,
+            false => dir_mode.or(file_mode),
+        };
+
+        Ok(AttributeChange {
+            file_mode,
+            dir_mode,
+            owner,
+            group,
+            recursive: self.recursive,
+        })
+    }
+
+    fn display_input(&self) -> Paragraph<'_> {
+        let style = Style::default().fg(Color::Black);
+        let focused_style = Style::default().bg(Color::Cyan).fg(Color::White);
+        let input_style = Style::default().bg(Color::Cyan).fg(Color::Black);
+        let style_of = |component: Components| match self.focused == component {
+            true => focused_style,
+            false => style,
+        };
+
+        let files_label = match self.files.len() {
+            1 => self.files[0].display().to_string(),
+            count => format!("{} items", count),
+        };
+        let mut spans = vec![
+            Spans::from(Span::styled(files_label, style)),
+            Spans::from(Span::styled(
+                format!(
+                    "        {:<7}{:<7}{:<7}",
+                    PERMISSION_COLUMNS[0][0], PERMISSION_COLUMNS[0][1], PERMISSION_COLUMNS[0][2]
+                ),
+                style,
+            )),
+        ];
+        for (row, row_title) in PERMISSION_ROWS.iter().enumerate() {
+            let mut row_spans = vec![Span::styled(format!("{} ", row_title), style)];
+            for (column, special_title) in PERMISSION_COLUMNS[1].iter().enumerate() {
+                let index = row * 3 + column;
+                let check_mark = match self.permissions & PERMISSION_BITS[index] != 0 {
+                    true => "X",
+                    false => " ",
+                };
+                let label = match row {
+                    3 => format!("[{}] {:<6} ", check_mark, special_title),
+                    _ => format!("[{}]    ", check_mark),
+                };
+                let checkbox_style =
+                    match self.focused == Components::Permissions && self.cursor == index {
+                        true => focused_style,
+                        false => style,
+                    };
+                row_spans.push(Span::styled(label, checkbox_style));
+            }
+            spans.push(Spans::from(row_spans));
+        }
+
+        let recursive_check_mark = match self.recursive {
+            true => "[X] Recursive",
+            false => "[ ] Recursive",
+        };
+        let button_titles = match self.button {
+            Buttons::Ok => ("[X] OK ", "[ ] Cancel"),
+            Buttons::Cancel => ("[ ] OK ", "[X] Cancel"),
+        };
+        spans.push(Spans::from(vec![
+            Span::styled("Mode: ", style_of(Components::Mode)),
+            Span::styled(format!("{:<5}", self.mode.value()), input_style),
+            Span::styled(" Directories: ", style_of(Components::DirMode)),
+            Span::styled(format!("{:<5}", self.dir_mode.value()), input_style),
+        ]));
+        spans.push(Spans::from(vec![
+            Span::styled("Owner: ", style_of(Components::Owner)),
+            Span::styled(format!("{:<10}", self.owner.value()), input_style),
+            Span::styled(" Group: ", style_of(Components::Group)),
+            Span::styled(format!("{:<10}", self.group.value()), input_style),
+        ]));
+        spans.push(Spans::from(Span::styled(
+            recursive_check_mark,
+            style_of(Components::Recursive),
+        )));
+        spans.push(Spans::from(vec![
+            Span::styled(button_titles.0, style_of(Components::Buttons)),
+            Span::styled(button_titles.1, style_of(Components::Buttons)),
+        ]));
+
+        Paragraph::new(Text::from(spans))
+            .block(
+                Block::default()
+                    .title(Span::styled("Attributes", Style::default().fg(Color::Cyan)))
+                    .style(Style::default().fg(Color::Black).bg(Color::Gray))
+                    .borders(Borders::ALL)
+                    .title_alignment(Alignment::Center),
+            )
+            .alignment(Alignment::Center)
+    }
+}
+
+fn is_name_char(char: char) -> bool {
+    char.is_alphanumeric() || char == '_' || char == '-' || char == '.'
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AttributesDialog, AttributesDialogState};
+    use crate::core::{attributes::file_mode, test_dir::TestDir};
+    use std::{
+        fs::{self, Permissions},
+        os::unix::fs::PermissionsExt,
+    };
+    use termion::event::Key;
+
+    #[test]
+    fn test_toggle_permission_updates_mode() {
+        let mut dialog = AttributesDialog::new(vec![]);
+        dialog.handle_key(Key::Char(' '));
+        assert_eq!(dialog.mode.value(), "0400");
+
+        dialog.handle_key(Key::Down);
+        dialog.handle_key(Key::Right);
+        dialog.handle_key(Key::Char(' '));
+        assert_eq!(dialog.mode.value(), "0420");
+    }
+
+    #[test]
+    fn test_unedited_mode_is_not_applied() {
+        let dir = TestDir::new("attributes-dialog");
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        fs::write(dir.join("b.sh"), "b").unwrap();
+        fs::set_permissions(dir.join("a.txt"), Permissions::from_mode(0o640)).unwrap();
+        fs::set_permissions(dir.join("b.sh"), Permissions::from_mode(0o755)).unwrap();
+
+        let mut dialog = AttributesDialog::new(vec![dir.join("a.txt"), dir.join("b.sh")]);
+        let change = dialog.attribute_change().unwrap();
+        assert_eq!(change.file_mode, None);
+
+        dialog.handle_key(Key::Char(' '));
+        assert_eq!(dialog.attribute_change().unwrap().file_mode, Some(0o240));
+        dialog.handle_key(Key::Char(' '));
+        dialog.handle_key(Key::Char('\n'));
+        assert!(dialog.should_quit());
+        assert_eq!(file_mode(&dir.join("b.sh")).unwrap(), 0o755);
+    }
+
+    #[test]
+    fn test_invalid_dir_mode_is_reported() {
+        let mut dialog = AttributesDialog::new(vec![]);
+        dialog.handle_key(Key::Char('\t'));
+        dialog.handle_key(Key::Char('\t'));
+        for char in "917777".chars() {
+            dialog.handle_key(Key::Char(char));
+        }
+        dialog.handle_key(Key::Char('\n'));
+
+        match dialog.state {
+            AttributesDialogState::DisplayErrorMessages(messages) => {
+                assert_eq!(
+                    messages,
+                    vec![String::from("Invalid directory mode: 17777")]
+                )
+            }
+            AttributesDialogState::WaitingForInput => panic!("error should be displayed"),
+        }
+    }
+}
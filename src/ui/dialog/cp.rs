@@ -1,43 +1,61 @@
-use super::{TransferProgress, TransferStrategy};
-use fs_extra::dir::{
-    copy_with_progress as copy_dir_with_progress, CopyOptions as DirCopyOptions,
-    TransitProcess as DirTransitProcess,
+use super::{
+    ErrorAnswer, TransferAborted, TransferChannel, TransferOptions, TransferProgress,
+    TransferStrategy,
+};
+use crate::core::{
+    conflict::{append_missing, ConflictAction, FileConflict},
+    copy::{
+        copy_file, copy_symlink, preserve_attributes, verify_copy, CopyProgress, TransferControl,
+    },
+    journal::{CreatedFiles, Operation},
+    list_dir::FilterOptions,
+    vfs::{LocalFs, Vfs},
 };
 use std::{
+    fs,
+    io::{self, Write},
     path::{Path, PathBuf},
-    sync::mpsc::Sender,
+    sync::Arc,
     thread,
-    time::Duration,
 };
+use walkdir::WalkDir;
+
+#[derive(Clone)]
+pub struct CopyStrategy {
+    vfs: Arc<dyn Vfs>,
+}
+
+impl CopyStrategy {
+    /// Creates a strategy copying the files on the `vfs`.
+    pub fn new(vfs: Arc<dyn Vfs>) -> Self {
+        CopyStrategy { vfs }
+    }
+}
 
-pub struct CopyStrategy;
+impl Default for CopyStrategy {
+    fn default() -> Self {
+        CopyStrategy::new(Arc::new(LocalFs))
+    }
+}
 
 impl TransferStrategy for CopyStrategy {
     fn transfer_dir<P: AsRef<Path>>(
         &mut self,
         source: P,
         destination: P,
-        tx: Sender<super::TransferProgress>,
+        options: TransferOptions,
+        mut channel: TransferChannel,
     ) {
-        let mut options = DirCopyOptions::new();
-        options.buffer_size = 8 * 1024 * 1024; // TODO: configurable buffer, default is 1MB
         let from = PathBuf::from(source.as_ref());
         let to = PathBuf::from(destination.as_ref());
+        let vfs = Arc::clone(&self.vfs);
 
         thread::spawn(move || {
-            let progress_handler = |progress_info: DirTransitProcess| {
-                if tx
-                    .send(TransferProgress::DirTransfer(progress_info))
-                    .is_ok()
-                {}
-                fs_extra::dir::TransitProcessResult::ContinueOrAbort
+            let _result = match local_paths(vfs.as_ref(), &from, &to) {
+                Some((from, to)) => transfer_tree(&from, &to, options, &mut channel, false),
+                None => transfer_vfs(vfs.as_ref(), &from, &to, &mut channel, false),
             };
-            let _result = copy_dir_with_progress(
-                AsRef::<Path>::as_ref(&from),
-                AsRef::<Path>::as_ref(&to),
-                &options,
-                progress_handler,
-            );
+            channel.complete();
         });
     }
 
@@ -45,29 +63,663 @@ impl TransferStrategy for CopyStrategy {
         &mut self,
         source: P,
         destination: P,
-        tx: Sender<super::TransferProgress>,
+        options: TransferOptions,
+        mut channel: TransferChannel,
     ) {
-        let mut options = fs_extra::file::CopyOptions::new();
-        options.buffer_size = 8 * 1024 * 1024; // TODO: configurable buffer, default is 1MB
         let from = PathBuf::from(source.as_ref());
-        let file_name = from.file_name().unwrap();
-        let mut to = PathBuf::from(destination.as_ref());
-        to.push(Path::new(file_name));
+        let to = PathBuf::from(destination.as_ref());
+        let vfs = Arc::clone(&self.vfs);
 
         thread::spawn(move || {
-            let progress_handler = |progress_info: fs_extra::file::TransitProcess| {
-                if tx
-                    .send(TransferProgress::FileTransfer(progress_info))
-                    .is_ok()
-                {}
-                thread::sleep(Duration::from_millis(500));
+            let (from, to) = match local_paths(vfs.as_ref(), &from, &to) {
+                Some(paths) => paths,
+                None => {
+                    let _result = transfer_vfs(vfs.as_ref(), &from, &to, &mut channel, false);
+                    channel.complete();
+                    return;
+                }
             };
-            let _result = fs_extra::file::copy_with_progress(
-                AsRef::<Path>::as_ref(&from),
-                AsRef::<Path>::as_ref(&to),
-                &options,
-                progress_handler,
-            );
+            let action = target_action(&from, &to, &mut channel);
+            let result = channel.retry(&from, |channel| {
+                if !options.follow_symlinks && from.is_symlink() {
+                    return transfer_symlink(&from, &to, action.clone(), false);
+                }
+                let progress_handler = file_progress_handler(&from, channel);
+                transfer_file(
+                    &from,
+                    &to,
+                    action.clone(),
+                    false,
+                    options.verify,
+                    channel.control(),
+                    progress_handler,
+                )
+            });
+            if let (Ok(true), ConflictAction::WriteTo(target)) = (result, &action) {
+                channel.created(target);
+            }
+            channel.complete();
         });
     }
+
+    fn vfs(&self) -> &dyn Vfs {
+        self.vfs.as_ref()
+    }
+
+    fn can_follow_symlinks(&self) -> bool {
+        true
+    }
+
+    fn can_verify(&self) -> bool {
+        true
+    }
+
+    fn journal_operation(
+        &self,
+        _source: &Path,
+        target: &Path,
+        created: CreatedFiles,
+    ) -> Option<Operation> {
+        Some(Operation::Copy {
+            to: target.to_path_buf(),
+            created,
+        })
+    }
+}
+
+/// Returns the local paths of `from` and `to` on the `vfs`,
+/// None unless both are on the local file system, then the files are streamed by `transfer_vfs()`.
+pub(super) fn local_paths(vfs: &dyn Vfs, from: &Path, to: &Path) -> Option<(PathBuf, PathBuf)> {
+    Some((vfs.local_path(from)?, vfs.local_path(to)?))
+}
+
+/// Returns a progress handler reporting the transfer of the single file `from`.
+pub(super) fn file_progress_handler<'a>(
+    from: &Path,
+    channel: &'a TransferChannel,
+) -> impl FnMut(u64) + 'a {
+    let total_bytes = fs::metadata(from)
+        .map(|metadata| metadata.len())
+        .unwrap_or(0);
+    let file_name = from.display().to_string();
+    move |copied_bytes| {
+        channel.send(TransferProgress::Progress(CopyProgress {
+            copied_files: (copied_bytes == total_bytes) as u64,
+            total_files: 1,
+            copied_bytes,
+            total_bytes,
+            file_bytes_copied: copied_bytes,
+            file_total_bytes: total_bytes,
+            file_name: file_name.clone(),
+        }))
+    }
+}
+
+/// Decides where to write the file `from`: to `to` if it does not exist,
+/// otherwise the user is asked through the `channel`.
+pub(super) fn target_action(
+    from: &Path,
+    to: &Path,
+    channel: &mut TransferChannel,
+) -> ConflictAction {
+    match fs::symlink_metadata(to) {
+        Ok(_) => channel.resolve_conflict(from, to),
+        Err(_) => ConflictAction::WriteTo(PathBuf::from(to)),
+    }
+}
+
+/// Copies the file `from` to `to` by the `action`, then removes `from` if `remove_source` is set.
+/// The copy is compared to the source if `verify` is set, a mismatch is returned as an error.
+/// A moved file is renamed, it is copied then removed only if `to` is on another file system.
+pub(super) fn transfer_file<F>(
+    from: &Path,
+    to: &Path,
+    action: ConflictAction,
+    remove_source: bool,
+    verify: bool,
+    control: &TransferControl,
+    progress_handler: F,
+) -> io::Result<()>
+where
+    F: FnMut(u64),
+{
+    let target = match action {
+        ConflictAction::Skip => return Ok(()),
+        ConflictAction::WriteTo(target) => target,
+        ConflictAction::Append => {
+            append_missing(from, to)?;
+            if remove_source {
+                fs::remove_file(from)?;
+            }
+            return Ok(());
+        }
+    };
+
+    if remove_source && rename(from, &target)? {
+        return Ok(());
+    }
+    copy_file(from, &target, control, progress_handler)?;
+    if verify {
+        verify_copy(from, &target, control)?;
+    }
+    if remove_source {
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+/// Creates a symbolic link pointing to the same path as the link `from` does, by the `action`,
+/// then removes `from` if `remove_source` is set.
+pub(super) fn transfer_symlink(
+    from: &Path,
+    to: &Path,
+    action: ConflictAction,
+    remove_source: bool,
+) -> io::Result<()> {
+    let target = match action {
+        ConflictAction::Skip => return Ok(()),
+        ConflictAction::WriteTo(target) => target,
+        // there is nothing to append to a link, it is replaced
+        ConflictAction::Append => PathBuf::from(to),
+    };
+    if remove_source && rename(from, &target)? {
+        return Ok(());
+    }
+    copy_symlink(from, &target)?;
+    if remove_source {
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+/// Renames `from` to `to`. Returns false if they are on different file systems,
+/// so `from` has to be copied then removed instead.
+fn rename(from: &Path, to: &Path) -> io::Result<bool> {
+    match fs::rename(from, to) {
+        Ok(()) => Ok(true),
+        Err(error) if error.raw_os_error() == Some(libc::EXDEV) => Ok(false),
+        Err(error) => Err(error),
+    }
+}
+
+/// Counts the files to transfer in the `from` directory and their size in bytes.
+fn scan_tree(from: &Path, follow_symlinks: bool) -> (u64, u64) {
+    WalkDir::new(from)
+        .follow_links(follow_symlinks)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| !entry.file_type().is_dir())
+        .fold((0, 0), |(files, bytes), entry| {
+            let size = match entry.file_type().is_file() {
+                true => entry.metadata().map(|metadata| metadata.len()).unwrap_or(0),
+                false => 0,
+            };
+            (files + 1, bytes + size)
+        })
+}
+
+/// Copies the `from` directory to the `root` path, asking the user about every existing target.
+/// Symbolic links found in the tree are re-created on the destination,
+/// unless `TransferOptions::follow_symlinks` is set. The created directories
+/// get the attributes of their sources.
+/// The transferred files are removed from the source if `remove_source` is set,
+/// along with the directories became empty. A directory moved to a new path
+/// on the same file system is renamed at once instead.
+/// The user is asked what to do with every file failed to transfer.
+pub(super) fn transfer_tree(
+    from: &Path,
+    root: &Path,
+    options: TransferOptions,
+    channel: &mut TransferChannel,
+    remove_source: bool,
+) -> Result<(), TransferAborted> {
+    let follow_symlinks = options.follow_symlinks;
+    if from.is_symlink() && !follow_symlinks {
+        let action = target_action(from, root, channel);
+        let transferred = channel.retry(from, |_| {
+            transfer_symlink(from, root, action.clone(), remove_source)
+        })?;
+        if let (true, ConflictAction::WriteTo(target)) = (transferred, &action) {
+            channel.created(target);
+        }
+        return Ok(());
+    }
+
+    if remove_source && !follow_symlinks && fs::symlink_metadata(root).is_err() {
+        let mut renamed = false;
+        let transferred = channel.retry(from, |_| {
+            renamed = rename(from, root)?;
+            Ok(())
+        })?;
+        if renamed || !transferred {
+            return Ok(());
+        }
+    }
+
+    let (total_files, total_bytes) = scan_tree(from, follow_symlinks);
+    let mut progress = CopyProgress {
+        total_files,
+        total_bytes,
+        ..CopyProgress::default()
+    };
+    channel.send(TransferProgress::Progress(progress.clone()));
+
+    // the attributes of the created directories are set once their content is written,
+    // a read-only directory could not be filled and writing in it changes its timestamps
+    let mut directories = Vec::new();
+    let mut entries = WalkDir::new(from).follow_links(follow_symlinks).into_iter();
+    while let Some(entry) = entries.next() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(error) => {
+                // a failed directory listing cannot be retried, it is skipped
+                let path = PathBuf::from(error.path().unwrap_or(from));
+                let answer = channel.report_error(&path, &io::Error::from(error));
+                if answer == ErrorAnswer::Abort {
+                    return Err(TransferAborted);
+                }
+                continue;
+            }
+        };
+        let relative_path = entry.path().strip_prefix(from).unwrap_or(entry.path());
+        let target = root.join(relative_path);
+        let file_type = entry.file_type();
+
+        if file_type.is_dir() {
+            let created = fs::symlink_metadata(&target).is_err();
+            if !channel.retry(entry.path(), |_| fs::create_dir_all(&target))? {
+                entries.skip_current_dir();
+            } else if created {
+                channel.created(&target);
+                if let Ok(metadata) = entry.metadata() {
+                    directories.push((target, metadata));
+                }
+            }
+            continue;
+        }
+
+        let action = target_action(entry.path(), &target, channel);
+        progress.file_name = entry.file_name().to_string_lossy().to_string();
+        let transferred = if file_type.is_symlink() {
+            channel.retry(entry.path(), |_| {
+                transfer_symlink(entry.path(), &target, action.clone(), remove_source)
+            })?
+        } else {
+            progress.file_total_bytes =
+                entry.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+            let transferred = channel.retry(entry.path(), |channel| {
+                let progress_handler = |file_bytes_copied| {
+                    channel.send(TransferProgress::Progress(CopyProgress {
+                        copied_bytes: progress.copied_bytes + file_bytes_copied,
+                        file_bytes_copied,
+                        ..progress.clone()
+                    }));
+                };
+                transfer_file(
+                    entry.path(),
+                    &target,
+                    action.clone(),
+                    remove_source,
+                    options.verify,
+                    channel.control(),
+                    progress_handler,
+                )
+            })?;
+            progress.copied_bytes += progress.file_total_bytes;
+            transferred
+        };
+        if let (true, ConflictAction::WriteTo(target)) = (transferred, &action) {
+            channel.created(target);
+        }
+        progress.copied_files += 1;
+        channel.send(TransferProgress::Progress(progress.clone()));
+    }
+    // the directories were created parents first, their contents are done in reverse
+    for (directory, metadata) in directories.iter().rev() {
+        channel.retry(directory, |_| preserve_attributes(directory, metadata))?;
+    }
+
+    if remove_source {
+        for entry in WalkDir::new(from)
+            .contents_first(true)
+            .into_iter()
+            .flatten()
+        {
+            if entry.file_type().is_dir() {
+                // skipped files are left in their directories
+                let _ = fs::remove_dir(entry.path());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Copies the file or directory `from` to `to` on a `vfs` without local paths,
+/// asking the user about every existing target, then removes `from` if `remove_source` is set.
+/// The files are streamed through the `vfs`, their attributes are not preserved.
+/// A moved file or directory is renamed, the file system has no devices to cross.
+/// The user is asked what to do with every file failed to transfer.
+pub(super) fn transfer_vfs(
+    vfs: &dyn Vfs,
+    from: &Path,
+    to: &Path,
+    channel: &mut TransferChannel,
+    remove_source: bool,
+) -> Result<(), TransferAborted> {
+    let mut source = None;
+    if !channel.retry(from, |_| {
+        source = Some(vfs.symlink_stat(from)?);
+        Ok(())
+    })? {
+        return Ok(());
+    }
+    let source = match source {
+        Some(source) => source,
+        None => return Ok(()),
+    };
+
+    if source.is_dir() {
+        if remove_source && !vfs.exists(to) {
+            channel.retry(from, |_| vfs.rename(from, to))?;
+            return Ok(());
+        }
+        let created = !vfs.exists(to);
+        if !channel.retry(from, |_| vfs.mkdir_all(to))? {
+            return Ok(());
+        }
+        if created {
+            channel.created(to);
+        }
+        let mut files = Vec::new();
+        let filter_options = FilterOptions {
+            show_hidden_files: true,
+        };
+        if !channel.retry(from, |_| {
+            files = vfs.list(from, &filter_options)?;
+            Ok(())
+        })? {
+            return Ok(());
+        }
+        for file in files {
+            transfer_vfs(
+                vfs,
+                &from.join(&file.name),
+                &to.join(&file.name),
+                channel,
+                remove_source,
+            )?;
+        }
+        if remove_source {
+            // skipped files are left in their directories
+            let _ = vfs.remove(from);
+        }
+        return Ok(());
+    }
+
+    let action = match vfs.symlink_stat(to) {
+        Ok(target) => {
+            let mut conflict = FileConflict::new(from, to);
+            conflict.source_size = source.size;
+            conflict.source_modified = source.modified;
+            conflict.target_size = target.size;
+            conflict.target_modified = target.modified;
+            channel.resolve_file_conflict(conflict)
+        }
+        Err(_) => ConflictAction::WriteTo(PathBuf::from(to)),
+    };
+    let target = match action {
+        ConflictAction::Skip => return Ok(()),
+        ConflictAction::WriteTo(target) => target,
+        ConflictAction::Append => {
+            channel.retry(from, |_| {
+                Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "appending is not supported on this file system",
+                ))
+            })?;
+            return Ok(());
+        }
+    };
+
+    let file_name = from.display().to_string();
+    let transferred = channel.retry(from, |channel| {
+        // an overwritten file is replaced, a directory in the way is reported
+        if vfs.symlink_stat(&target).is_ok_and(|stat| !stat.is_dir()) {
+            vfs.remove(&target)?;
+        }
+        if remove_source {
+            return vfs.rename(from, &target);
+        }
+        let mut reader = vfs.open_read(from)?;
+        let mut writer = vfs.open_write(&target)?;
+        let copied_bytes = io::copy(&mut reader, &mut writer)?;
+        writer.flush()?;
+        channel.send(TransferProgress::Progress(CopyProgress {
+            copied_files: 1,
+            total_files: 1,
+            copied_bytes,
+            total_bytes: source.size,
+            file_bytes_copied: copied_bytes,
+            file_total_bytes: source.size,
+            file_name: file_name.clone(),
+        }));
+        Ok(())
+    })?;
+    if transferred {
+        channel.created(&target);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::transfer_tree;
+    use crate::{
+        core::{conflict::ConflictResolution, test_dir::TestDir},
+        ui::{
+            ErrorAnswer, TransferAborted, TransferAnswer, TransferChannel, TransferOptions,
+            TransferProgress,
+        },
+    };
+    use std::fs::{self, File, FileTimes, Permissions};
+    use std::os::unix::fs::{symlink, MetadataExt, PermissionsExt};
+    use std::path::PathBuf;
+    use std::sync::{mpsc, Arc};
+    use std::time::{Duration, SystemTime};
+
+    fn setup(name: &str) -> TestDir {
+        let root = TestDir::new(name);
+        fs::create_dir_all(root.join("source/sub")).unwrap();
+        fs::create_dir_all(root.join("destination")).unwrap();
+        fs::write(root.join("source/sub/file.txt"), "content").unwrap();
+        root
+    }
+
+    fn channel(conflict_resolution: Option<ConflictResolution>) -> TransferChannel {
+        let (progress_tx, _progress_rx) = mpsc::channel();
+        let (_answer_tx, answer_rx) = mpsc::channel();
+        TransferChannel::new(progress_tx, answer_rx, conflict_resolution, Arc::default())
+    }
+
+    #[test]
+    fn test_transfer_tree_preserves_links() {
+        let root = setup("links");
+        let source = root.join("source");
+        symlink("sub", source.join("link_to_sub")).unwrap();
+
+        let copied = root.join("destination/source");
+        transfer_tree(
+            &source,
+            &copied,
+            TransferOptions::default(),
+            &mut channel(None),
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(copied.join("sub/file.txt")).unwrap(),
+            "content"
+        );
+        assert!(copied.join("link_to_sub").is_symlink());
+        assert_eq!(
+            fs::read_link(copied.join("link_to_sub")).unwrap(),
+            PathBuf::from("sub")
+        );
+    }
+
+    #[test]
+    fn test_transfer_tree_preserves_directory_attributes() {
+        let root = setup("directory-attributes");
+        let sub = root.join("source/sub");
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        fs::set_permissions(&sub, Permissions::from_mode(0o700)).unwrap();
+        File::open(&sub)
+            .unwrap()
+            .set_times(FileTimes::new().set_modified(modified))
+            .unwrap();
+
+        let copied = root.join("destination/source");
+        transfer_tree(
+            &root.join("source"),
+            &copied,
+            TransferOptions::default(),
+            &mut channel(None),
+            false,
+        )
+        .unwrap();
+
+        // the timestamps are set after the file is written in the directory
+        let metadata = fs::metadata(copied.join("sub")).unwrap();
+        assert_eq!(metadata.mode() & 0o777, 0o700);
+        assert_eq!(metadata.modified().unwrap(), modified);
+        assert_eq!(
+            fs::read_to_string(copied.join("sub/file.txt")).unwrap(),
+            "content"
+        );
+    }
+
+    #[test]
+    fn test_moved_tree_is_renamed() {
+        let root = setup("rename");
+        let source = root.join("source");
+        let inode = fs::metadata(&source).unwrap().ino();
+
+        let moved = root.join("destination/source");
+        transfer_tree(
+            &source,
+            &moved,
+            TransferOptions::default(),
+            &mut channel(None),
+            true,
+        )
+        .unwrap();
+
+        assert!(!source.exists());
+        assert_eq!(fs::metadata(&moved).unwrap().ino(), inode);
+        assert_eq!(
+            fs::read_to_string(moved.join("sub/file.txt")).unwrap(),
+            "content"
+        );
+    }
+
+    #[test]
+    fn test_transfer_tree_resolves_conflicts() {
+        let root = setup("conflicts");
+        let copied = root.join("destination/source");
+        fs::create_dir_all(copied.join("sub")).unwrap();
+        fs::write(copied.join("sub/file.txt"), "old").unwrap();
+
+        transfer_tree(
+            &root.join("source"),
+            &copied,
+            TransferOptions::default(),
+            &mut channel(Some(ConflictResolution::Skip)),
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(copied.join("sub/file.txt")).unwrap(),
+            "old"
+        );
+
+        transfer_tree(
+            &root.join("source"),
+            &copied,
+            TransferOptions::default(),
+            &mut channel(Some(ConflictResolution::Rename)),
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(copied.join("sub/file (1).txt")).unwrap(),
+            "content"
+        );
+
+        // a dialog gone away skips the conflicting files
+        transfer_tree(
+            &root.join("source"),
+            &copied,
+            TransferOptions::default(),
+            &mut channel(None),
+            true,
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(copied.join("sub/file.txt")).unwrap(),
+            "old"
+        );
+        assert!(root.join("source/sub/file.txt").exists());
+    }
+
+    #[test]
+    fn test_transfer_tree_reports_errors() {
+        let root = setup("errors");
+        fs::write(root.join("source/sub/other.txt"), "other").unwrap();
+        let copied = root.join("destination/source");
+        // a directory in the way of a file cannot be overwritten
+        fs::create_dir_all(copied.join("sub/file.txt")).unwrap();
+
+        let (progress_tx, progress_rx) = mpsc::channel();
+        let (answer_tx, answer_rx) = mpsc::channel();
+        answer_tx
+            .send(TransferAnswer::Error(ErrorAnswer::SkipAll))
+            .unwrap();
+        let mut skipping_channel = TransferChannel::new(
+            progress_tx,
+            answer_rx,
+            Some(ConflictResolution::Overwrite),
+            Arc::default(),
+        );
+        let result = transfer_tree(
+            &root.join("source"),
+            &copied,
+            TransferOptions::default(),
+            &mut skipping_channel,
+            false,
+        );
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            fs::read_to_string(copied.join("sub/other.txt")).unwrap(),
+            "other"
+        );
+        let failed: Vec<PathBuf> = progress_rx
+            .try_iter()
+            .filter_map(|progress| match progress {
+                TransferProgress::Error(error) => Some(error.path),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(failed, vec![root.join("source/sub/file.txt")]);
+
+        // the transfer is aborted if nobody answers
+        let result = transfer_tree(
+            &root.join("source"),
+            &copied,
+            TransferOptions::default(),
+            &mut channel(Some(ConflictResolution::Overwrite)),
+            false,
+        );
+        assert_eq!(result, Err(TransferAborted));
+    }
 }
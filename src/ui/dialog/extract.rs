@@ -0,0 +1,435 @@
+use super::{
+    ErrorAnswer, TransferAborted, TransferChannel, TransferOptions, TransferProgress,
+    TransferStrategy,
+};
+use crate::core::{
+    archive::{
+        extract_file, extract_symlink, extraction_target, ArchiveEntry, ArchiveIndex, EntryKind,
+    },
+    conflict::{ConflictAction, FileConflict},
+    copy::CopyProgress,
+    journal::{CreatedFiles, Operation},
+};
+use std::{
+    fs,
+    io::{self, Read},
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+};
+
+/// Copies the members of an archive browsed in a panel out to the file system.
+/// The sources are the paths of the members under the archive file,
+/// e.g. `/home/user/archive.zip/dir/file`, the archive file itself stands for all of them.
+#[derive(Clone)]
+pub struct ExtractStrategy {
+    archive: Arc<ArchiveIndex>,
+    unpack: bool,
+}
+
+impl ExtractStrategy {
+    pub fn new(archive: Arc<ArchiveIndex>) -> Self {
+        ExtractStrategy {
+            archive,
+            unpack: false,
+        }
+    }
+
+    /// Extracts all the members of the `archive` into the destination directory.
+    pub fn unpack(archive: Arc<ArchiveIndex>) -> Self {
+        ExtractStrategy {
+            archive,
+            unpack: true,
+        }
+    }
+
+    fn extract(&self, source: &Path, destination: &Path, mut channel: TransferChannel) {
+        let archive = Arc::clone(&self.archive);
+        let member = PathBuf::from(archive.member_path(source).unwrap_or(source));
+        let root = PathBuf::from(destination);
+        thread::spawn(move || {
+            let _result = extract_tree(&archive, &member, &root, &mut channel);
+            channel.complete();
+        });
+    }
+}
+
+impl TransferStrategy for ExtractStrategy {
+    fn transfer_dir<P: AsRef<Path>>(
+        &mut self,
+        source: P,
+        destination: P,
+        _options: TransferOptions,
+        channel: TransferChannel,
+    ) {
+        self.extract(source.as_ref(), destination.as_ref(), channel);
+    }
+
+    fn transfer_file<P: AsRef<Path>>(
+        &mut self,
+        source: P,
+        destination: P,
+        _options: TransferOptions,
+        channel: TransferChannel,
+    ) {
+        self.extract(source.as_ref(), destination.as_ref(), channel);
+    }
+
+    fn source_is_dir(&self, source: &Path) -> Option<bool> {
+        let member = self.archive.member_path(source)?;
+        match member.as_os_str().is_empty() {
+            true => Some(true),
+            false => self.archive.entry(member).map(ArchiveEntry::is_dir),
+        }
+    }
+
+    fn transfers_contents(&self) -> bool {
+        self.unpack
+    }
+
+    fn journal_operation(
+        &self,
+        _source: &Path,
+        target: &Path,
+        created: CreatedFiles,
+    ) -> Option<Operation> {
+        Some(Operation::Copy {
+            to: target.to_path_buf(),
+            created,
+        })
+    }
+}
+
+/// Extracts the `member` of the `archive` with the members under it to the `root` path,
+/// e.g. the `dir/file` member of `dir` to `root/file`. The whole archive is extracted
+/// into `root` if `member` is empty.
+/// The user is asked about every existing target and every member failed to extract.
+/// A member which would be written through a symbolic link is not extracted.
+pub(super) fn extract_tree(
+    archive: &ArchiveIndex,
+    member: &Path,
+    root: &Path,
+    channel: &mut TransferChannel,
+) -> Result<(), TransferAborted> {
+    let (total_files, total_bytes) = archive
+        .tree(member)
+        .filter(|entry| !entry.is_dir())
+        .fold((0, 0), |(files, bytes), entry| {
+            (files + 1, bytes + entry.size)
+        });
+    let mut progress = CopyProgress {
+        total_files,
+        total_bytes,
+        ..CopyProgress::default()
+    };
+    channel.send(TransferProgress::Progress(progress.clone()));
+
+    let mut result = Ok(());
+    let mut skipped_dirs: Vec<PathBuf> = Vec::new();
+    let read_result = archive.for_each_member_in(member, |entry, reader| {
+        if skipped_dirs.iter().any(|dir| entry.path.starts_with(dir)) {
+            return ControlFlow::Continue(());
+        }
+        progress.file_name = entry.path.display().to_string();
+        progress.file_total_bytes = entry.size;
+        let extraction = Extraction {
+            archive,
+            member,
+            root,
+            progress: &progress,
+        };
+        match extraction.extract(&entry, reader, channel) {
+            Ok(false) if entry.is_dir() => skipped_dirs.push(entry.path.clone()),
+            Ok(_) => {}
+            Err(TransferAborted) => {
+                result = Err(TransferAborted);
+                return ControlFlow::Break(());
+            }
+        }
+        if !entry.is_dir() {
+            progress.copied_files += 1;
+            progress.copied_bytes += entry.size;
+            channel.send(TransferProgress::Progress(progress.clone()));
+        }
+        ControlFlow::Continue(())
+    });
+
+    // a damaged archive cannot be read any further
+    if let Err(error) = read_result {
+        if channel.report_error(archive.path(), &error) == ErrorAnswer::Abort {
+            return Err(TransferAborted);
+        }
+    }
+    result
+}
+
+/// The extraction of the members under `member` to `root`.
+struct Extraction<'a> {
+    archive: &'a ArchiveIndex,
+    member: &'a Path,
+    root: &'a Path,
+    /// The progress of the whole extraction before the current member.
+    progress: &'a CopyProgress,
+}
+
+impl Extraction<'_> {
+    /// Extracts the `entry` read from `reader`. Returns false if the user skipped it.
+    fn extract(
+        &self,
+        entry: &ArchiveEntry,
+        reader: &mut dyn Read,
+        channel: &mut TransferChannel,
+    ) -> Result<bool, TransferAborted> {
+        let source = self.archive.path().join(&entry.path);
+        let relative = entry.path.strip_prefix(self.member).unwrap_or(&entry.path);
+        let target = match extraction_target(self.root, relative) {
+            Ok(target) => target,
+            Err(error) => {
+                return match channel.report_error(&source, &error) {
+                    ErrorAnswer::Abort => Err(TransferAborted),
+                    _ => Ok(false),
+                };
+            }
+        };
+        if entry.is_dir() {
+            let missing = missing_paths(&target);
+            let created = channel.retry(&source, |_| fs::create_dir_all(&target))?;
+            note_created(&missing, channel);
+            return Ok(created);
+        }
+
+        let action = match fs::symlink_metadata(&target) {
+            Ok(_) => {
+                let mut conflict = FileConflict::new(&source, &target);
+                conflict.source_size = entry.size;
+                conflict.source_modified = entry.modified;
+                channel.resolve_file_conflict(conflict)
+            }
+            Err(_) => ConflictAction::WriteTo(target.clone()),
+        };
+        let (target, append) = match action {
+            ConflictAction::Skip => return Ok(false),
+            ConflictAction::WriteTo(target) => (target, false),
+            ConflictAction::Append => (target, true),
+        };
+
+        // the content is streamed only once, it is read from the archive again to retry
+        let mut stream = Some(reader);
+        let missing = missing_paths(&target);
+        let extracted = channel.retry(&source, |channel| {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            match stream.take() {
+                Some(reader) => self.write(entry, reader, &target, append, channel),
+                None => self.archive.read_member(&entry.path, |reader| {
+                    self.write(entry, reader, &target, append, channel)
+                }),
+            }
+        })?;
+        note_created(&missing, channel);
+        Ok(extracted)
+    }
+
+    /// Writes the `entry` read from `reader` to `target` by its kind.
+    /// A hard link is linked to the file its target member was extracted to,
+    /// or the content of the target member is written if it was not extracted.
+    fn write(
+        &self,
+        entry: &ArchiveEntry,
+        reader: &mut dyn Read,
+        target: &Path,
+        append: bool,
+        channel: &TransferChannel,
+    ) -> io::Result<()> {
+        match &entry.kind {
+            EntryKind::Dir => fs::create_dir_all(target),
+            EntryKind::File => self.write_file(entry, reader, target, append, channel),
+            EntryKind::Symlink(link_target) => extract_symlink(link_target, target),
+            EntryKind::HardLink(linked) => {
+                let extracted = linked
+                    .strip_prefix(self.member)
+                    .ok()
+                    .map(|relative| self.root.join(relative))
+                    .filter(|extracted| extracted.is_file());
+                if let Some(extracted) = extracted {
+                    if fs::symlink_metadata(target).is_ok() {
+                        fs::remove_file(target)?;
+                    }
+                    return fs::hard_link(extracted, target);
+                }
+                let linked_entry = self.archive.entry(linked).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("{} is not in the archive", linked.display()),
+                    )
+                })?;
+                self.archive.read_member(linked, |reader| {
+                    self.write_file(linked_entry, reader, target, append, channel)
+                })
+            }
+        }
+    }
+
+    fn write_file(
+        &self,
+        entry: &ArchiveEntry,
+        reader: &mut dyn Read,
+        target: &Path,
+        append: bool,
+        channel: &TransferChannel,
+    ) -> io::Result<()> {
+        let progress_handler = |file_bytes_copied| {
+            channel.send(TransferProgress::Progress(CopyProgress {
+                copied_bytes: self.progress.copied_bytes + file_bytes_copied,
+                file_bytes_copied,
+                ..self.progress.clone()
+            }));
+        };
+        extract_file(
+            entry,
+            reader,
+            target,
+            append,
+            channel.control(),
+            progress_handler,
+        )
+    }
+}
+
+/// Returns `path` and its parent directories which do not exist, the parents first.
+fn missing_paths(path: &Path) -> Vec<PathBuf> {
+    let mut missing: Vec<PathBuf> = path
+        .ancestors()
+        .take_while(|ancestor| fs::symlink_metadata(ancestor).is_err())
+        .map(PathBuf::from)
+        .collect();
+    missing.reverse();
+    missing
+}
+
+/// Notes the `missing` paths the extraction of a member created on the `channel`.
+fn note_created(missing: &[PathBuf], channel: &mut TransferChannel) {
+    for path in missing {
+        if fs::symlink_metadata(path).is_ok() {
+            channel.created(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::extract_tree;
+    use crate::{
+        core::{archive::ArchiveIndex, conflict::ConflictResolution, test_dir::TestDir},
+        ui::{ErrorAnswer, TransferAnswer, TransferChannel},
+    };
+    use std::{
+        fs::{self, File},
+        os::unix::fs::{symlink, MetadataExt, PermissionsExt},
+        path::Path,
+        sync::{mpsc, Arc},
+    };
+
+    fn setup(name: &str) -> TestDir {
+        let root = TestDir::new(name);
+        fs::create_dir_all(root.join("destination")).unwrap();
+
+        let mut builder = tar::Builder::new(File::create(root.join("release.tar")).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(7);
+        header.set_mode(0o755);
+        builder
+            .append_data(&mut header, "release/bin/run", &b"#!/bin/sh"[..7])
+            .unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        builder
+            .append_link(&mut header, "release/run", "bin/run")
+            .unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Link);
+        header.set_size(0);
+        builder
+            .append_link(&mut header, "release/bin/start", "release/bin/run")
+            .unwrap();
+        builder.finish().unwrap();
+        root
+    }
+
+    fn channel(
+        conflict_resolution: Option<ConflictResolution>,
+        answers: &[ErrorAnswer],
+    ) -> TransferChannel {
+        let (progress_tx, _progress_rx) = mpsc::channel();
+        let (answer_tx, answer_rx) = mpsc::channel();
+        for answer in answers {
+            answer_tx.send(TransferAnswer::Error(*answer)).unwrap();
+        }
+        TransferChannel::new(progress_tx, answer_rx, conflict_resolution, Arc::default())
+    }
+
+    #[test]
+    fn test_extract_tree() {
+        let root = setup("tree");
+        let archive = ArchiveIndex::open(&root.join("release.tar")).unwrap();
+        let extracted = root.join("destination/release");
+
+        extract_tree(
+            &archive,
+            Path::new("release"),
+            &extracted,
+            &mut channel(None, &[]),
+        )
+        .unwrap();
+        let run = extracted.join("bin/run");
+        assert_eq!(fs::read_to_string(&run).unwrap(), "#!/bin/");
+        assert_eq!(
+            fs::metadata(&run).unwrap().permissions().mode() & 0o777,
+            0o755
+        );
+        assert_eq!(
+            fs::read_link(extracted.join("run")).unwrap(),
+            Path::new("bin/run")
+        );
+        assert_eq!(
+            fs::metadata(extracted.join("bin/start")).unwrap().ino(),
+            fs::metadata(&run).unwrap().ino()
+        );
+
+        // a single file, with the existing target renamed
+        extract_tree(
+            &archive,
+            Path::new("release/bin/start"),
+            &run,
+            &mut channel(Some(ConflictResolution::Rename), &[]),
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(extracted.join("bin/run (1)")).unwrap(),
+            "#!/bin/"
+        );
+    }
+
+    #[test]
+    fn test_nothing_is_extracted_through_links() {
+        let root = setup("links");
+        let archive = ArchiveIndex::open(&root.join("release.tar")).unwrap();
+        let extracted = root.join("destination/release");
+        fs::create_dir_all(root.join("outside")).unwrap();
+        fs::create_dir_all(&extracted).unwrap();
+        symlink("../../outside", extracted.join("bin")).unwrap();
+
+        extract_tree(
+            &archive,
+            Path::new("release"),
+            &extracted,
+            &mut channel(None, &[ErrorAnswer::SkipAll]),
+        )
+        .unwrap();
+        assert!(fs::read_dir(root.join("outside")).unwrap().next().is_none());
+        assert!(extracted.join("run").is_symlink());
+    }
+}
@@ -78,6 +78,10 @@ impl HelpDialog {
                     Span::styled("Change directory: ", style),
                     Span::styled("Enter", key_style),
                 ])),
+                Cell::from(Spans::from(vec![
+                    Span::styled("Undo: ", style),
+                    Span::styled("Ctrl+z", key_style),
+                ])),
             ]),
             Row::new(vec![
                 Cell::from(Spans::from(vec![
@@ -88,6 +92,10 @@ impl HelpDialog {
                     Span::styled("Move cursor: ", style),
                     Span::styled(" 🡅 🡇", key_style),
                 ])),
+                Cell::from(Spans::from(vec![
+                    Span::styled("New file: ", style),
+                    Span::styled("Shift+F4", key_style),
+                ])),
             ]),
             Row::new(vec![
                 Cell::from(Spans::from(vec![
@@ -98,6 +106,10 @@ impl HelpDialog {
                     Span::styled("Sort by name: ", style),
                     Span::styled("Ctrl+n", key_style),
                 ])),
+                Cell::from(Spans::from(vec![
+                    Span::styled("View file: ", style),
+                    Span::styled("F3", key_style),
+                ])),
             ]),
             Row::new(vec![
                 Cell::from(""),
@@ -105,6 +117,10 @@ impl HelpDialog {
                     Span::styled("Sort by size: ", style),
                     Span::styled("Ctrl+s", key_style),
                 ])),
+                Cell::from(Spans::from(vec![
+                    Span::styled("Pack files: ", style),
+                    Span::styled("Alt+F5", key_style),
+                ])),
             ]),
             Row::new(vec![
                 Cell::from(""),
@@ -112,6 +128,10 @@ impl HelpDialog {
                     Span::styled("Sort by last modified time: ", style),
                     Span::styled("Ctrl+l", key_style),
                 ])),
+                Cell::from(Spans::from(vec![
+                    Span::styled("Unpack archive: ", style),
+                    Span::styled("Alt+F9", key_style),
+                ])),
             ]),
             Row::new(vec![
                 Cell::from(""),
@@ -127,6 +147,27 @@ impl HelpDialog {
                     Span::styled("Ctrl+d", key_style),
                 ])),
             ]),
+            Row::new(vec![
+                Cell::from(""),
+                Cell::from(Spans::from(vec![
+                    Span::styled("Mark/unmark file: ", style),
+                    Span::styled("Insert", key_style),
+                ])),
+            ]),
+            Row::new(vec![
+                Cell::from(""),
+                Cell::from(Spans::from(vec![
+                    Span::styled("Rename file: ", style),
+                    Span::styled("Shift+F6", key_style),
+                ])),
+            ]),
+            Row::new(vec![
+                Cell::from(""),
+                Cell::from(Spans::from(vec![
+                    Span::styled("Delete permanently: ", style),
+                    Span::styled("Shift+F8", key_style),
+                ])),
+            ]),
         ];
 
         let table = Table::new(rows).header(header).widths(&[
@@ -152,6 +193,7 @@ impl HelpDialog {
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
 
@@ -0,0 +1,101 @@
+use crate::core::journal::Journal;
+use std::io::Stdout;
+use termion::{event::Key, raw::RawTerminal};
+use tui::{
+    backend::TermionBackend,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table},
+    Frame,
+};
+
+/// Lists the recent file operations recorded in the `Journal`, the latest first.
+///
+/// ## Key controls
+/// * u or Ctrl+Z: undoes the latest operation
+pub struct HistoryDialog {
+    message: Option<Result<String, String>>,
+}
+
+impl HistoryDialog {
+    pub fn new() -> Self {
+        HistoryDialog { message: None }
+    }
+
+    /// Opens the dialog with the result of an undo done outside of it.
+    pub fn with_error(error: String) -> Self {
+        HistoryDialog {
+            message: Some(Err(error)),
+        }
+    }
+
+    /// Handles the key, returns true if an operation was undone.
+    pub fn handle_key(&mut self, key: Key, journal: &mut Journal) -> bool {
+        match key {
+            Key::Char('u') | Key::Ctrl('z') => {
+                let result = journal.undo();
+                let undone = result.is_ok();
+                self.message =
+                    Some(result.map(|operation| format!("Undone: {}", operation.title())));
+                undone
+            }
+            _ => false,
+        }
+    }
+
+    pub fn render(
+        &self,
+        frame: &mut Frame<TermionBackend<RawTerminal<Stdout>>>,
+        area: Rect,
+        journal: &Journal,
+    ) {
+        let layout = Layout::default()
+            .constraints([
+                Constraint::Min(1),
+                Constraint::Length(1),
+                Constraint::Length(1),
+            ])
+            .margin(1)
+            .split(area);
+        let style = Style::default().fg(Color::White);
+        let key_style = Style::default().fg(Color::LightYellow);
+        let bold_style = style.add_modifier(Modifier::BOLD);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title("History")
+            .title_alignment(tui::layout::Alignment::Center);
+
+        let header = Row::new(vec![
+            Cell::from(Span::styled("Time", bold_style)),
+            Cell::from(Span::styled("Operation", bold_style)),
+        ]);
+        let rows = journal.entries().map(|entry| {
+            Row::new(vec![
+                Cell::from(entry.time.format("%H:%M:%S").to_string()),
+                Cell::from(entry.operation.title()),
+            ])
+            .style(style)
+        });
+        let table = Table::new(rows)
+            .header(header)
+            .widths(&[Constraint::Length(9), Constraint::Percentage(100)]);
+
+        let message = match &self.message {
+            Some(Ok(message)) => Span::styled(message.as_str(), Style::default().fg(Color::Green)),
+            Some(Err(error)) => Span::styled(error.as_str(), Style::default().fg(Color::LightRed)),
+            None => Span::raw(""),
+        };
+        let hints = Paragraph::new(Spans::from(vec![
+            Span::styled("Undo the latest: ", style),
+            Span::styled("u", key_style),
+            Span::styled(" or ", style),
+            Span::styled("Ctrl+Z", key_style),
+        ]));
+
+        frame.render_widget(block, area);
+        frame.render_widget(table, layout[0]);
+        frame.render_widget(Paragraph::new(message), layout[1]);
+        frame.render_widget(hints, layout[2]);
+    }
+}
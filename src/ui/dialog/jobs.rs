@@ -0,0 +1,117 @@
+use crate::ui::{JobManager, JobStatus};
+use std::io::Stdout;
+use termion::{event::Key, raw::RawTerminal};
+use tui::{
+    backend::TermionBackend,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState},
+    Frame,
+};
+
+/// Lists the background jobs of the `JobManager` with their progress.
+///
+/// ## Key controls
+/// * Up and Down: select a job
+/// * Delete: cancels the selected job
+/// * p: toggles running the jobs in parallel
+/// * x: removes the jobs which are over
+pub struct JobsDialog {
+    selected: usize,
+}
+
+impl JobsDialog {
+    pub fn new() -> Self {
+        JobsDialog { selected: 0 }
+    }
+
+    pub fn handle_key(&mut self, key: Key, jobs: &mut JobManager) {
+        match key {
+            Key::Up => self.selected = self.selected.saturating_sub(1),
+            Key::Down if self.selected + 1 < jobs.jobs().len() => self.selected += 1,
+            Key::Delete => {
+                if let Some(job) = jobs.job_mut(self.selected) {
+                    job.cancel();
+                }
+            }
+            Key::Char('p') => jobs.set_parallel(!jobs.is_parallel()),
+            Key::Char('x') => {
+                jobs.clear_finished();
+                self.selected = 0;
+            }
+            _ => {}
+        }
+    }
+
+    pub fn render(
+        &self,
+        frame: &mut Frame<TermionBackend<RawTerminal<Stdout>>>,
+        area: Rect,
+        jobs: &JobManager,
+    ) {
+        let layout = Layout::default()
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .margin(1)
+            .split(area);
+        let style = Style::default().fg(Color::White);
+        let key_style = Style::default().fg(Color::LightYellow);
+        let bold_style = style.add_modifier(Modifier::BOLD);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title("Jobs")
+            .title_alignment(tui::layout::Alignment::Center);
+
+        let header = Row::new(vec![
+            Cell::from(Span::styled("Status", bold_style)),
+            Cell::from(Span::styled("Progress", bold_style)),
+            Cell::from(Span::styled("Operation", bold_style)),
+        ]);
+        let rows = jobs.jobs().iter().map(|job| {
+            let status = match job.failures().len() {
+                0 => job.status().title().to_string(),
+                failures => format!("{} ({} failed)", job.status().title(), failures),
+            };
+            let row_style = match job.status() {
+                JobStatus::Failed => Style::default().fg(Color::LightRed),
+                JobStatus::Finished | JobStatus::Cancelled => Style::default().fg(Color::Gray),
+                _ => style,
+            };
+            Row::new(vec![
+                Cell::from(status),
+                Cell::from(format!("{}%", job.percentage())),
+                Cell::from(job.title().to_string()),
+            ])
+            .style(row_style)
+        });
+        let table = Table::new(rows)
+            .header(header)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .widths(&[
+                Constraint::Length(20),
+                Constraint::Length(9),
+                Constraint::Percentage(100),
+            ]);
+        let mut state = TableState::default();
+        if !jobs.jobs().is_empty() {
+            state.select(Some(self.selected));
+        }
+
+        let parallel = match jobs.is_parallel() {
+            true => "[X]",
+            false => "[ ]",
+        };
+        let hints = Paragraph::new(Spans::from(vec![
+            Span::styled(format!("{} Run in parallel: ", parallel), style),
+            Span::styled("p", key_style),
+            Span::styled("  Cancel: ", style),
+            Span::styled("Delete", key_style),
+            Span::styled("  Clear finished: ", style),
+            Span::styled("x", key_style),
+        ]));
+
+        frame.render_widget(block, area);
+        frame.render_stateful_widget(table, layout[0], &mut state);
+        frame.render_widget(hints, layout[1]);
+    }
+}
@@ -0,0 +1,254 @@
+use super::{display_errors, handle_input_keys};
+use crate::core::{
+    journal::Operation,
+    link::{create_link, LinkKind},
+    rename::validate_file_name,
+};
+use std::{
+    ffi::{OsStr, OsString},
+    path::{Path, PathBuf},
+};
+use termion::event::Key;
+use tui::{
+    layout::Alignment,
+    style::{Color, Style},
+    text::{Span, Spans, Text},
+    widgets::{Block, Borders, Paragraph},
+};
+use tui_input::Input;
+
+enum Buttons {
+    Ok,
+    Cancel,
+}
+
+impl Buttons {
+    fn next(&mut self) {
+        match *self {
+            Buttons::Ok => *self = Buttons::Cancel,
+            Buttons::Cancel => *self = Buttons::Ok,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Components {
+    Name,
+    Relative,
+    Buttons,
+}
+
+/// Represents the state of the dialog
+pub enum LinkDialogState {
+    WaitingForInput,
+    DisplayErrorMessages(Vec<String>),
+}
+
+/// A dialog for creating a symbolic or hard link to a file in another directory.
+///
+/// ## Key controls
+/// * Tab and Shift+Tab: focus the next/previous component
+/// * <- and -> : select an absolute or relative target of a symbolic link, or a button
+/// * Enter: create the link or cancel, depending on the focused button
+/// * Esc: closes the dialog
+pub struct LinkDialog {
+    button: Buttons,
+    created_link: Option<PathBuf>,
+    focused: Components,
+    input: Input,
+    kind: LinkKind,
+    link_dir: PathBuf,
+    relative: bool,
+    should_quit: bool,
+    state: LinkDialogState,
+    target: PathBuf,
+}
+
+impl LinkDialog {
+    /// Creates a new dialog for creating a link of `kind` to `target` in the `link_dir` directory.
+    /// The link is named after the target by default.
+    pub fn new<P, Q>(target: P, link_dir: Q, kind: LinkKind) -> Self
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let target = target.as_ref();
+        let name = target.file_name().unwrap_or_default().to_string_lossy();
+        LinkDialog {
+            button: Buttons::Ok,
+            created_link: None,
+            focused: Components::Name,
+            input: Input::new(name.to_string()),
+            kind,
+            link_dir: PathBuf::from(link_dir.as_ref()),
+            relative: false,
+            should_quit: false,
+            state: LinkDialogState::WaitingForInput,
+            target: PathBuf::from(target),
+        }
+    }
+
+    pub fn handle_key(&mut self, key: Key) {
+        if let LinkDialogState::DisplayErrorMessages(_) = self.state {
+            if key == Key::Char('\n') {
+                self.state = LinkDialogState::WaitingForInput;
+            }
+            return;
+        }
+
+        match key {
+            Key::Char('\n') => match self.button {
+                Buttons::Ok => self.create(),
+                Buttons::Cancel => self.should_quit = true,
+            },
+            Key::Char('\t') => self.focus_next(),
+            Key::BackTab => self.focus_previous(),
+            _ => match self.focused {
+                Components::Name => {
+                    handle_input_keys(&mut self.input, key, |char| !char.is_control())
+                }
+                Components::Relative => {
+                    if let Key::Left | Key::Right | Key::Char(' ') = key {
+                        self.relative = !self.relative;
+                    }
+                }
+                Components::Buttons => {
+                    if let Key::Left | Key::Right = key {
+                        self.button.next()
+                    }
+                }
+            },
+        }
+    }
+
+    /// Returns a representation based on the actual state of the dialog to render.
+    pub fn widget(&self) -> Paragraph<'_> {
+        match &self.state {
+            LinkDialogState::WaitingForInput => self.display_input(),
+            LinkDialogState::DisplayErrorMessages(messages) => display_errors(messages),
+        }
+    }
+
+    /// Returns the height of the dialog including its borders.
+    pub fn height(&self) -> u16 {
+        match self.kind {
+            LinkKind::Symbolic => 7,
+            LinkKind::Hard => 6,
+        }
+    }
+
+    /// Returns the operation to record in the `Journal` if the link was created.
+    pub fn completed_operation(&self) -> Option<Operation> {
+        self.created_link
+            .clone()
+            .map(|path| Operation::Link { path })
+    }
+
+    /// Returns the name of the created link.
+    pub fn created_name(&self) -> Option<OsString> {
+        self.created_link
+            .as_ref()
+            .and_then(|link| link.file_name())
+            .map(OsString::from)
+    }
+
+    /// Signals that the dialog should be closed or not
+    pub fn should_quit(&self) -> bool {
+        self.should_quit
+    }
+
+    /// Only a symbolic link has a relative target to choose.
+    fn focus_next(&mut self) {
+        self.focused = match (self.focused, self.kind) {
+            (Components::Name, LinkKind::Symbolic) => Components::Relative,
+            (Components::Name, LinkKind::Hard) => Components::Buttons,
+            (Components::Relative, _) => Components::Buttons,
+            (Components::Buttons, _) => Components::Name,
+        }
+    }
+
+    fn focus_previous(&mut self) {
+        self.focused = match (self.focused, self.kind) {
+            (Components::Name, _) => Components::Buttons,
+            (Components::Relative, _) => Components::Name,
+            (Components::Buttons, LinkKind::Symbolic) => Components::Relative,
+            (Components::Buttons, LinkKind::Hard) => Components::Name,
+        }
+    }
+
+    /// Creates the link, the dialog will be closed if it was created,
+    /// otherwise the error will be displayed.
+    fn create(&mut self) {
+        let name = self.input.value();
+        if let Err(message) = validate_file_name(OsStr::new(name)) {
+            self.state = LinkDialogState::DisplayErrorMessages(vec![message]);
+            return;
+        }
+        let link = self.link_dir.join(name);
+        match create_link(&self.target, &link, self.kind, self.relative) {
+            Ok(()) => {
+                self.created_link = Some(link);
+                self.should_quit = true;
+            }
+            Err(error) => {
+                self.state =
+                    LinkDialogState::DisplayErrorMessages(vec![format!("{}: {}", name, error)]);
+            }
+        }
+    }
+
+    fn display_input(&self) -> Paragraph<'_> {
+        let style = Style::default().fg(Color::Black);
+        let focused_style = Style::default().bg(Color::Cyan).fg(Color::White);
+        let input_style = Style::default().bg(Color::Cyan).fg(Color::Black);
+        let style_of = |component: Components, default: Style| match self.focused == component {
+            true => focused_style,
+            false => default,
+        };
+        let button_titles = match self.button {
+            Buttons::Ok => ("[X] OK ", "[ ] Cancel"),
+            Buttons::Cancel => ("[ ] OK ", "[X] Cancel"),
+        };
+        let (title, kind) = match self.kind {
+            LinkKind::Symbolic => ("Creating a symbolic link", "Symbolic link"),
+            LinkKind::Hard => ("Creating a hard link", "Hard link"),
+        };
+        let mut spans = vec![
+            Spans::from(Span::styled(
+                format!("{} to {}", kind, self.target.display()),
+                style,
+            )),
+            Spans::from(Span::styled(
+                format!("Link name in {}:", self.link_dir.display()),
+                style,
+            )),
+            Spans::from(Span::styled(
+                self.input.value(),
+                style_of(Components::Name, input_style),
+            )),
+        ];
+        if self.kind == LinkKind::Symbolic {
+            let target_titles = match self.relative {
+                false => "(*) Absolute ( ) Relative",
+                true => "( ) Absolute (*) Relative",
+            };
+            spans.push(Spans::from(vec![
+                Span::styled("Target: ", style),
+                Span::styled(target_titles, style_of(Components::Relative, style)),
+            ]));
+        }
+        spans.push(Spans::from(vec![
+            Span::styled(button_titles.0, style_of(Components::Buttons, style)),
+            Span::styled(button_titles.1, style_of(Components::Buttons, style)),
+        ]));
+        Paragraph::new(Text::from(spans))
+            .block(
+                Block::default()
+                    .title(Span::styled(title, Style::default().fg(Color::Cyan)))
+                    .style(Style::default().fg(Color::Black).bg(Color::Gray))
+                    .borders(Borders::ALL)
+                    .title_alignment(Alignment::Center),
+            )
+            .alignment(Alignment::Center)
+    }
+}
@@ -49,7 +49,8 @@ pub struct PanelOpionsDialog {
     component: Components,
     focused_button: Buttons,
     list_state: ListState,
-    options: [String; 1],
+    options: [String; 2],
+    remember_sort_per_dir: bool,
     request_config_change: bool,
     selected_option: usize,
     should_quit: bool,
@@ -58,10 +59,16 @@ pub struct PanelOpionsDialog {
 
 impl PanelOpionsDialog {
     pub fn new(config: &Configuration) -> Self {
-        let mut options = [String::from("[ ] Show hidden files")];
+        let mut options = [
+            String::from("[ ] Show hidden files"),
+            String::from("[ ] Remember sort order per directory"),
+        ];
         if config.show_hidden_files() {
             check_mark(&mut options[0])
         }
+        if config.remember_sort_per_dir() {
+            check_mark(&mut options[1])
+        }
 
         let mut list_state = ListState::default();
         list_state.select(Some(0));
@@ -71,6 +78,7 @@ impl PanelOpionsDialog {
             focused_button: Buttons::Cancel,
             list_state,
             options,
+            remember_sort_per_dir: config.remember_sort_per_dir(),
             request_config_change: false,
             selected_option: 0,
             should_quit: false,
@@ -87,6 +95,14 @@ impl PanelOpionsDialog {
                 self.show_hidden_files = true;
                 check_mark(&mut self.options[0]);
             }
+        } else if self.selected_option == 1 {
+            if self.remember_sort_per_dir {
+                self.remember_sort_per_dir = false;
+                uncheck_mark(&mut self.options[1]);
+            } else {
+                self.remember_sort_per_dir = true;
+                check_mark(&mut self.options[1]);
+            }
         }
     }
 
@@ -112,7 +128,8 @@ impl PanelOpionsDialog {
 
 impl BoxedDialog for PanelOpionsDialog {
     fn change_configuration(&mut self, config: &mut Configuration, _activa_panel: ActivePanel) {
-        config.set_show_hidden_files(self.show_hidden_files)
+        config.set_show_hidden_files(self.show_hidden_files);
+        config.set_remember_sort_per_dir(self.remember_sort_per_dir);
     }
 
     fn handle_keys(&mut self, key: Key, _app: &mut Application) {
@@ -172,9 +189,13 @@ impl BoxedDialog for PanelOpionsDialog {
             .margin(1)
             .split(dialog_layout[0]);
 
-        let items = ListItem::new(self.options[0].clone());
-        let options_list = List::new(vec![items])
-            .highlight_style(Style::default().bg(Color::Cyan).fg(Color::White));
+        let items: Vec<ListItem> = self
+            .options
+            .iter()
+            .map(|option| ListItem::new(option.clone()))
+            .collect();
+        let options_list =
+            List::new(items).highlight_style(Style::default().bg(Color::Cyan).fg(Color::White));
         let mut options_list_state = self.list_state.clone();
 
         let buttons = buttons(&self.component, &self.focused_button);
@@ -1,15 +1,19 @@
+use super::{display_errors, handle_input_keys};
+use crate::core::{attributes::parse_mode, journal::Operation, vfs::Vfs};
 use std::{
-    io::{self},
-    path::{Path, PathBuf},
+    ffi::OsString,
+    io,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
 };
 use termion::event::Key;
 use tui::{
     layout::Alignment,
     style::{Color, Style},
     text::{Span, Spans, Text},
-    widgets::{Block, Borders, Paragraph, Wrap},
+    widgets::{Block, Borders, Paragraph},
 };
-use tui_input::{Input, InputRequest};
+use tui_input::Input;
 
 enum Buttons {
     Ok,
@@ -25,70 +29,174 @@ impl Buttons {
     }
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum Components {
+    Names,
+    Mode,
+    EnterDir,
+    Buttons,
+}
+
+impl Components {
+    fn next(&mut self) {
+        *self = match self {
+            Components::Names => Components::Mode,
+            Components::Mode => Components::EnterDir,
+            Components::EnterDir => Components::Buttons,
+            Components::Buttons => Components::Names,
+        }
+    }
+
+    fn previous(&mut self) {
+        *self = match self {
+            Components::Names => Components::Buttons,
+            Components::Mode => Components::Names,
+            Components::EnterDir => Components::Mode,
+            Components::Buttons => Components::EnterDir,
+        }
+    }
+}
+
 #[derive(PartialEq)]
 /// Represents the state of the dialog
 pub enum MkDirDialogState {
     WaitingForInput,
-    DisplayErrorMessage(String),
+    DisplayErrorMessages(Vec<String>),
 }
 
-/// Represents a dialog used for creating a new directory.
+/// Represents a dialog used for creating new directories on the `Vfs` of the panel.
+/// Several names can be entered separated by `;`, a name can be a path like `a/b/c`,
+/// its missing parent directories are created too.
+///
+/// ## Key controls
+/// * Tab and Shift+Tab: focus the next/previous component
+/// * Space: toggle entering the new directory
+/// * Enter: create the directories or cancel, depending on the focused button
+/// * Esc: closes the dialog
 pub struct MkDirDialog {
     button: Buttons,
+    created_dirs: Vec<PathBuf>,
+    enter_dir: bool,
+    focused: Components,
     input: Input,
     hide: bool,
+    mode: Input,
     parent_dir: PathBuf,
     state: MkDirDialogState,
+    vfs: Arc<dyn Vfs>,
 }
 
 impl MkDirDialog {
-    pub fn new<P>(parent_dir: P) -> Self
+    pub fn new<P>(parent_dir: P, vfs: Arc<dyn Vfs>) -> Self
     where
         P: AsRef<Path>,
     {
         MkDirDialog {
             button: Buttons::Ok,
+            created_dirs: Vec::new(),
+            enter_dir: false,
+            focused: Components::Names,
             input: Input::default(),
             hide: false,
+            mode: Input::default(),
             state: MkDirDialogState::WaitingForInput,
             parent_dir: PathBuf::from(parent_dir.as_ref()),
+            vfs,
+        }
+    }
+
+    /// Creates the entered directories, the dialog will be closed if there were no errors,
+    /// otherwise the errors will be displayed.
+    pub fn create_dir(&mut self) {
+        let mode = match self.mode.value().trim() {
+            "" => None,
+            value => match parse_mode(value) {
+                Some(mode) => Some(mode),
+                None => {
+                    self.state = MkDirDialogState::DisplayErrorMessages(vec![format!(
+                        "Invalid mode: {}",
+                        value
+                    )]);
+                    return;
+                }
+            },
+        };
+        let names = split_names(self.input.value());
+        if names.is_empty() {
+            self.state = MkDirDialogState::DisplayErrorMessages(vec![String::from(
+                "The directory name cannot be empty",
+            )]);
+            return;
+        }
+
+        let (created, errors) = create_dirs(self.vfs.as_ref(), &self.parent_dir, &names, mode);
+        self.created_dirs.extend(created);
+        match errors.is_empty() {
+            true => self.hide = true,
+            false => self.state = MkDirDialogState::DisplayErrorMessages(errors),
+        }
+    }
+
+    /// Returns the operation to record in the `Journal` if directories were created.
+    pub fn completed_operation(&self) -> Option<Operation> {
+        match self.created_dirs.is_empty() {
+            true => None,
+            false => Some(Operation::MkDir {
+                paths: self.created_dirs.clone(),
+            }),
+        }
+    }
+
+    /// Returns the name of the entry to select in the parent directory:
+    /// the first component of the first entered name.
+    pub fn selected_name(&self) -> Option<OsString> {
+        let names = split_names(self.input.value());
+        match Path::new(names.first()?).components().next() {
+            Some(Component::Normal(name)) => Some(name.to_os_string()),
+            _ => None,
         }
     }
 
-    pub fn create_dir(&mut self) -> io::Result<()> {
-        let mut parent_dir = self.parent_dir.clone();
-        parent_dir.push(self.input.value());
-        std::fs::create_dir(parent_dir)
+    /// Returns the first entered directory if the user asked to change into it.
+    pub fn dir_to_enter(&self) -> Option<PathBuf> {
+        match self.enter_dir {
+            true => split_names(self.input.value())
+                .first()
+                .map(|name| self.parent_dir.join(name))
+                .filter(|dir| self.vfs.is_dir(dir)),
+            false => None,
+        }
     }
 
     pub fn handle_key(&mut self, key: Key) {
         match self.state {
             MkDirDialogState::WaitingForInput => match key {
                 Key::Char('\n') => match self.button {
-                    Buttons::Ok => match self.create_dir() {
-                        Ok(_) => self.hide = true,
-                        Err(error) => {
-                            self.state = MkDirDialogState::DisplayErrorMessage(error.to_string())
-                        }
-                    },
+                    Buttons::Ok => self.create_dir(),
                     Buttons::Cancel => self.hide = true,
                 },
-                Key::Char(char) => {
-                    // TODO: regex for allowed chars in linux file names
-                    if char.is_alphanumeric() {
-                        self.input.handle(InputRequest::InsertChar(char));
+                Key::Char('\t') => self.focused.next(),
+                Key::BackTab => self.focused.previous(),
+                _ => match self.focused {
+                    Components::Names => {
+                        handle_input_keys(&mut self.input, key, |char| !char.is_control())
                     }
-                }
-                Key::Backspace => {
-                    self.input.handle(InputRequest::DeletePrevChar);
-                }
-                Key::Delete => {
-                    self.input.handle(InputRequest::DeleteNextChar);
-                }
-                Key::Right | Key::Left | Key::Up | Key::Down => self.button.next(),
-                _ => {}
+                    Components::Mode => {
+                        handle_input_keys(&mut self.mode, key, |char| char.is_digit(8))
+                    }
+                    Components::EnterDir => {
+                        if key == Key::Char(' ') {
+                            self.enter_dir = !self.enter_dir;
+                        }
+                    }
+                    Components::Buttons => {
+                        if let Key::Left | Key::Right = key {
+                            self.button.next()
+                        }
+                    }
+                },
             },
-            MkDirDialogState::DisplayErrorMessage(_) => match key {
+            MkDirDialogState::DisplayErrorMessages(_) => match key {
                 Key::Char('\n') => self.state = MkDirDialogState::WaitingForInput,
                 Key::Esc => self.hide = true,
                 _ => {}
@@ -97,10 +205,10 @@ impl MkDirDialog {
     }
 
     /// Returns a representation based on the actual state of the dialog to render.
-    pub fn widget(&self) -> Paragraph {
+    pub fn widget(&self) -> Paragraph<'_> {
         match &self.state {
             MkDirDialogState::WaitingForInput => self.display_input(),
-            MkDirDialogState::DisplayErrorMessage(msg) => self.display_error(msg),
+            MkDirDialogState::DisplayErrorMessages(messages) => display_errors(messages),
         }
     }
 
@@ -109,23 +217,45 @@ impl MkDirDialog {
         self.hide
     }
 
-    fn display_input(&self) -> Paragraph {
+    fn display_input(&self) -> Paragraph<'_> {
+        let style = Style::default().fg(Color::Black);
+        let focused_style = Style::default().bg(Color::Cyan).fg(Color::White);
+        let input_style = Style::default().bg(Color::Cyan).fg(Color::Black);
+        let style_of = |component: Components, default: Style| match self.focused == component {
+            true => focused_style,
+            false => default,
+        };
+        let check_mark = match self.enter_dir {
+            true => "[X]",
+            false => "[ ]",
+        };
         let button_titles = match self.button {
             Buttons::Ok => ("[X] OK ", "[ ] Cancel"),
             Buttons::Cancel => ("[ ] OK ", "[X] Cancel"),
         };
         let spans = vec![
             Spans::from(vec![Span::styled(
-                "New directory name:",
-                Style::default().fg(Color::Black),
+                "New directory names (separated by ;):",
+                style,
             )]),
             Spans::from(Span::styled(
                 self.input.value(),
-                Style::default().bg(Color::Cyan).fg(Color::Black),
+                style_of(Components::Names, input_style),
             )),
             Spans::from(vec![
-                Span::styled(button_titles.0, Style::default().fg(Color::Black)),
-                Span::styled(button_titles.1, Style::default().fg(Color::Black)),
+                Span::styled("Mode (octal, optional): ", style),
+                Span::styled(
+                    format!("{:<5}", self.mode.value()),
+                    style_of(Components::Mode, input_style),
+                ),
+            ]),
+            Spans::from(Span::styled(
+                format!("{} Enter the new directory", check_mark),
+                style_of(Components::EnterDir, style),
+            )),
+            Spans::from(vec![
+                Span::styled(button_titles.0, style_of(Components::Buttons, style)),
+                Span::styled(button_titles.1, style_of(Components::Buttons, style)),
             ]),
         ];
         let text = Text::from(spans);
@@ -142,28 +272,103 @@ impl MkDirDialog {
             )
             .alignment(Alignment::Center)
     }
+}
 
-    fn display_error<'error_msg>(&self, error_message: &'error_msg str) -> Paragraph<'error_msg> {
-        let spans = vec![
-            Spans::from(vec![Span::styled(
-                error_message,
-                Style::default().fg(Color::White),
-            )]),
-            Spans::from(vec![Span::styled(
-                "[ OK ]",
-                Style::default().fg(Color::White),
-            )]),
-        ];
-        let text = Text::from(spans);
-        Paragraph::new(text)
-            .block(
-                Block::default()
-                    .title("Error")
-                    .borders(Borders::ALL)
-                    .style(Style::default().bg(Color::LightRed).fg(Color::White)),
-            )
-            .wrap(Wrap { trim: false })
-            .style(Style::default().bg(Color::LightRed).fg(Color::Gray))
-            .alignment(Alignment::Center)
+/// Splits the entered names separated by `;` or new lines, ignoring the empty ones.
+fn split_names(value: &str) -> Vec<&str> {
+    value
+        .split([';', '\n'])
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Creates the directories named `names` in `parent` along with their missing parents.
+/// The `mode` is set on the named directories, their parents get the default permissions.
+/// Returns the created directories, the parents first, and the errors.
+fn create_dirs(
+    vfs: &dyn Vfs,
+    parent: &Path,
+    names: &[&str],
+    mode: Option<u32>,
+) -> (Vec<PathBuf>, Vec<String>) {
+    let mut created = Vec::new();
+    let mut errors = Vec::new();
+    for name in names {
+        let dir = parent.join(name);
+        let mut missing: Vec<PathBuf> = dir
+            .ancestors()
+            .take_while(|ancestor| !vfs.exists(ancestor))
+            .map(PathBuf::from)
+            .collect();
+        missing.reverse();
+
+        let result = create_dir(vfs, &dir, mode);
+        // the directories could be created before an error are kept
+        created.extend(missing.into_iter().filter(|dir| vfs.is_dir(dir)));
+        if let Err(error) = result {
+            errors.push(format!("{}: {}", name, error));
+        }
+    }
+    (created, errors)
+}
+
+/// Creates `dir` with its missing parents, succeeds if it already exists.
+/// The `mode` is set exactly, without the umask, if the directory was created.
+fn create_dir(vfs: &dyn Vfs, dir: &Path, mode: Option<u32>) -> io::Result<()> {
+    if let Some(parent) = dir.parent() {
+        vfs.mkdir_all(parent)?;
+    }
+    match vfs.mkdir(dir, mode) {
+        Err(error) if error.kind() == io::ErrorKind::AlreadyExists && vfs.is_dir(dir) => Ok(()),
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{create_dirs, split_names};
+    use crate::core::{
+        test_dir::TestDir,
+        vfs::{LocalFs, MemoryFs, Vfs},
+    };
+    use std::{fs, os::unix::fs::PermissionsExt, path::Path};
+
+    #[test]
+    fn test_split_names() {
+        assert_eq!(split_names("a; b/c ;\nd;;"), ["a", "b/c", "d"]);
+        assert!(split_names(" ; ").is_empty());
+    }
+
+    #[test]
+    fn test_create_nested_dirs() {
+        let dir = TestDir::new("mkdir");
+        fs::create_dir_all(dir.join("a")).unwrap();
+
+        let (created, errors) = create_dirs(&LocalFs, &dir, &["a/b/c", "d"], Some(0o700));
+        assert!(errors.is_empty());
+        assert_eq!(created, [dir.join("a/b"), dir.join("a/b/c"), dir.join("d")]);
+        let mode = |path| fs::metadata(dir.join(path)).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode("a/b/c"), 0o700);
+        assert_ne!(mode("a/b"), 0o700);
+        assert_eq!(mode("d"), 0o700);
+
+        // an existing directory is not an error, a file in the way is
+        fs::write(dir.join("file"), "").unwrap();
+        let (created, errors) = create_dirs(&LocalFs, &dir, &["a", "file/e"], None);
+        assert!(created.is_empty());
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_create_dirs_in_memory() {
+        let vfs = MemoryFs::new();
+        vfs.write("/file", "");
+
+        let (created, errors) = create_dirs(&vfs, Path::new("/"), &["a/b", "file/c"], Some(0o700));
+        assert_eq!(created, [Path::new("/a"), Path::new("/a/b")]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(vfs.stat(Path::new("/a/b")).unwrap().mode, Some(0o700));
+        assert_eq!(vfs.stat(Path::new("/a")).unwrap().mode, Some(0o755));
     }
 }
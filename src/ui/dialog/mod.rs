@@ -1,46 +1,414 @@
-use fs_extra::{
-    dir::TransitProcess as DirTransitProcess, file::TransitProcess as FileTransitProcess,
+use crate::core::{
+    conflict::{ConflictAction, ConflictResolution, FileConflict},
+    copy::{CopyProgress, TransferControl},
+    journal::{CreatedFiles, Operation},
+    trash::TrashEntry,
+    vfs::{LocalFs, Vfs},
 };
-use std::path::Path;
-use std::sync::mpsc::Sender;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{
+    mpsc::{Receiver, Sender},
+    Arc,
+};
+use termion::event::Key;
+use tui::{
+    layout::Alignment,
+    style::{Color, Modifier, Style},
+    text::{Span, Spans, Text},
+    widgets::{Block, Borders, Paragraph, Wrap},
+};
+use tui_input::{Input, InputRequest};
 
+mod attributes;
 mod cp;
+mod extract;
 mod help;
+mod history;
+mod jobs;
+mod link;
 mod menu;
 mod mkdir;
+mod multi_rename;
 mod mv;
+mod new_file;
+mod pack;
+mod rename;
 mod rm;
+mod touch;
 mod transfer;
+mod trash;
 
+pub use self::attributes::*;
 pub use self::cp::*;
+pub use self::extract::*;
 pub use self::help::*;
+pub use self::history::*;
+pub use self::jobs::*;
+pub use self::link::*;
 pub use self::menu::*;
 pub use self::mkdir::*;
+pub use self::multi_rename::*;
 pub use self::mv::*;
+pub use self::new_file::*;
+pub use self::pack::*;
+pub use self::rename::*;
 pub use self::rm::*;
+pub use self::touch::*;
 pub use self::transfer::*;
+pub use self::trash::*;
 
 /// Abstraction of file transfers (copy/move) for reusing
 /// the same TransferDialog fo every different file transfers.
+///
+/// The `destination` is the full path of the transferred file or directory,
+/// not the directory it is transferred into.
 pub trait TransferStrategy {
     fn transfer_dir<P: AsRef<Path>>(
         &mut self,
         source: P,
         destination: P,
-        tx: Sender<TransferProgress>,
+        options: TransferOptions,
+        channel: TransferChannel,
     );
     fn transfer_file<P: AsRef<Path>>(
         &mut self,
         source: P,
         destination: P,
-        tx: Sender<TransferProgress>,
+        options: TransferOptions,
+        channel: TransferChannel,
     );
+
+    /// Returns the file system the files are transferred on, the `TransferDialog`
+    /// checks the source and the destination and creates the missing directories through it.
+    fn vfs(&self) -> &dyn Vfs {
+        &LocalFs
+    }
+
+    /// Returns whether the `source` is a directory or a file,
+    /// None if it does not exist, then it is reported as a failure.
+    /// A symbolic link is a directory if it points to one, a broken link is a file,
+    /// so it is reported if it cannot be transferred.
+    fn source_is_dir(&self, source: &Path) -> Option<bool> {
+        let vfs = self.vfs();
+        vfs.symlink_stat(source).ok().map(|_| vfs.is_dir(source))
+    }
+
+    /// Returns true if the source is transferred into the entered destination directory itself,
+    /// instead of a file or directory named after the source in it, e.g. an unpacked archive.
+    fn transfers_contents(&self) -> bool {
+        false
+    }
+
+    /// Returns true if the strategy can choose between transferring symbolic links as links
+    /// or the files they point to, see `TransferOptions::follow_symlinks`.
+    fn can_follow_symlinks(&self) -> bool {
+        false
+    }
+
+    /// Returns true if the strategy can compare the copied files to their sources,
+    /// see `TransferOptions::verify`.
+    fn can_verify(&self) -> bool {
+        false
+    }
+
+    /// Returns the operation to record in the `Journal` after `source` was transferred
+    /// to the new `target`, or None if the transfer cannot be undone.
+    /// The transfer reported the files it `created` when it completed.
+    fn journal_operation(
+        &self,
+        _source: &Path,
+        _target: &Path,
+        _created: CreatedFiles,
+    ) -> Option<Operation> {
+        None
+    }
+}
+
+/// Options chosen by the user in the `TransferDialog` altering the behavior of a file transfer.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TransferOptions {
+    /// Transfer the files the symbolic links are pointing to,
+    /// instead of re-creating the links on the destination.
+    /// The `TransferDialog` sets it if the strategy can follow links, so copies follow them by default.
+    pub follow_symlinks: bool,
+    /// Create the missing parent directories of the destination.
+    pub create_dirs: bool,
+    /// Re-read every copied file and compare its checksum to the source's.
+    pub verify: bool,
+    /// How to handle the existing targets, the user is asked if it is None.
+    pub conflict_resolution: Option<ConflictResolution>,
 }
 
 // Convenient type for sending two different type of data through a channel:
 // dont need two distinct (tx,rx)
 pub enum TransferProgress {
-    DirTransfer(DirTransitProcess),
-    FileTransfer(FileTransitProcess),
+    Progress(CopyProgress),
+    /// The target of a file exists, the transfer waits for a `ConflictAnswer`.
+    Conflict(FileConflict),
+    /// A file could not be transferred, the transfer waits for an `ErrorAnswer`.
+    Error(TransferError),
+    /// A file could not be transferred and it was skipped without asking.
+    Skipped(TransferError),
+    /// A file was moved to the trash, it can be restored from the entry.
+    Trashed(TrashEntry),
+    /// The transfer is over, it created the files.
+    Completed(CreatedFiles),
     None,
 }
+
+/// A file which could not be transferred.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransferError {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+/// Returned by a transfer aborted by the user.
+#[derive(Debug, PartialEq)]
+pub struct TransferAborted;
+
+/// The answers of the user sent back to a running transfer.
+pub enum TransferAnswer {
+    Conflict(ConflictAnswer),
+    Error(ErrorAnswer),
+}
+
+/// The user's decision about a `FileConflict`.
+pub struct ConflictAnswer {
+    pub resolution: ConflictResolution,
+    /// Resolve the rest of the conflicts of the transfer the same way without asking.
+    pub apply_to_all: bool,
+}
+
+/// The user's decision about a `TransferError`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorAnswer {
+    Retry,
+    Skip,
+    /// Skip this and the rest of the failing files without asking.
+    SkipAll,
+    Abort,
+}
+
+impl ErrorAnswer {
+    pub const ALL: [ErrorAnswer; 4] = [
+        ErrorAnswer::Retry,
+        ErrorAnswer::Skip,
+        ErrorAnswer::SkipAll,
+        ErrorAnswer::Abort,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            ErrorAnswer::Retry => "Retry",
+            ErrorAnswer::Skip => "Skip",
+            ErrorAnswer::SkipAll => "Skip all",
+            ErrorAnswer::Abort => "Abort",
+        }
+    }
+}
+
+/// The connection of a transfer running on its own thread with the `TransferDialog`:
+/// progress is reported through it, and the user's decisions are received from it.
+pub struct TransferChannel {
+    answers: Receiver<TransferAnswer>,
+    conflict_resolution: Option<ConflictResolution>,
+    control: Arc<TransferControl>,
+    created: CreatedFiles,
+    progress: Sender<TransferProgress>,
+    skip_errors: bool,
+}
+
+impl TransferChannel {
+    pub fn new(
+        progress: Sender<TransferProgress>,
+        answers: Receiver<TransferAnswer>,
+        conflict_resolution: Option<ConflictResolution>,
+        control: Arc<TransferControl>,
+    ) -> Self {
+        TransferChannel {
+            answers,
+            conflict_resolution,
+            control,
+            created: CreatedFiles::default(),
+            progress,
+            skip_errors: false,
+        }
+    }
+
+    pub fn send(&self, progress: TransferProgress) {
+        let _ = self.progress.send(progress);
+    }
+
+    /// Notes the file or directory the transfer just created at `path`,
+    /// they are reported when the transfer completes.
+    pub fn created(&mut self, path: &Path) {
+        self.created.push(path);
+    }
+
+    /// Reports the end of the transfer with the files it created.
+    pub fn complete(mut self) {
+        let created = std::mem::take(&mut self.created);
+        self.send(TransferProgress::Completed(created));
+    }
+
+    pub fn control(&self) -> &TransferControl {
+        &self.control
+    }
+
+    /// Decides what to do with the existing `target` of the `source` file.
+    /// The user is asked unless a resolution was chosen for all the conflicts,
+    /// the file is skipped if the dialog is gone.
+    pub fn resolve_conflict(&mut self, source: &Path, target: &Path) -> ConflictAction {
+        self.resolve_file_conflict(FileConflict::new(source, target))
+    }
+
+    /// Decides what to do with the `conflict`, like `resolve_conflict()`,
+    /// for a source which is not on the file system, e.g. an archive member.
+    pub fn resolve_file_conflict(&mut self, conflict: FileConflict) -> ConflictAction {
+        let resolution = match self.conflict_resolution {
+            Some(resolution) => resolution,
+            None if self.control.is_unattended() => ConflictResolution::Skip,
+            None => {
+                self.send(TransferProgress::Conflict(conflict.clone()));
+                match self.answers.recv() {
+                    Ok(TransferAnswer::Conflict(answer)) => {
+                        if answer.apply_to_all {
+                            self.conflict_resolution = Some(answer.resolution);
+                        }
+                        answer.resolution
+                    }
+                    _ => ConflictResolution::Skip,
+                }
+            }
+        };
+        conflict.action(resolution)
+    }
+
+    /// Asks the user what to do with the `path` failed to transfer:
+    /// returns `ErrorAnswer::Retry`, `ErrorAnswer::Skip` or `ErrorAnswer::Abort`.
+    /// The transfer is aborted if the dialog is gone, unless it runs in the background.
+    pub fn report_error(&mut self, path: &Path, error: &io::Error) -> ErrorAnswer {
+        let error = TransferError {
+            path: PathBuf::from(path),
+            message: error.to_string(),
+        };
+        if self.skip_errors || self.control.is_unattended() {
+            self.send(TransferProgress::Skipped(error));
+            return ErrorAnswer::Skip;
+        }
+
+        self.send(TransferProgress::Error(error));
+        match self.answers.recv() {
+            Ok(TransferAnswer::Error(ErrorAnswer::SkipAll)) => {
+                self.skip_errors = true;
+                ErrorAnswer::Skip
+            }
+            Ok(TransferAnswer::Error(answer)) => answer,
+            _ if self.control.is_unattended() => ErrorAnswer::Skip,
+            _ => ErrorAnswer::Abort,
+        }
+    }
+
+    /// Runs the `operation` on the `path` until it succeeds, or the user skips it.
+    /// Returns true if the operation succeeded.
+    /// The operation is not started while the transfer is paused, nor after it was cancelled.
+    pub fn retry<F>(&mut self, path: &Path, mut operation: F) -> Result<bool, TransferAborted>
+    where
+        F: FnMut(&Self) -> io::Result<()>,
+    {
+        loop {
+            if !self.control.proceed() {
+                return Err(TransferAborted);
+            }
+            let error = match operation(self) {
+                Ok(()) => return Ok(true),
+                Err(_) if self.control.is_cancelled() => return Err(TransferAborted),
+                Err(error) => error,
+            };
+            match self.report_error(path, &error) {
+                ErrorAnswer::Retry => continue,
+                ErrorAnswer::Skip | ErrorAnswer::SkipAll => return Ok(false),
+                ErrorAnswer::Abort => return Err(TransferAborted),
+            }
+        }
+    }
+}
+
+/// Creates a widget listing the error messages with an OK button.
+pub(crate) fn display_errors(messages: &[String]) -> Paragraph<'_> {
+    let mut spans: Vec<Spans> = messages
+        .iter()
+        .map(|message| {
+            Spans::from(Span::styled(
+                message.as_str(),
+                Style::default().fg(Color::White),
+            ))
+        })
+        .collect();
+    spans.push(Spans::from(Span::styled(
+        "[ OK ]",
+        Style::default().fg(Color::White),
+    )));
+    Paragraph::new(Text::from(spans))
+        .block(
+            Block::default()
+                .title("Error")
+                .borders(Borders::ALL)
+                .style(Style::default().bg(Color::LightRed).fg(Color::White)),
+        )
+        .wrap(Wrap { trim: false })
+        .style(Style::default().bg(Color::LightRed).fg(Color::Gray))
+        .alignment(Alignment::Center)
+}
+
+/// Creates the spans of the visible part of the `input` in `width` columns,
+/// with the char under the cursor highlighted.
+pub(crate) fn input_spans(input: &Input, width: usize, style: Style) -> Vec<Span<'static>> {
+    let chars: Vec<char> = input.value().chars().collect();
+    let cursor = input.cursor();
+    let scroll = cursor.saturating_sub(width.saturating_sub(1));
+    let end = chars.len().min(scroll + width);
+    let before: String = chars[scroll..cursor.min(end)].iter().collect();
+    let under_cursor = chars.get(cursor).copied().unwrap_or(' ');
+    let after: String = chars[(cursor + 1).min(end)..end].iter().collect();
+    let padding = width.saturating_sub(end - scroll + usize::from(cursor == chars.len()));
+
+    vec![
+        Span::styled(before, style),
+        Span::styled(
+            under_cursor.to_string(),
+            style.add_modifier(Modifier::REVERSED),
+        ),
+        Span::styled(after + &" ".repeat(padding), style),
+    ]
+}
+
+/// Passes the editing keys to the `input`, inserting only the chars `accept` allows.
+pub(crate) fn handle_input_keys(input: &mut Input, key: Key, accept: fn(char) -> bool) {
+    match key {
+        Key::Char(char) if accept(char) => {
+            input.handle(InputRequest::InsertChar(char));
+        }
+        Key::Backspace => {
+            input.handle(InputRequest::DeletePrevChar);
+        }
+        Key::Delete => {
+            input.handle(InputRequest::DeleteNextChar);
+        }
+        Key::Left => {
+            input.handle(InputRequest::GoToPrevChar);
+        }
+        Key::Right => {
+            input.handle(InputRequest::GoToNextChar);
+        }
+        Key::Home => {
+            input.handle(InputRequest::GoToStart);
+        }
+        Key::End => {
+            input.handle(InputRequest::GoToEnd);
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,646 @@
+use super::{display_errors, handle_input_keys};
+use crate::core::{
+    journal::Operation,
+    list_dir::escape_file_name,
+    rename::{
+        plan_renames, rename_all, CaseConversion, PlannedRename, RenameBatch, RenameRule,
+        RenameSource, MAX_COUNTER_WIDTH,
+    },
+};
+use std::{
+    ffi::OsString,
+    fmt::Display,
+    io::Stdout,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use termion::{event::Key, raw::RawTerminal};
+use tui::{
+    backend::TermionBackend,
+    layout::{Alignment, Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table, TableState},
+    Frame,
+};
+use tui_input::Input;
+
+const CASE_CONVERSIONS: [(CaseConversion, &str); 4] = [
+    (CaseConversion::Unchanged, "Unchanged"),
+    (CaseConversion::Upper, "UPPER"),
+    (CaseConversion::Lower, "lower"),
+    (CaseConversion::Title, "Title"),
+];
+
+enum Buttons {
+    Ok,
+    Cancel,
+}
+
+impl Buttons {
+    fn next(&mut self) {
+        match *self {
+            Buttons::Ok => *self = Buttons::Cancel,
+            Buttons::Cancel => *self = Buttons::Ok,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Components {
+    NameTemplate,
+    ExtensionTemplate,
+    Search,
+    Replace,
+    CounterStart,
+    CounterStep,
+    CounterWidth,
+    Case,
+    Preview,
+    Buttons,
+}
+
+impl Components {
+    const ALL: [Components; 10] = [
+        Components::NameTemplate,
+        Components::ExtensionTemplate,
+        Components::Search,
+        Components::Replace,
+        Components::CounterStart,
+        Components::CounterStep,
+        Components::CounterWidth,
+        Components::Case,
+        Components::Preview,
+        Components::Buttons,
+    ];
+
+    fn next(&mut self) {
+        let index = Components::ALL.iter().position(|c| c == self).unwrap_or(0);
+        *self = Components::ALL[(index + 1) % Components::ALL.len()];
+    }
+
+    fn previous(&mut self) {
+        let index = Components::ALL.iter().position(|c| c == self).unwrap_or(0);
+        *self = Components::ALL[(index + Components::ALL.len() - 1) % Components::ALL.len()];
+    }
+}
+
+/// Represents the state of the dialog
+pub enum MultiRenameDialogState {
+    WaitingForInput,
+    /// The files have been renamed, they can be renamed back with the Undo button.
+    Renamed,
+    DisplayErrorMessages(Vec<String>),
+}
+
+/// A dialog for renaming the marked files by a template, see `RenameRule`.
+/// The old and new names are previewed while editing, conflicting names are highlighted.
+///
+/// ## Key controls
+/// * Tab and Shift+Tab: focus the next/previous component
+/// * <- and -> : select the case conversion or a button
+/// * Up and Down: scroll the preview
+/// * Enter: rename the files or cancel, depending on the focused button
+/// * Esc: closes the dialog
+pub struct MultiRenameDialog {
+    button: Buttons,
+    case: usize,
+    counter_start: Input,
+    counter_step: Input,
+    counter_width: Input,
+    dir: PathBuf,
+    extension_template: Input,
+    files: Vec<RenameSource>,
+    focused: Components,
+    name_template: Input,
+    planned: Vec<PlannedRename>,
+    preview_state: TableState,
+    /// The error of an invalid search pattern, nothing is renamed until it is fixed.
+    rule_error: Option<String>,
+    renamed: RenameBatch,
+    replace: Input,
+    search: Input,
+    should_quit: bool,
+    state: MultiRenameDialogState,
+}
+
+impl MultiRenameDialog {
+    /// Creates a new dialog for renaming the `files` of the `dir` directory.
+    pub fn new<P: AsRef<Path>>(dir: P, files: Vec<PathBuf>) -> Self {
+        let rule = RenameRule::default();
+        let mut dialog = MultiRenameDialog {
+            button: Buttons::Ok,
+            case: 0,
+            counter_start: Input::new(rule.counter_start.to_string()),
+            counter_step: Input::new(rule.counter_step.to_string()),
+            counter_width: Input::new(rule.counter_width.to_string()),
+            dir: PathBuf::from(dir.as_ref()),
+            extension_template: Input::new(rule.extension_template),
+            files: files
+                .iter()
+                .map(|file| RenameSource::from_path(file))
+                .collect(),
+            focused: Components::NameTemplate,
+            name_template: Input::new(rule.name_template),
+            planned: Vec::new(),
+            preview_state: TableState::default(),
+            rule_error: None,
+            renamed: RenameBatch::default(),
+            replace: Input::default(),
+            search: Input::default(),
+            should_quit: false,
+            state: MultiRenameDialogState::WaitingForInput,
+        };
+        dialog.update_preview();
+        dialog
+    }
+
+    pub fn handle_key(&mut self, key: Key) {
+        match &self.state {
+            MultiRenameDialogState::WaitingForInput => {}
+            MultiRenameDialogState::Renamed => {
+                match key {
+                    Key::Char('\n') => match self.button {
+                        Buttons::Ok => self.should_quit = true,
+                        Buttons::Cancel => self.undo(),
+                    },
+                    Key::Left | Key::Right | Key::Char('\t') => self.button.next(),
+                    _ => {}
+                }
+                return;
+            }
+            MultiRenameDialogState::DisplayErrorMessages(_) => {
+                if key == Key::Char('\n') {
+                    self.should_quit = true;
+                }
+                return;
+            }
+        }
+
+        match key {
+            Key::Char('\n') => match self.button {
+                Buttons::Ok => self.rename(),
+                Buttons::Cancel => self.should_quit = true,
+            },
+            Key::Char('\t') => self.focused.next(),
+            Key::BackTab => self.focused.previous(),
+            _ => {
+                match self.focused {
+                    Components::NameTemplate => {
+                        handle_input_keys(&mut self.name_template, key, |_| true)
+                    }
+                    Components::ExtensionTemplate => {
+                        handle_input_keys(&mut self.extension_template, key, |_| true)
+                    }
+                    Components::Search => handle_input_keys(&mut self.search, key, |_| true),
+                    Components::Replace => handle_input_keys(&mut self.replace, key, |_| true),
+                    Components::CounterStart => {
+                        handle_input_keys(&mut self.counter_start, key, is_digit)
+                    }
+                    Components::CounterStep => {
+                        handle_input_keys(&mut self.counter_step, key, is_digit)
+                    }
+                    Components::CounterWidth => {
+                        handle_input_keys(&mut self.counter_width, key, is_digit)
+                    }
+                    Components::Case => match key {
+                        Key::Left => {
+                            self.case =
+                                (self.case + CASE_CONVERSIONS.len() - 1) % CASE_CONVERSIONS.len()
+                        }
+                        Key::Right => self.case = (self.case + 1) % CASE_CONVERSIONS.len(),
+                        _ => {}
+                    },
+                    Components::Preview => self.scroll_preview(key),
+                    Components::Buttons => {
+                        if let Key::Left | Key::Right = key {
+                            self.button.next()
+                        }
+                    }
+                }
+                self.update_preview();
+            }
+        }
+    }
+
+    pub fn render(&self, frame: &mut Frame<TermionBackend<RawTerminal<Stdout>>>, area: Rect) {
+        if let MultiRenameDialogState::DisplayErrorMessages(messages) = &self.state {
+            frame.render_widget(display_errors(messages), area);
+            return;
+        }
+
+        let block = Block::default()
+            .title(Span::styled(
+                "Multi-rename",
+                Style::default().fg(Color::Cyan),
+            ))
+            .style(Style::default().fg(Color::Black).bg(Color::Gray))
+            .borders(Borders::ALL)
+            .title_alignment(Alignment::Center);
+        let layout = Layout::default()
+            .constraints([
+                Constraint::Length(4),
+                Constraint::Min(3),
+                Constraint::Length(1),
+            ])
+            .split(block.inner(area));
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+        frame.render_widget(self.display_rule(), layout[0]);
+        frame.render_stateful_widget(
+            self.display_preview(),
+            layout[1],
+            &mut self.preview_state.clone(),
+        );
+        frame.render_widget(self.display_buttons(), layout[2]);
+    }
+
+    /// Signals that the dialog should be closed or not
+    pub fn should_quit(&self) -> bool {
+        self.should_quit
+    }
+
+    /// Returns the renames done by the dialog to be recorded in the journal,
+    /// if they were not undone.
+    pub fn completed_operation(&self) -> Option<Operation> {
+        match self.renamed.is_empty() {
+            true => None,
+            false => Some(Operation::MultiRename {
+                batch: self.renamed.clone(),
+            }),
+        }
+    }
+
+    fn rule(&self) -> Result<RenameRule, String> {
+        let default = RenameRule::default();
+        Ok(RenameRule {
+            name_template: self.name_template.value().to_string(),
+            extension_template: self.extension_template.value().to_string(),
+            search: RenameRule::search_pattern(self.search.value())?,
+            replace: self.replace.value().to_string(),
+            case: CASE_CONVERSIONS[self.case].0,
+            counter_start: number_input(
+                &self.counter_start,
+                "Counter start",
+                default.counter_start,
+                u64::MAX,
+            )?,
+            counter_step: number_input(
+                &self.counter_step,
+                "Counter step",
+                default.counter_step,
+                u64::MAX,
+            )?,
+            counter_width: number_input(
+                &self.counter_width,
+                "Counter width",
+                default.counter_width,
+                MAX_COUNTER_WIDTH,
+            )?,
+        })
+    }
+
+    fn update_preview(&mut self) {
+        match self.rule() {
+            Ok(rule) => {
+                self.planned = plan_renames(&self.dir, &self.files, &rule);
+                self.rule_error = None;
+            }
+            Err(error) => {
+                self.planned.clear();
+                self.rule_error = Some(error);
+            }
+        }
+    }
+
+    fn scroll_preview(&mut self, key: Key) {
+        let last = self.planned.len().saturating_sub(1);
+        let selected = self.preview_state.selected().unwrap_or(0);
+        match key {
+            Key::Up => self.preview_state.select(Some(selected.saturating_sub(1))),
+            Key::Down => self.preview_state.select(Some((selected + 1).min(last))),
+            _ => {}
+        }
+    }
+
+    /// Renames the files if none of the new names conflicts.
+    fn rename(&mut self) {
+        self.update_preview();
+        if self.rule_error.is_some() || self.planned.iter().any(|rename| rename.conflict.is_some())
+        {
+            return;
+        }
+
+        let renames: Vec<(OsString, OsString)> = self
+            .planned
+            .iter()
+            .filter(|rename| !rename.is_unchanged())
+            .map(|rename| (rename.from.clone(), rename.to.clone()))
+            .collect();
+        match rename_all(&self.dir, &renames) {
+            Ok(batch) if batch.is_empty() => self.should_quit = true,
+            Ok(batch) => {
+                self.renamed = batch;
+                self.state = MultiRenameDialogState::Renamed;
+            }
+            Err(error) => {
+                self.state = MultiRenameDialogState::DisplayErrorMessages(vec![
+                    format!(
+                        "{} of {} files renamed",
+                        error.completed.len(),
+                        renames.len()
+                    ),
+                    error.error.to_string(),
+                ]);
+                // the completed renames are recorded in the journal
+                self.renamed = error.completed;
+            }
+        }
+    }
+
+    fn undo(&mut self) {
+        if let MultiRenameDialogState::Renamed = &self.state {
+            match self.renamed.undo() {
+                Ok(_) => {
+                    self.renamed = RenameBatch::default();
+                    self.should_quit = true
+                }
+                // the renames can still be undone from the journal once the files are fixed
+                Err(error) => {
+                    self.state = MultiRenameDialogState::DisplayErrorMessages(vec![
+                        String::from("Undo failed"),
+                        error.error.to_string(),
+                    ])
+                }
+            }
+        }
+    }
+
+    fn display_rule(&self) -> Paragraph<'_> {
+        let style = Style::default().fg(Color::Black);
+        let focused_style = Style::default().bg(Color::Cyan).fg(Color::White);
+        let input_style = Style::default().bg(Color::Cyan).fg(Color::Black);
+        let label = |component: Components, title: &'static str| {
+            Span::styled(
+                title,
+                match self.focused == component {
+                    true => focused_style,
+                    false => style,
+                },
+            )
+        };
+        let input = |input: &Input, width: usize| {
+            Span::styled(
+                format!("{:<width$}", input.value(), width = width),
+                input_style,
+            )
+        };
+        let mut case_spans = vec![label(Components::Case, "Case: ")];
+        case_spans.extend(
+            CASE_CONVERSIONS
+                .iter()
+                .enumerate()
+                .map(|(index, (_, title))| {
+                    let radio_button = match self.case == index {
+                        true => format!("(*) {} ", title),
+                        false => format!("( ) {} ", title),
+                    };
+                    Span::styled(radio_button, style)
+                }),
+        );
+
+        Paragraph::new(vec![
+            Spans::from(vec![
+                label(Components::NameTemplate, "Name: "),
+                input(&self.name_template, 20),
+                label(Components::ExtensionTemplate, " Extension: "),
+                input(&self.extension_template, 8),
+            ]),
+            Spans::from(vec![
+                label(Components::Search, "Search: "),
+                input(&self.search, 14),
+                label(Components::Replace, " Replace: "),
+                input(&self.replace, 14),
+            ]),
+            Spans::from(vec![
+                label(Components::CounterStart, "Counter start: "),
+                input(&self.counter_start, 4),
+                label(Components::CounterStep, " step: "),
+                input(&self.counter_step, 3),
+                label(Components::CounterWidth, " width: "),
+                input(&self.counter_width, 2),
+            ]),
+            Spans::from(case_spans),
+        ])
+    }
+
+    fn display_preview(&self) -> Table<'_> {
+        let header_style = Style::default()
+            .fg(Color::Black)
+            .add_modifier(Modifier::BOLD);
+        let header = Row::new(vec![
+            Cell::from(Span::styled("Old name", header_style)),
+            Cell::from(Span::styled("New name", header_style)),
+            Cell::from(Span::styled("Status", header_style)),
+        ]);
+        let rows = self.planned.iter().map(|rename| {
+            let (status, style) = match (&rename.conflict, &self.state) {
+                (Some(conflict), _) => (conflict.as_str(), Style::default().fg(Color::Red)),
+                (None, MultiRenameDialogState::Renamed) => {
+                    ("Renamed", Style::default().fg(Color::Black))
+                }
+                (None, _) if rename.is_unchanged() => {
+                    ("Unchanged", Style::default().fg(Color::DarkGray))
+                }
+                (None, _) => ("", Style::default().fg(Color::Black)),
+            };
+            Row::new(vec![
+                Cell::from(escape_file_name(&rename.from)),
+                Cell::from(escape_file_name(&rename.to)),
+                Cell::from(status),
+            ])
+            .style(style)
+        });
+        let border_style = match self.focused == Components::Preview {
+            true => Style::default().fg(Color::Cyan),
+            false => Style::default().fg(Color::Black),
+        };
+        let mut block = Block::default()
+            .borders(Borders::TOP)
+            .border_style(border_style);
+        if let Some(error) = &self.rule_error {
+            block = block.title(Span::styled(
+                error.as_str(),
+                Style::default().fg(Color::Red),
+            ));
+        }
+
+        Table::new(rows)
+            .header(header)
+            .block(block)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .widths(&[
+                Constraint::Percentage(40),
+                Constraint::Percentage(40),
+                Constraint::Percentage(20),
+            ])
+    }
+
+    fn display_buttons(&self) -> Paragraph<'_> {
+        let style = match self.focused == Components::Buttons {
+            true => Style::default().bg(Color::Cyan).fg(Color::White),
+            false => Style::default().fg(Color::Black),
+        };
+        let titles = match &self.state {
+            MultiRenameDialogState::Renamed => ("OK", "Undo"),
+            _ => ("Rename", "Cancel"),
+        };
+        let (ok, cancel) = match self.button {
+            Buttons::Ok => ("[X]", "[ ]"),
+            Buttons::Cancel => ("[ ]", "[X]"),
+        };
+        Paragraph::new(Spans::from(vec![
+            Span::styled(format!("{} {} ", ok, titles.0), style),
+            Span::styled(format!("{} {}", cancel, titles.1), style),
+        ]))
+        .alignment(Alignment::Center)
+    }
+}
+
+fn is_digit(char: char) -> bool {
+    char.is_ascii_digit()
+}
+
+/// Reads the number in the digit `input` named `label`, an empty input means the `default`.
+/// Returns an error if the number is greater than `max`.
+fn number_input<T>(input: &Input, label: &str, default: T, max: T) -> Result<T, String>
+where
+    T: FromStr + PartialOrd + Display,
+{
+    if input.value().is_empty() {
+        return Ok(default);
+    }
+    match input.value().parse() {
+        Ok(number) if number <= max => Ok(number),
+        // the input has only digits, it cannot be parsed only if it is too large
+        _ => Err(format!("{} can be at most {}", label, max)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{MultiRenameDialog, MultiRenameDialogState};
+    use crate::core::test_dir::TestDir;
+    use std::fs;
+    use termion::event::Key;
+
+    #[test]
+    fn test_rename_and_undo() {
+        let dir = TestDir::new("multi-rename");
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        fs::write(dir.join("b.txt"), "b").unwrap();
+
+        let files = vec![dir.join("a.txt"), dir.join("b.txt")];
+        let mut dialog = MultiRenameDialog::new(&dir, files);
+        dialog.handle_key(Key::Backspace);
+        dialog.handle_key(Key::Backspace);
+        dialog.handle_key(Key::Backspace);
+        for char in "file_[C]".chars() {
+            dialog.handle_key(Key::Char(char));
+        }
+        assert_eq!(dialog.planned[1].to, "file_2.txt");
+
+        dialog.handle_key(Key::Char('\n'));
+        assert!(matches!(dialog.state, MultiRenameDialogState::Renamed));
+        assert_eq!(fs::read_to_string(dir.join("file_1.txt")).unwrap(), "a");
+        assert!(dialog.completed_operation().is_some());
+
+        dialog.handle_key(Key::Right);
+        dialog.handle_key(Key::Char('\n'));
+        assert!(dialog.should_quit());
+        assert!(dialog.completed_operation().is_none());
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "a");
+        assert!(!dir.join("file_2.txt").exists());
+    }
+
+    #[test]
+    fn test_conflicts_prevent_renaming() {
+        let dir = TestDir::new("multi-conflict");
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        fs::write(dir.join("b.txt"), "b").unwrap();
+
+        let files = vec![dir.join("a.txt"), dir.join("b.txt")];
+        let mut dialog = MultiRenameDialog::new(&dir, files);
+        for _ in 0..3 {
+            dialog.handle_key(Key::Backspace);
+        }
+        dialog.handle_key(Key::Char('x'));
+        assert!(dialog.planned[1].conflict.is_some());
+
+        dialog.handle_key(Key::Char('\n'));
+        assert!(matches!(
+            dialog.state,
+            MultiRenameDialogState::WaitingForInput
+        ));
+        assert!(dir.join("a.txt").exists());
+    }
+
+    #[test]
+    fn test_invalid_search_pattern_prevents_renaming() {
+        let dir = TestDir::new("multi-regex");
+        fs::write(dir.join("a.txt"), "a").unwrap();
+
+        let mut dialog = MultiRenameDialog::new(&dir, vec![dir.join("a.txt")]);
+        dialog.handle_key(Key::Char('\t'));
+        dialog.handle_key(Key::Char('\t'));
+        dialog.handle_key(Key::Char('('));
+        assert!(dialog.rule_error.is_some());
+
+        dialog.handle_key(Key::Char('\n'));
+        assert!(matches!(
+            dialog.state,
+            MultiRenameDialogState::WaitingForInput
+        ));
+        assert!(dir.join("a.txt").exists());
+
+        dialog.handle_key(Key::Char(')'));
+        assert!(dialog.rule_error.is_none());
+    }
+
+    #[test]
+    fn test_counter_inputs_are_capped() {
+        let dir = TestDir::new("multi-counter");
+        fs::write(dir.join("a.txt"), "a").unwrap();
+
+        let mut dialog = MultiRenameDialog::new(&dir, vec![dir.join("a.txt")]);
+        for _ in 0..6 {
+            dialog.handle_key(Key::Char('\t'));
+        }
+        dialog.handle_key(Key::Backspace);
+        dialog.handle_key(Key::Char('2'));
+        dialog.handle_key(Key::Char('0'));
+        assert_eq!(dialog.rule_error, None);
+        dialog.handle_key(Key::Char('0'));
+        assert_eq!(
+            dialog.rule_error,
+            Some(String::from("Counter width can be at most 20"))
+        );
+
+        dialog.handle_key(Key::BackTab);
+        dialog.handle_key(Key::Backspace);
+        for _ in 0..20 {
+            dialog.handle_key(Key::Char('9'));
+        }
+        assert_eq!(
+            dialog.rule_error,
+            Some(format!("Counter step can be at most {}", u64::MAX))
+        );
+
+        dialog.handle_key(Key::Char('\n'));
+        assert!(matches!(
+            dialog.state,
+            MultiRenameDialogState::WaitingForInput
+        ));
+        assert!(dir.join("a.txt").exists());
+    }
+}
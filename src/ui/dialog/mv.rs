@@ -1,44 +1,55 @@
-use super::{TransferProgress, TransferStrategy};
-use fs_extra::{
-    dir::{
-        move_dir_with_progress, CopyOptions as DirCopyOptions, TransitProcess as DirTransitProcess,
+use super::{
+    cp::{
+        file_progress_handler, local_paths, target_action, transfer_file, transfer_symlink,
+        transfer_tree, transfer_vfs,
     },
-    file::move_file_with_progress,
+    TransferChannel, TransferOptions, TransferStrategy,
+};
+use crate::core::{
+    journal::{CreatedFiles, Operation},
+    vfs::{LocalFs, Vfs},
 };
 use std::{
     path::{Path, PathBuf},
+    sync::Arc,
     thread,
-    time::Duration,
 };
 
-pub struct MoveStrategy;
+#[derive(Clone)]
+pub struct MoveStrategy {
+    vfs: Arc<dyn Vfs>,
+}
+
+impl MoveStrategy {
+    /// Creates a strategy moving the files on the `vfs`.
+    pub fn new(vfs: Arc<dyn Vfs>) -> Self {
+        MoveStrategy { vfs }
+    }
+}
+
+impl Default for MoveStrategy {
+    fn default() -> Self {
+        MoveStrategy::new(Arc::new(LocalFs))
+    }
+}
 
 impl TransferStrategy for MoveStrategy {
     fn transfer_dir<P: AsRef<std::path::Path>>(
         &mut self,
         source: P,
         destination: P,
-        tx: std::sync::mpsc::Sender<super::TransferProgress>,
+        options: TransferOptions,
+        mut channel: TransferChannel,
     ) {
-        let mut options = DirCopyOptions::new();
-        options.buffer_size = 8 * 1024 * 1024; // TODO: configurable buffer, default is 1MB
         let from = PathBuf::from(source.as_ref());
         let to = PathBuf::from(destination.as_ref());
+        let vfs = Arc::clone(&self.vfs);
 
         thread::spawn(move || {
-            let progress_handler = |progress_info: DirTransitProcess| {
-                if tx
-                    .send(TransferProgress::DirTransfer(progress_info))
-                    .is_ok()
-                {}
-                fs_extra::dir::TransitProcessResult::ContinueOrAbort
+            let _result = match local_paths(vfs.as_ref(), &from, &to) {
+                Some((from, to)) => transfer_tree(&from, &to, options, &mut channel, true),
+                None => transfer_vfs(vfs.as_ref(), &from, &to, &mut channel, true),
             };
-            let _result = move_dir_with_progress(
-                AsRef::<Path>::as_ref(&from),
-                AsRef::<Path>::as_ref(&to),
-                &options,
-                progress_handler,
-            );
         });
     }
 
@@ -46,29 +57,97 @@ impl TransferStrategy for MoveStrategy {
         &mut self,
         source: P,
         destination: P,
-        tx: std::sync::mpsc::Sender<super::TransferProgress>,
+        _options: TransferOptions,
+        mut channel: TransferChannel,
     ) {
-        let mut options = fs_extra::file::CopyOptions::new();
-        options.buffer_size = 8 * 1024 * 1024; // TODO: configurable buffer, default is 1MB
         let from = PathBuf::from(source.as_ref());
-        let file_name = from.file_name().unwrap();
-        let mut to = PathBuf::from(destination.as_ref());
-        to.push(Path::new(file_name));
+        let to = PathBuf::from(destination.as_ref());
+        let vfs = Arc::clone(&self.vfs);
 
         thread::spawn(move || {
-            let progress_handler = |progress_info: fs_extra::file::TransitProcess| {
-                if tx
-                    .send(TransferProgress::FileTransfer(progress_info))
-                    .is_ok()
-                {}
-                thread::sleep(Duration::from_millis(500));
+            let (from, to) = match local_paths(vfs.as_ref(), &from, &to) {
+                Some(paths) => paths,
+                None => {
+                    let _result = transfer_vfs(vfs.as_ref(), &from, &to, &mut channel, true);
+                    return;
+                }
             };
-            let _result = move_file_with_progress(
-                AsRef::<Path>::as_ref(&from),
-                AsRef::<Path>::as_ref(&to),
-                &options,
-                progress_handler,
-            );
+            let action = target_action(&from, &to, &mut channel);
+            let _result = channel.retry(&from, |channel| {
+                // a link is moved as it is, across file systems too
+                if from.is_symlink() {
+                    return transfer_symlink(&from, &to, action.clone(), true);
+                }
+                let progress_handler = file_progress_handler(&from, channel);
+                transfer_file(
+                    AsRef::<Path>::as_ref(&from),
+                    AsRef::<Path>::as_ref(&to),
+                    action.clone(),
+                    true,
+                    false,
+                    channel.control(),
+                    progress_handler,
+                )
+            });
         });
     }
+
+    fn vfs(&self) -> &dyn Vfs {
+        self.vfs.as_ref()
+    }
+
+    fn journal_operation(
+        &self,
+        source: &Path,
+        target: &Path,
+        _created: CreatedFiles,
+    ) -> Option<Operation> {
+        Some(Operation::Move {
+            from: source.to_path_buf(),
+            to: target.to_path_buf(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MoveStrategy;
+    use crate::{
+        core::test_dir::TestDir,
+        ui::{TransferChannel, TransferOptions, TransferStrategy},
+    };
+    use std::fs;
+    use std::os::unix::fs::symlink;
+    use std::path::PathBuf;
+    use std::sync::{mpsc, Arc};
+
+    #[test]
+    fn test_move_symlink_keeps_link() {
+        let root = TestDir::new("move-symlink");
+        fs::write(root.join("file.txt"), "content").unwrap();
+        symlink("file.txt", root.join("link")).unwrap();
+        fs::create_dir(root.join("destination")).unwrap();
+
+        let (progress_tx, progress_rx) = mpsc::channel();
+        let (_answer_tx, answer_rx) = mpsc::channel();
+        let channel = TransferChannel::new(progress_tx, answer_rx, None, Arc::default());
+        MoveStrategy::default().transfer_file(
+            root.join("link"),
+            root.join("destination/link"),
+            TransferOptions::default(),
+            channel,
+        );
+        // the worker is done when it drops the channel
+        while progress_rx.recv().is_ok() {}
+
+        assert!(!root.join("link").is_symlink());
+        assert_eq!(
+            fs::read_link(root.join("destination/link")).unwrap(),
+            PathBuf::from("file.txt")
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("file.txt")).unwrap(),
+            "content"
+        );
+    }
 }
@@ -0,0 +1,272 @@
+use super::{display_errors, handle_input_keys};
+use crate::core::{
+    journal::Operation,
+    rename::validate_file_name,
+    template::{create_file, Template},
+};
+use std::{
+    ffi::{OsStr, OsString},
+    path::{Path, PathBuf},
+};
+use termion::event::Key;
+use tui::{
+    layout::Alignment,
+    style::{Color, Style},
+    text::{Span, Spans, Text},
+    widgets::{Block, Borders, Paragraph},
+};
+use tui_input::Input;
+
+enum Buttons {
+    Ok,
+    Cancel,
+}
+
+impl Buttons {
+    fn next(&mut self) {
+        match *self {
+            Buttons::Ok => *self = Buttons::Cancel,
+            Buttons::Cancel => *self = Buttons::Ok,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Components {
+    Name,
+    Template,
+    OpenEditor,
+    Buttons,
+}
+
+impl Components {
+    fn next(&mut self) {
+        *self = match self {
+            Components::Name => Components::Template,
+            Components::Template => Components::OpenEditor,
+            Components::OpenEditor => Components::Buttons,
+            Components::Buttons => Components::Name,
+        }
+    }
+
+    fn previous(&mut self) {
+        *self = match self {
+            Components::Name => Components::Buttons,
+            Components::Template => Components::Name,
+            Components::OpenEditor => Components::Template,
+            Components::Buttons => Components::OpenEditor,
+        }
+    }
+}
+
+/// Represents the state of the dialog
+pub enum NewFileDialogState {
+    WaitingForInput,
+    DisplayErrorMessages(Vec<String>),
+}
+
+/// A dialog for creating a new file, empty or filled from a template.
+///
+/// ## Key controls
+/// * Tab and Shift+Tab: focus the next/previous component
+/// * <- and -> : select the template or a button
+/// * Space: toggle opening the new file in the editor
+/// * Enter: create the file or cancel, depending on the focused button
+/// * Esc: closes the dialog
+pub struct NewFileDialog {
+    button: Buttons,
+    created_file: Option<PathBuf>,
+    focused: Components,
+    input: Input,
+    open_editor: bool,
+    parent_dir: PathBuf,
+    selected_template: usize,
+    should_quit: bool,
+    state: NewFileDialogState,
+    templates: Vec<Template>,
+}
+
+impl NewFileDialog {
+    /// Creates a new dialog for creating a file in `parent_dir` from one of the `templates`.
+    pub fn new<P: AsRef<Path>>(parent_dir: P, templates: Vec<Template>) -> Self {
+        NewFileDialog {
+            button: Buttons::Ok,
+            created_file: None,
+            focused: Components::Name,
+            input: Input::default(),
+            open_editor: false,
+            parent_dir: PathBuf::from(parent_dir.as_ref()),
+            selected_template: 0,
+            should_quit: false,
+            state: NewFileDialogState::WaitingForInput,
+            templates,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: Key) {
+        if let NewFileDialogState::DisplayErrorMessages(_) = self.state {
+            if key == Key::Char('\n') {
+                self.state = NewFileDialogState::WaitingForInput;
+            }
+            return;
+        }
+
+        match key {
+            Key::Char('\n') => match self.button {
+                Buttons::Ok => self.create(),
+                Buttons::Cancel => self.should_quit = true,
+            },
+            Key::Char('\t') => self.focused.next(),
+            Key::BackTab => self.focused.previous(),
+            _ => match self.focused {
+                Components::Name => {
+                    handle_input_keys(&mut self.input, key, |char| !char.is_control())
+                }
+                // the first choice is the empty file
+                Components::Template => match key {
+                    Key::Left | Key::Up => {
+                        self.selected_template = match self.selected_template {
+                            0 => self.templates.len(),
+                            selected => selected - 1,
+                        }
+                    }
+                    Key::Right | Key::Down => {
+                        self.selected_template =
+                            (self.selected_template + 1) % (self.templates.len() + 1)
+                    }
+                    _ => {}
+                },
+                Components::OpenEditor => {
+                    if key == Key::Char(' ') {
+                        self.open_editor = !self.open_editor;
+                    }
+                }
+                Components::Buttons => {
+                    if let Key::Left | Key::Right = key {
+                        self.button.next()
+                    }
+                }
+            },
+        }
+    }
+
+    /// Returns a representation based on the actual state of the dialog to render.
+    pub fn widget(&self) -> Paragraph<'_> {
+        match &self.state {
+            NewFileDialogState::WaitingForInput => self.display_input(),
+            NewFileDialogState::DisplayErrorMessages(messages) => display_errors(messages),
+        }
+    }
+
+    /// Returns the operation to record in the `Journal` if the file was created.
+    pub fn completed_operation(&self) -> Option<Operation> {
+        self.created_file
+            .clone()
+            .map(|path| Operation::NewFile { path })
+    }
+
+    /// Returns the created file if the user asked to open it in the editor.
+    pub fn file_to_edit(&self) -> Option<&Path> {
+        match self.open_editor {
+            true => self.created_file.as_deref(),
+            false => None,
+        }
+    }
+
+    /// Returns the name of the created file.
+    pub fn created_name(&self) -> Option<OsString> {
+        self.created_file
+            .as_ref()
+            .and_then(|file| file.file_name())
+            .map(OsString::from)
+    }
+
+    /// Signals that the dialog should be closed or not
+    pub fn should_quit(&self) -> bool {
+        self.should_quit
+    }
+
+    fn template(&self) -> Option<&Template> {
+        match self.selected_template {
+            0 => None,
+            selected => self.templates.get(selected - 1),
+        }
+    }
+
+    /// Creates the file, the dialog will be closed if it was created,
+    /// otherwise the error will be displayed.
+    fn create(&mut self) {
+        let name = self.input.value();
+        if let Err(message) = validate_file_name(OsStr::new(name)) {
+            self.state = NewFileDialogState::DisplayErrorMessages(vec![message]);
+            return;
+        }
+        let path = self.parent_dir.join(name);
+        match create_file(&path, self.template()) {
+            Ok(()) => {
+                self.created_file = Some(path);
+                self.should_quit = true;
+            }
+            Err(error) => {
+                self.state =
+                    NewFileDialogState::DisplayErrorMessages(vec![format!("{}: {}", name, error)]);
+            }
+        }
+    }
+
+    fn display_input(&self) -> Paragraph<'_> {
+        let style = Style::default().fg(Color::Black);
+        let focused_style = Style::default().bg(Color::Cyan).fg(Color::White);
+        let input_style = Style::default().bg(Color::Cyan).fg(Color::Black);
+        let style_of = |component: Components, default: Style| match self.focused == component {
+            true => focused_style,
+            false => default,
+        };
+        let check_mark = match self.open_editor {
+            true => "[X]",
+            false => "[ ]",
+        };
+        let template = match self.template() {
+            Some(template) => template.name.as_str(),
+            None => "Empty file",
+        };
+        let button_titles = match self.button {
+            Buttons::Ok => ("[X] OK ", "[ ] Cancel"),
+            Buttons::Cancel => ("[ ] OK ", "[X] Cancel"),
+        };
+        let spans = vec![
+            Spans::from(Span::styled("New file name:", style)),
+            Spans::from(Span::styled(
+                self.input.value(),
+                style_of(Components::Name, input_style),
+            )),
+            Spans::from(vec![
+                Span::styled("Template: ", style),
+                Span::styled(
+                    format!("< {} >", template),
+                    style_of(Components::Template, style),
+                ),
+            ]),
+            Spans::from(Span::styled(
+                format!("{} Open in the editor", check_mark),
+                style_of(Components::OpenEditor, style),
+            )),
+            Spans::from(vec![
+                Span::styled(button_titles.0, style_of(Components::Buttons, style)),
+                Span::styled(button_titles.1, style_of(Components::Buttons, style)),
+            ]),
+        ];
+        Paragraph::new(Text::from(spans))
+            .block(
+                Block::default()
+                    .title(Span::styled(
+                        "Creating a new file",
+                        Style::default().fg(Color::Cyan),
+                    ))
+                    .style(Style::default().fg(Color::Black).bg(Color::Gray))
+                    .borders(Borders::ALL)
+                    .title_alignment(Alignment::Center),
+            )
+            .alignment(Alignment::Center)
+    }
+}
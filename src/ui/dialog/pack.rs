@@ -0,0 +1,700 @@
+use super::{
+    display_errors, handle_input_keys, ErrorAnswer, TransferAborted, TransferChannel,
+    TransferDialog, TransferError, TransferOptions, TransferProgress, TransferStrategy,
+};
+use crate::core::{
+    archive::ArchiveFormat,
+    conflict::{ConflictAction, ConflictResolution, FileConflict},
+    copy::CopyProgress,
+    journal::{CreatedFiles, Operation},
+    pack::{member_name, ArchiveWriter, PackOptions, PackReader, MAX_LEVEL},
+    rename::validate_file_name,
+};
+use std::{
+    ffi::OsStr,
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    thread,
+};
+use termion::event::Key;
+use tui::{
+    layout::Alignment,
+    style::{Color, Style},
+    text::{Span, Spans, Text},
+    widgets::{Block, Borders, Paragraph},
+};
+use tui_input::Input;
+use walkdir::WalkDir;
+
+/// Packs files into a new archive. The source of the transfer is the directory
+/// the files are packed from, the destination is the archive.
+#[derive(Clone)]
+pub struct PackStrategy {
+    files: Vec<PathBuf>,
+    options: PackOptions,
+}
+
+impl PackStrategy {
+    pub fn new(files: Vec<PathBuf>, options: PackOptions) -> Self {
+        PackStrategy { files, options }
+    }
+
+    fn pack(&self, base_dir: &Path, archive: &Path, mut channel: TransferChannel) {
+        let files = self.files.clone();
+        let options = self.options;
+        let base_dir = PathBuf::from(base_dir);
+        let archive = PathBuf::from(archive);
+        thread::spawn(move || {
+            let _result = pack_files(&files, &base_dir, &archive, options, &mut channel);
+        });
+    }
+}
+
+impl TransferStrategy for PackStrategy {
+    fn transfer_dir<P: AsRef<Path>>(
+        &mut self,
+        source: P,
+        destination: P,
+        _options: TransferOptions,
+        channel: TransferChannel,
+    ) {
+        self.pack(source.as_ref(), destination.as_ref(), channel);
+    }
+
+    fn transfer_file<P: AsRef<Path>>(
+        &mut self,
+        source: P,
+        destination: P,
+        _options: TransferOptions,
+        channel: TransferChannel,
+    ) {
+        self.pack(source.as_ref(), destination.as_ref(), channel);
+    }
+
+    fn source_is_dir(&self, _source: &Path) -> Option<bool> {
+        Some(true)
+    }
+
+    fn journal_operation(
+        &self,
+        _source: &Path,
+        target: &Path,
+        _created: CreatedFiles,
+    ) -> Option<Operation> {
+        Some(Operation::Pack {
+            archive: target.to_path_buf(),
+        })
+    }
+}
+
+/// Packs the `files` with the files under them into the new `archive`, their names are
+/// relative to `base_dir` unless the options tell otherwise. The user is asked if the archive
+/// exists, and about every file which cannot be read.
+///
+/// The archive is written under a temporary name, and it is renamed when it is complete:
+/// an existing archive is kept if the packing fails or it is cancelled.
+pub(super) fn pack_files(
+    files: &[PathBuf],
+    base_dir: &Path,
+    archive: &Path,
+    options: PackOptions,
+    channel: &mut TransferChannel,
+) -> Result<(), TransferAborted> {
+    let (total_files, total_bytes) = scan_files(files);
+    let archive = match fs::symlink_metadata(archive) {
+        Ok(_) => {
+            let mut conflict = FileConflict::new(base_dir, archive);
+            conflict.source_size = total_bytes;
+            match channel.resolve_file_conflict(conflict) {
+                ConflictAction::Skip => return Ok(()),
+                ConflictAction::WriteTo(archive) => archive,
+                // nothing is appended to an archive, it is replaced
+                ConflictAction::Append => PathBuf::from(archive),
+            }
+        }
+        Err(_) => PathBuf::from(archive),
+    };
+    let partial = partial_path(&archive);
+
+    let mut writer = None;
+    let created = channel.retry(&archive, |_| {
+        writer = Some(ArchiveWriter::create(&partial, options)?);
+        Ok(())
+    })?;
+    let writer = match (created, writer) {
+        (true, Some(writer)) => writer,
+        _ => return Ok(()),
+    };
+    let mut packing = Packing {
+        writer,
+        archive: &archive,
+        partial: &partial,
+        base_dir,
+        options,
+        progress: CopyProgress {
+            total_files,
+            total_bytes,
+            ..CopyProgress::default()
+        },
+    };
+    channel.send(TransferProgress::Progress(packing.progress.clone()));
+
+    let mut result = files
+        .iter()
+        .try_for_each(|file| packing.add_tree(file, channel));
+    if result.is_ok() {
+        result = packing
+            .writer
+            .finish()
+            .map_err(|error| fail(&archive, &error, channel));
+    }
+    if result.is_ok() {
+        result = channel
+            .retry(&archive, |_| fs::rename(&partial, &archive))
+            .map(|_| ());
+    }
+    if fs::symlink_metadata(&partial).is_ok() {
+        let _ = fs::remove_file(&partial);
+    }
+    result
+}
+
+/// The state of packing files into an archive.
+struct Packing<'a> {
+    writer: ArchiveWriter,
+    archive: &'a Path,
+    partial: &'a Path,
+    base_dir: &'a Path,
+    options: PackOptions,
+    progress: CopyProgress,
+}
+
+impl Packing<'_> {
+    /// Adds the `file` with the files under it. The archive is left out of itself.
+    fn add_tree(
+        &mut self,
+        file: &Path,
+        channel: &mut TransferChannel,
+    ) -> Result<(), TransferAborted> {
+        let mut entries = WalkDir::new(file).sort_by_file_name().into_iter();
+        while let Some(entry) = entries.next() {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(error) => {
+                    // a failed directory listing cannot be retried, it is skipped
+                    let path = PathBuf::from(error.path().unwrap_or(file));
+                    if channel.report_error(&path, &io::Error::from(error)) == ErrorAnswer::Abort {
+                        return Err(TransferAborted);
+                    }
+                    continue;
+                }
+            };
+            let path = entry.path();
+            if path == self.partial || path == self.archive {
+                continue;
+            }
+            let name = member_name(path, self.base_dir, self.options.absolute_paths);
+            self.progress.file_name = name.display().to_string();
+
+            let file_type = entry.file_type();
+            if file_type.is_dir() {
+                let mut metadata = None;
+                if !channel.retry(path, |_| {
+                    metadata = Some(fs::symlink_metadata(path)?);
+                    Ok(())
+                })? {
+                    entries.skip_current_dir();
+                    continue;
+                }
+                if let Some(metadata) = metadata {
+                    let result = self.writer.add_dir(&name, &metadata);
+                    result.map_err(|error| fail(path, &error, channel))?;
+                }
+                continue;
+            }
+
+            if file_type.is_symlink() {
+                self.add_symlink(path, &name, channel)?;
+            } else if file_type.is_file() {
+                self.add_file(path, &name, channel)?;
+            } else {
+                channel.send(TransferProgress::Skipped(TransferError {
+                    path: PathBuf::from(path),
+                    message: String::from("Devices, sockets and named pipes are not packed"),
+                }));
+            }
+            self.progress.copied_files += 1;
+            channel.send(TransferProgress::Progress(self.progress.clone()));
+        }
+        Ok(())
+    }
+
+    fn add_symlink(
+        &mut self,
+        path: &Path,
+        name: &Path,
+        channel: &mut TransferChannel,
+    ) -> Result<(), TransferAborted> {
+        let mut link = None;
+        channel.retry(path, |_| {
+            link = Some((fs::symlink_metadata(path)?, fs::read_link(path)?));
+            Ok(())
+        })?;
+        match link {
+            Some((metadata, target)) => self
+                .writer
+                .add_symlink(name, &target, &metadata)
+                .map_err(|error| fail(path, &error, channel)),
+            None => Ok(()),
+        }
+    }
+
+    /// Adds the content of the file at `path`. Only opening the file can be retried,
+    /// the archive cannot be continued if reading the file fails.
+    fn add_file(
+        &mut self,
+        path: &Path,
+        name: &Path,
+        channel: &mut TransferChannel,
+    ) -> Result<(), TransferAborted> {
+        let mut opened = None;
+        channel.retry(path, |_| {
+            let file = File::open(path)?;
+            opened = Some((file.metadata()?, file));
+            Ok(())
+        })?;
+        let (metadata, file) = match opened {
+            Some(opened) => opened,
+            None => return Ok(()),
+        };
+        self.progress.file_total_bytes = metadata.len();
+
+        let progress = &self.progress;
+        let result = {
+            let channel = &*channel;
+            let progress_handler = |file_bytes_copied| {
+                channel.send(TransferProgress::Progress(CopyProgress {
+                    copied_bytes: progress.copied_bytes + file_bytes_copied,
+                    file_bytes_copied,
+                    ..progress.clone()
+                }));
+            };
+            let mut reader = PackReader::new(file, channel.control(), progress_handler);
+            self.writer.add_file(name, &metadata, &mut reader)
+        };
+        result.map_err(|error| fail(path, &error, channel))?;
+        self.progress.copied_bytes += metadata.len();
+        Ok(())
+    }
+}
+
+/// Reports the `error` which made the archive unusable, unless the user cancelled the packing.
+fn fail(path: &Path, error: &io::Error, channel: &TransferChannel) -> TransferAborted {
+    if !channel.control().is_cancelled() {
+        channel.send(TransferProgress::Skipped(TransferError {
+            path: PathBuf::from(path),
+            message: format!("{}, the archive was not created", error),
+        }));
+    }
+    TransferAborted
+}
+
+/// Returns the temporary path the `archive` is written to, in the same directory.
+fn partial_path(archive: &Path) -> PathBuf {
+    let name = archive.file_name().unwrap_or_default().to_string_lossy();
+    archive.with_file_name(format!(".{}.part", name))
+}
+
+/// Counts the files to pack under the `files` and their size in bytes.
+fn scan_files(files: &[PathBuf]) -> (u64, u64) {
+    files
+        .iter()
+        .flat_map(|file| {
+            WalkDir::new(file)
+                .into_iter()
+                .filter_map(|entry| entry.ok())
+        })
+        .filter(|entry| !entry.file_type().is_dir())
+        .fold((0, 0), |(files, bytes), entry| {
+            let size = match entry.file_type().is_file() {
+                true => entry.metadata().map(|metadata| metadata.len()).unwrap_or(0),
+                false => 0,
+            };
+            (files + 1, bytes + size)
+        })
+}
+
+enum Buttons {
+    Ok,
+    Cancel,
+}
+
+impl Buttons {
+    fn next(&mut self) {
+        match *self {
+            Buttons::Ok => *self = Buttons::Cancel,
+            Buttons::Cancel => *self = Buttons::Ok,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Components {
+    Name,
+    Format,
+    Level,
+    Paths,
+    Buttons,
+}
+
+impl Components {
+    fn next(&mut self) {
+        *self = match self {
+            Components::Name => Components::Format,
+            Components::Format => Components::Level,
+            Components::Level => Components::Paths,
+            Components::Paths => Components::Buttons,
+            Components::Buttons => Components::Name,
+        }
+    }
+
+    fn previous(&mut self) {
+        *self = match self {
+            Components::Name => Components::Buttons,
+            Components::Format => Components::Name,
+            Components::Level => Components::Format,
+            Components::Paths => Components::Level,
+            Components::Buttons => Components::Paths,
+        }
+    }
+}
+
+/// Represents the state of the dialog
+pub enum PackDialogState {
+    WaitingForInput,
+    DisplayErrorMessages(Vec<String>),
+}
+
+/// A dialog for packing the marked files into a new archive in another directory.
+/// The packing itself runs in a `TransferDialog` handed over by `take_transfer()`.
+///
+/// ## Key controls
+/// * Tab and Shift+Tab: focus the next/previous component
+/// * <- and -> : select the format, the compression level, relative or absolute paths, or a button
+/// * 0-9: set the compression level
+/// * Enter: start packing or cancel, depending on the focused button
+/// * Esc: closes the dialog
+pub struct PackDialog {
+    archive_dir: PathBuf,
+    base_dir: PathBuf,
+    button: Buttons,
+    conflict_resolution: Option<ConflictResolution>,
+    files: Vec<PathBuf>,
+    focused: Components,
+    input: Input,
+    options: PackOptions,
+    should_quit: bool,
+    state: PackDialogState,
+    transfer: Option<TransferDialog<PackStrategy>>,
+}
+
+impl PackDialog {
+    /// Creates a new dialog for packing the `files` of the `base_dir` directory
+    /// into an archive in `archive_dir`. The archive is named after the file
+    /// if there is only one, otherwise after the `base_dir`.
+    pub fn new<P, Q>(
+        files: Vec<PathBuf>,
+        base_dir: P,
+        archive_dir: Q,
+        conflict_resolution: Option<ConflictResolution>,
+    ) -> Self
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let base_dir = base_dir.as_ref();
+        let options = PackOptions {
+            format: ArchiveFormat::TarGz,
+            level: 6,
+            absolute_paths: false,
+        };
+        let stem = match files.as_slice() {
+            [file] => file.file_name(),
+            _ => base_dir.file_name(),
+        }
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| String::from("archive"));
+        PackDialog {
+            archive_dir: PathBuf::from(archive_dir.as_ref()),
+            base_dir: PathBuf::from(base_dir),
+            button: Buttons::Ok,
+            conflict_resolution,
+            files,
+            focused: Components::Name,
+            input: Input::new(stem + options.format.extension()),
+            options,
+            should_quit: false,
+            state: PackDialogState::WaitingForInput,
+            transfer: None,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: Key) {
+        if let PackDialogState::DisplayErrorMessages(_) = self.state {
+            if key == Key::Char('\n') {
+                self.state = PackDialogState::WaitingForInput;
+            }
+            return;
+        }
+
+        match key {
+            Key::Char('\n') => match self.button {
+                Buttons::Ok => self.pack(),
+                Buttons::Cancel => self.should_quit = true,
+            },
+            Key::Char('\t') => self.focused.next(),
+            Key::BackTab => self.focused.previous(),
+            _ => match self.focused {
+                Components::Name => {
+                    handle_input_keys(&mut self.input, key, |char| !char.is_control())
+                }
+                Components::Format => match key {
+                    Key::Left => self.select_format(ArchiveFormat::ALL.len() - 1),
+                    Key::Right => self.select_format(1),
+                    _ => {}
+                },
+                Components::Level => match key {
+                    Key::Left => self.options.level = self.options.level.saturating_sub(1),
+                    Key::Right => self.options.level = (self.options.level + 1).min(MAX_LEVEL),
+                    Key::Char(char) => {
+                        if let Some(level) = char.to_digit(10) {
+                            self.options.level = level.min(MAX_LEVEL);
+                        }
+                    }
+                    _ => {}
+                },
+                Components::Paths => {
+                    if let Key::Left | Key::Right | Key::Char(' ') = key {
+                        self.options.absolute_paths = !self.options.absolute_paths;
+                    }
+                }
+                Components::Buttons => {
+                    if let Key::Left | Key::Right = key {
+                        self.button.next()
+                    }
+                }
+            },
+        }
+    }
+
+    /// Returns a representation based on the actual state of the dialog to render.
+    pub fn widget(&self) -> Paragraph<'_> {
+        match &self.state {
+            PackDialogState::WaitingForInput => self.display_input(),
+            PackDialogState::DisplayErrorMessages(messages) => display_errors(messages),
+        }
+    }
+
+    /// Returns the started packing to show instead of the dialog.
+    pub fn take_transfer(&mut self) -> Option<TransferDialog<PackStrategy>> {
+        self.transfer.take()
+    }
+
+    /// Signals that the dialog should be closed or not
+    pub fn should_quit(&self) -> bool {
+        self.should_quit
+    }
+
+    /// Selects the format `step` places after the current one,
+    /// the extension of the archive name follows the format.
+    fn select_format(&mut self, step: usize) {
+        let formats = ArchiveFormat::ALL;
+        let index = formats
+            .iter()
+            .position(|format| *format == self.options.format)
+            .unwrap_or(0);
+        let format = formats[(index + step) % formats.len()];
+        let name = self.input.value();
+        if let Some(stem) = name.strip_suffix(self.options.format.extension()) {
+            self.input = Input::new(format!("{}{}", stem, format.extension()));
+        }
+        self.options.format = format;
+    }
+
+    /// Starts packing, or displays the error if the archive name is not valid.
+    fn pack(&mut self) {
+        let name = self.input.value();
+        if let Err(message) = validate_file_name(OsStr::new(name)) {
+            self.state = PackDialogState::DisplayErrorMessages(vec![message]);
+            return;
+        }
+        let strategy = PackStrategy::new(self.files.clone(), self.options);
+        self.transfer = Some(
+            TransferDialog::new(
+                self.base_dir.clone(),
+                self.archive_dir.join(name),
+                strategy,
+                String::from("Pack"),
+            )
+            .with_conflict_resolution(self.conflict_resolution)
+            .started(),
+        );
+        self.should_quit = true;
+    }
+
+    fn display_input(&self) -> Paragraph<'_> {
+        let style = Style::default().fg(Color::Black);
+        let focused_style = Style::default().bg(Color::Cyan).fg(Color::White);
+        let input_style = Style::default().bg(Color::Cyan).fg(Color::Black);
+        let style_of = |component: Components, default: Style| match self.focused == component {
+            true => focused_style,
+            false => default,
+        };
+        let button_titles = match self.button {
+            Buttons::Ok => ("[X] OK ", "[ ] Cancel"),
+            Buttons::Cancel => ("[ ] OK ", "[X] Cancel"),
+        };
+        let formats: Vec<String> = ArchiveFormat::ALL
+            .iter()
+            .map(|format| {
+                let mark = match *format == self.options.format {
+                    true => "(*)",
+                    false => "( )",
+                };
+                format!("{} {}", mark, &format.extension()[1..])
+            })
+            .collect();
+        let level = match self.options.format {
+            ArchiveFormat::Tar => String::from("-"),
+            _ => self.options.level.to_string(),
+        };
+        let paths = match self.options.absolute_paths {
+            false => "(*) Relative ( ) Absolute",
+            true => "( ) Relative (*) Absolute",
+        };
+        let files = match self.files.as_slice() {
+            [file] => file.display().to_string(),
+            files => format!("{} files", files.len()),
+        };
+        let spans = vec![
+            Spans::from(Span::styled(format!("Pack {}", files), style)),
+            Spans::from(Span::styled(
+                format!("Archive in {}:", self.archive_dir.display()),
+                style,
+            )),
+            Spans::from(Span::styled(
+                self.input.value(),
+                style_of(Components::Name, input_style),
+            )),
+            Spans::from(Span::styled(
+                formats.join(" "),
+                style_of(Components::Format, style),
+            )),
+            Spans::from(vec![
+                Span::styled("Compression level (0-9): ", style),
+                Span::styled(level, style_of(Components::Level, style)),
+            ]),
+            Spans::from(vec![
+                Span::styled("Paths: ", style),
+                Span::styled(paths, style_of(Components::Paths, style)),
+            ]),
+            Spans::from(vec![
+                Span::styled(button_titles.0, style_of(Components::Buttons, style)),
+                Span::styled(button_titles.1, style_of(Components::Buttons, style)),
+            ]),
+        ];
+        Paragraph::new(Text::from(spans))
+            .block(
+                Block::default()
+                    .title(Span::styled(
+                        "Packing files",
+                        Style::default().fg(Color::Cyan),
+                    ))
+                    .style(Style::default().fg(Color::Black).bg(Color::Gray))
+                    .borders(Borders::ALL)
+                    .title_alignment(Alignment::Center),
+            )
+            .alignment(Alignment::Center)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::pack_files;
+    use crate::{
+        core::{
+            archive::{ArchiveFormat, ArchiveIndex},
+            conflict::ConflictResolution,
+            pack::PackOptions,
+            test_dir::TestDir,
+        },
+        ui::TransferChannel,
+    };
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+        sync::{mpsc, Arc},
+    };
+
+    fn channel(conflict_resolution: Option<ConflictResolution>) -> TransferChannel {
+        let (progress_tx, _progress_rx) = mpsc::channel();
+        let (_answer_tx, answer_rx) = mpsc::channel();
+        TransferChannel::new(progress_tx, answer_rx, conflict_resolution, Arc::default())
+    }
+
+    fn members(archive: &Path) -> Vec<PathBuf> {
+        let index = ArchiveIndex::open(archive).unwrap();
+        index
+            .tree(Path::new(""))
+            .map(|entry| entry.path.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_pack_files() {
+        let dir = TestDir::new("pack-files");
+        fs::create_dir_all(dir.join("project/src")).unwrap();
+        fs::write(dir.join("project/src/main.rs"), "fn main() {}").unwrap();
+        fs::write(dir.join("project/README"), "readme").unwrap();
+
+        // the archive is created in the packed directory, but it is not packed into itself
+        let archive = dir.join("project/project.zip");
+        let options = PackOptions {
+            format: ArchiveFormat::Zip,
+            level: 0,
+            absolute_paths: false,
+        };
+        let files = [dir.join("project")];
+        pack_files(&files, &dir, &archive, options, &mut channel(None)).unwrap();
+        assert_eq!(
+            members(&archive),
+            [
+                Path::new("project"),
+                Path::new("project/README"),
+                Path::new("project/src"),
+                Path::new("project/src/main.rs"),
+            ]
+        );
+        assert!(!dir.join("project/.project.zip.part").exists());
+
+        // an existing archive is kept if the conflict is skipped, or renamed
+        let files = [dir.join("project/README")];
+        let options = PackOptions {
+            absolute_paths: true,
+            ..options
+        };
+        let mut skip = channel(Some(ConflictResolution::Skip));
+        pack_files(&files, &dir, &archive, options, &mut skip).unwrap();
+        assert_eq!(members(&archive).len(), 4);
+        let mut rename = channel(Some(ConflictResolution::Rename));
+        pack_files(&files, &dir, &archive, options, &mut rename).unwrap();
+        let readme = dir.join("project/README");
+        assert_eq!(
+            members(&dir.join("project/project (1).zip"))
+                .last()
+                .unwrap(),
+            &readme.strip_prefix("/").unwrap()
+        );
+    }
+}
@@ -0,0 +1,304 @@
+use super::display_errors;
+use crate::core::{
+    journal::Operation, list_dir::escape_file_name, rename::validate_file_name, vfs::Vfs,
+};
+use std::{
+    ffi::{OsStr, OsString},
+    io::Stdout,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use termion::{event::Key, raw::RawTerminal};
+use tui::{
+    backend::TermionBackend,
+    layout::{Alignment, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans, Text},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+use tui_input::{Input, InputRequest};
+
+enum Buttons {
+    Ok,
+    Cancel,
+}
+
+impl Buttons {
+    fn next(&mut self) {
+        match *self {
+            Buttons::Ok => *self = Buttons::Cancel,
+            Buttons::Cancel => *self = Buttons::Ok,
+        }
+    }
+}
+
+/// Represents the state of the dialog
+pub enum RenameDialogState {
+    WaitingForInput,
+    DisplayErrorMessage(String),
+}
+
+/// A dialog for renaming a file in its own directory on the `Vfs` of the panel.
+/// The input is prefilled with the current name, and its stem (the name without the extension) is selected:
+/// typing replaces the stem, moving the cursor cancels the selection.
+///
+/// ## Key controls
+/// * <- and -> , Home and End: move the cursor
+/// * Tab: select the OK or Cancel button
+/// * Enter: rename the file or cancel, depending on the selected button
+/// * Esc: closes the dialog without renaming
+pub struct RenameDialog {
+    button: Buttons,
+    input: Input,
+    original_name: OsString,
+    parent_dir: PathBuf,
+    renamed_to: Option<OsString>,
+    should_quit: bool,
+    stem_selected: bool,
+    state: RenameDialogState,
+    vfs: Arc<dyn Vfs>,
+}
+
+impl RenameDialog {
+    /// Creates a new dialog for renaming the file on `path`.
+    pub fn new<P: AsRef<Path>>(path: P, vfs: Arc<dyn Vfs>) -> Self {
+        let path = path.as_ref();
+        let original_name = path.file_name().unwrap_or_default().to_os_string();
+        let name = original_name.to_string_lossy().to_string();
+        let stem_length = Path::new(&original_name)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().chars().count())
+            .unwrap_or_default();
+
+        RenameDialog {
+            button: Buttons::Ok,
+            input: Input::new(name).with_cursor(stem_length),
+            original_name,
+            parent_dir: path.parent().map(PathBuf::from).unwrap_or_default(),
+            renamed_to: None,
+            should_quit: false,
+            stem_selected: stem_length > 0,
+            state: RenameDialogState::WaitingForInput,
+            vfs,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: Key) {
+        if let RenameDialogState::DisplayErrorMessage(_) = self.state {
+            if key == Key::Char('\n') {
+                self.state = RenameDialogState::WaitingForInput;
+            }
+            return;
+        }
+
+        match key {
+            Key::Char('\n') => match self.button {
+                Buttons::Ok => self.rename(),
+                Buttons::Cancel => self.should_quit = true,
+            },
+            Key::Char('\t') | Key::BackTab => self.button.next(),
+            Key::Char(char) => {
+                self.delete_selected_stem();
+                self.input.handle(InputRequest::InsertChar(char));
+            }
+            Key::Backspace | Key::Delete if self.stem_selected => self.delete_selected_stem(),
+            Key::Backspace => {
+                self.input.handle(InputRequest::DeletePrevChar);
+            }
+            Key::Delete => {
+                self.input.handle(InputRequest::DeleteNextChar);
+            }
+            Key::Left => self.move_cursor(InputRequest::GoToPrevChar),
+            Key::Right => self.move_cursor(InputRequest::GoToNextChar),
+            Key::Home => self.move_cursor(InputRequest::GoToStart),
+            Key::End => self.move_cursor(InputRequest::GoToEnd),
+            _ => {}
+        }
+    }
+
+    pub fn render(&self, frame: &mut Frame<TermionBackend<RawTerminal<Stdout>>>, area: Rect) {
+        match &self.state {
+            RenameDialogState::WaitingForInput => frame.render_widget(self.display_input(), area),
+            RenameDialogState::DisplayErrorMessage(message) => {
+                frame.render_widget(display_errors(std::slice::from_ref(message)), area)
+            }
+        }
+    }
+
+    /// Returns the new name of the file after it has been renamed.
+    pub fn renamed_to(&self) -> Option<&OsStr> {
+        self.renamed_to.as_deref()
+    }
+
+    /// Returns the operation to record in the `Journal` if the file was renamed.
+    pub fn completed_operation(&self) -> Option<Operation> {
+        match &self.renamed_to {
+            Some(name) if *name != self.original_name => Some(Operation::Move {
+                from: self.parent_dir.join(&self.original_name),
+                to: self.parent_dir.join(name),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Signals that the dialog should be closed or not
+    pub fn should_quit(&self) -> bool {
+        self.should_quit
+    }
+
+    /// Removes the stem from the input if it is selected.
+    fn delete_selected_stem(&mut self) {
+        if !self.stem_selected {
+            return;
+        }
+        self.stem_selected = false;
+        let extension: String = self
+            .input
+            .value()
+            .chars()
+            .skip(self.input.cursor())
+            .collect();
+        self.input = Input::new(extension).with_cursor(0);
+    }
+
+    fn move_cursor(&mut self, request: InputRequest) {
+        self.stem_selected = false;
+        self.input.handle(request);
+    }
+
+    /// Renames the file to the entered name.
+    /// The dialog will be closed if the file was renamed, otherwise the error will be displayed.
+    fn rename(&mut self) {
+        let new_name = self.input.value();
+        // the name of a non UTF-8 file is displayed lossy, keep it as it is if it was not edited
+        if new_name == self.original_name.to_string_lossy() {
+            self.renamed_to = Some(self.original_name.clone());
+            self.should_quit = true;
+            return;
+        }
+
+        match rename_file(
+            self.vfs.as_ref(),
+            &self.parent_dir,
+            &self.original_name,
+            OsStr::new(new_name),
+        ) {
+            Ok(()) => {
+                self.renamed_to = Some(OsString::from(new_name));
+                self.should_quit = true;
+            }
+            Err(message) => self.state = RenameDialogState::DisplayErrorMessage(message),
+        }
+    }
+
+    fn display_input(&self) -> Paragraph<'_> {
+        let style = Style::default().fg(Color::Black);
+        let button_titles = match self.button {
+            Buttons::Ok => ("[X] OK ", "[ ] Cancel"),
+            Buttons::Cancel => ("[ ] OK ", "[X] Cancel"),
+        };
+        let spans = vec![
+            Spans::from(Span::styled(
+                format!("Rename {} to:", escape_file_name(&self.original_name)),
+                style,
+            )),
+            Spans::from(self.input_spans()),
+            Spans::from(vec![
+                Span::styled(button_titles.0, style),
+                Span::styled(button_titles.1, style),
+            ]),
+        ];
+
+        Paragraph::new(Text::from(spans))
+            .block(
+                Block::default()
+                    .title(Span::styled("Rename", Style::default().fg(Color::Cyan)))
+                    .style(Style::default().fg(Color::Black).bg(Color::Gray))
+                    .borders(Borders::ALL)
+                    .title_alignment(Alignment::Center),
+            )
+            .alignment(Alignment::Center)
+    }
+
+    /// Splits the input into the selected stem or the char under the cursor, and the rest of the input.
+    fn input_spans(&self) -> Vec<Span<'_>> {
+        let input_style = Style::default().bg(Color::Cyan).fg(Color::Black);
+        let highlighted_style = Style::default().bg(Color::Black).fg(Color::White);
+        let value = self.input.value();
+        let byte_index = |char_index: usize| {
+            value
+                .char_indices()
+                .nth(char_index)
+                .map(|(index, _)| index)
+                .unwrap_or(value.len())
+        };
+        let cursor = byte_index(self.input.cursor());
+
+        if self.stem_selected {
+            return vec![
+                Span::styled(&value[..cursor], highlighted_style),
+                Span::styled(&value[cursor..], input_style),
+            ];
+        }
+
+        let after_cursor = byte_index(self.input.cursor() + 1);
+        let under_cursor = match &value[cursor..after_cursor] {
+            "" => " ",
+            char => char,
+        };
+        vec![
+            Span::styled(&value[..cursor], input_style),
+            Span::styled(under_cursor, input_style.add_modifier(Modifier::REVERSED)),
+            Span::styled(&value[after_cursor..], input_style),
+        ]
+    }
+}
+
+/// Renames the file `from` to `to` in the `dir` directory, refusing to overwrite an existing file.
+fn rename_file(vfs: &dyn Vfs, dir: &Path, from: &OsStr, to: &OsStr) -> Result<(), String> {
+    validate_file_name(to)?;
+    let destination = dir.join(to);
+    if vfs.exists(&destination) {
+        return Err(format!("{} already exists", escape_file_name(to)));
+    }
+    vfs.rename(&dir.join(from), &destination)
+        .map_err(|error| error.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{rename_file, RenameDialog};
+    use crate::core::{test_dir::TestDir, vfs::LocalFs};
+    use std::{ffi::OsStr, fs, sync::Arc};
+    use termion::event::Key;
+
+    #[test]
+    fn test_typing_replaces_the_stem() {
+        let mut dialog = RenameDialog::new("/tmp/archive.tar.gz", Arc::new(LocalFs));
+        for char in "backup".chars() {
+            dialog.handle_key(Key::Char(char));
+        }
+        assert_eq!(dialog.input.value(), "backup.gz");
+
+        let mut dialog = RenameDialog::new("/tmp/archive.tar.gz", Arc::new(LocalFs));
+        dialog.handle_key(Key::End);
+        dialog.handle_key(Key::Backspace);
+        assert_eq!(dialog.input.value(), "archive.tar.g");
+    }
+
+    #[test]
+    fn test_rename_file_detects_collision() {
+        let dir = TestDir::new("rename");
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        fs::write(dir.join("b.txt"), "b").unwrap();
+
+        assert_eq!(
+            rename_file(&LocalFs, &dir, OsStr::new("a.txt"), OsStr::new("b.txt")),
+            Err(String::from("b.txt already exists"))
+        );
+        assert!(rename_file(&LocalFs, &dir, OsStr::new("a.txt"), OsStr::new("c.txt")).is_ok());
+        assert_eq!(fs::read_to_string(dir.join("c.txt")).unwrap(), "a");
+        assert!(!dir.join("a.txt").exists());
+    }
+}
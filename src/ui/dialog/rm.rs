@@ -1,23 +1,43 @@
-use std::{io::Stdout, path::PathBuf};
+use super::{display_errors, TransferChannel, TransferError, TransferProgress};
+use crate::core::{
+    calculate_progress_percentage,
+    copy::{CopyProgress, TransferControl},
+    journal::Operation,
+    list_dir::FilterOptions,
+    trash::{Trash, TrashEntry},
+    vfs::Vfs,
+};
+use crate::ui::{Job, JobStart};
+use std::{
+    io::{self, Stdout},
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, TryRecvError},
+        Arc,
+    },
+    thread,
+};
 use termion::{event::Key, raw::RawTerminal};
 use tui::{
     backend::TermionBackend,
-    layout::{Alignment, Rect},
+    layout::{Alignment, Constraint, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Span, Spans, Text},
-    widgets::{Block, Borders, Paragraph, Wrap},
+    widgets::{Block, Borders, Gauge, Paragraph, Wrap},
     Frame,
 };
 
 enum Buttons {
     Ok,
+    Background,
     Cancel,
 }
 
 impl Buttons {
     fn next(&mut self) {
         match *self {
-            Buttons::Ok => *self = Buttons::Cancel,
+            Buttons::Ok => *self = Buttons::Background,
+            Buttons::Background => *self = Buttons::Cancel,
             Buttons::Cancel => *self = Buttons::Ok,
         }
     }
@@ -28,35 +48,87 @@ pub enum DeleteDialogState {
     #[default]
     WaitingForConfirmation,
     Deleting,
+    /// The deletion is over, but some of the files could not be deleted.
     Deleted,
 }
 
+/// Asks for confirmation, then moves the files to the trash, or deletes them permanently
+/// from the `Vfs` of the panel if `permanent` is set, on its own thread showing the progress.
+/// The deletion can be cancelled with Enter or Esc, the files could not be deleted
+/// are listed at the end.
 pub struct RmDirDialog {
+    background_job: Option<Job>,
+    control: Arc<TransferControl>,
     dialog_state: DeleteDialogState,
+    failures: Vec<TransferError>,
     files: Vec<PathBuf>,
     focused_button: Buttons,
+    permanent: bool,
+    progress: CopyProgress,
+    rx: Option<Receiver<TransferProgress>>,
     should_quit: bool,
+    /// The files moved to the trash so far, with the entries they got there.
+    trashed: Vec<TrashEntry>,
+    vfs: Arc<dyn Vfs>,
 }
 
 impl RmDirDialog {
-    pub fn new(files: Vec<PathBuf>) -> Self {
+    pub fn new(files: Vec<PathBuf>, permanent: bool, vfs: Arc<dyn Vfs>) -> Self {
         RmDirDialog {
+            background_job: None,
+            control: Arc::default(),
             dialog_state: DeleteDialogState::default(),
+            failures: Vec::new(),
             files,
             focused_button: Buttons::Cancel,
+            permanent,
+            progress: CopyProgress::default(),
+            rx: None,
             should_quit: false,
+            trashed: Vec::new(),
+            vfs,
         }
     }
 
     pub fn handle_keys(&mut self, key: Key) {
+        match self.dialog_state {
+            DeleteDialogState::Deleting => {
+                if let Key::Char('\n') | Key::Esc = key {
+                    self.control.cancel();
+                }
+                return;
+            }
+            DeleteDialogState::Deleted => {
+                if let Key::Char('\n') = key {
+                    self.should_quit = true;
+                }
+                return;
+            }
+            DeleteDialogState::WaitingForConfirmation => {}
+        }
         match key {
             Key::Char('\n') => {
                 if let DeleteDialogState::WaitingForConfirmation = self.dialog_state {
                     match self.focused_button {
-                        Buttons::Ok => {
-                            self.dialog_state = DeleteDialogState::Deleting;
-                            delete_files(&self.files);
-                            self.dialog_state = DeleteDialogState::Deleted;
+                        Buttons::Ok => self.start_deleting(),
+                        Buttons::Background => {
+                            let files = self.files.clone();
+                            let permanent = self.permanent;
+                            let vfs = Arc::clone(&self.vfs);
+                            let start: JobStart = Box::new(move |channel| {
+                                thread::spawn(move || {
+                                    remove_files(vfs.as_ref(), &files, permanent, channel)
+                                });
+                            });
+                            let operation = match self.permanent {
+                                true => "Delete",
+                                false => "Trash",
+                            };
+                            let title = match self.files.as_slice() {
+                                [file] => format!("{} {}", operation, file.display()),
+                                files => format!("{} {} items", operation, files.len()),
+                            };
+                            self.background_job = Some(Job::queued(title, None, start));
                             self.should_quit = true;
                         }
                         Buttons::Cancel => {
@@ -76,7 +148,26 @@ impl RmDirDialog {
         match self.dialog_state {
             DeleteDialogState::WaitingForConfirmation => self.show_confirmation_dialog(frame, area),
             DeleteDialogState::Deleting => self.show_deleting_files(frame, area),
-            DeleteDialogState::Deleted => {}
+            DeleteDialogState::Deleted => {
+                let mut messages = vec![match self.control.is_cancelled() {
+                    true => String::from("The deletion was cancelled, failed files:"),
+                    false => String::from("Failed files:"),
+                }];
+                messages.extend(
+                    self.failures
+                        .iter()
+                        .map(|failure| format!("{}: {}", failure.path.display(), failure.message)),
+                );
+                frame.render_widget(display_errors(&messages), area);
+            }
+        }
+    }
+
+    /// Returns the height of the dialog, which grows with the list of the failed files.
+    pub fn height(&self) -> u16 {
+        match self.dialog_state {
+            DeleteDialogState::Deleted => (self.failures.len() as u16 + 4).clamp(7, 20),
+            _ => 7,
         }
     }
 
@@ -84,6 +175,60 @@ impl RmDirDialog {
         self.should_quit
     }
 
+    /// Returns the operation to record in the `Journal` if files were moved to the trash
+    /// in the dialog. The permanently deleted files cannot be restored.
+    pub fn completed_operation(&self) -> Option<Operation> {
+        match self.trashed.is_empty() {
+            true => None,
+            false => Some(Operation::Trash {
+                entries: self.trashed.clone(),
+            }),
+        }
+    }
+
+    pub fn is_deleting(&self) -> bool {
+        self.rx.is_some()
+    }
+
+    /// Processes the progress reported by the running deletion.
+    pub fn tick(&mut self) {
+        let rx = match &self.rx {
+            Some(rx) => rx,
+            None => return,
+        };
+        loop {
+            match rx.try_recv() {
+                Ok(TransferProgress::Progress(progress)) => self.progress = progress,
+                Ok(TransferProgress::Skipped(error)) => self.failures.push(error),
+                Ok(TransferProgress::Trashed(entry)) => self.trashed.push(entry),
+                Ok(_) => {}
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => break,
+            }
+        }
+        self.rx = None;
+        self.dialog_state = DeleteDialogState::Deleted;
+        self.should_quit = self.failures.is_empty();
+    }
+
+    fn start_deleting(&mut self) {
+        let (tx, rx) = mpsc::channel();
+        // the deletion never asks, the failed files are collected
+        let (_answers_tx, answers_rx) = mpsc::channel();
+        let channel = TransferChannel::new(tx, answers_rx, None, Arc::clone(&self.control));
+        let files = self.files.clone();
+        let permanent = self.permanent;
+        let vfs = Arc::clone(&self.vfs);
+        thread::spawn(move || remove_files(vfs.as_ref(), &files, permanent, channel));
+        self.rx = Some(rx);
+        self.dialog_state = DeleteDialogState::Deleting;
+    }
+
+    /// Returns the deletion the user sent to the background, if there is one.
+    pub fn take_background_job(&mut self) -> Option<Job> {
+        self.background_job.take()
+    }
+
     fn show_confirmation_dialog(
         &self,
         frame: &mut Frame<TermionBackend<RawTerminal<Stdout>>>,
@@ -91,8 +236,9 @@ impl RmDirDialog {
     ) {
         let button_titles = {
             match self.focused_button {
-                Buttons::Ok => ("[X] OK ", "[ ] Cancel"),
-                Buttons::Cancel => ("[ ] OK ", "[X] Cancel"),
+                Buttons::Ok => ("[X] OK ", "[ ] Background ", "[ ] Cancel"),
+                Buttons::Background => ("[ ] OK ", "[X] Background ", "[ ] Cancel"),
+                Buttons::Cancel => ("[ ] OK ", "[ ] Background ", "[X] Cancel"),
             }
         };
         let spans = vec![
@@ -109,13 +255,17 @@ impl RmDirDialog {
             Spans::from(vec![
                 Span::styled(button_titles.0, Style::default().fg(Color::Black)),
                 Span::styled(button_titles.1, Style::default().fg(Color::Black)),
+                Span::styled(button_titles.2, Style::default().fg(Color::Black)),
             ]),
         ];
         let text = Text::from(spans);
         let p = Paragraph::new(text)
             .block(
                 Block::default()
-                    .title("Delete")
+                    .title(match self.permanent {
+                        true => "Delete",
+                        false => "Move to trash",
+                    })
                     .borders(Borders::ALL)
                     .style(Style::default().bg(Color::LightRed).fg(Color::White)),
             )
@@ -130,50 +280,68 @@ impl RmDirDialog {
         frame: &mut Frame<TermionBackend<RawTerminal<Stdout>>>,
         area: Rect,
     ) {
-        let spans = vec![
-            Spans::from(vec![Span::styled(
-                "Deleting file(s)",
-                Style::default().fg(Color::Black),
-            )]),
-            /*Spans::from(vec![Span::styled(
-                self.get_name(),
-                Style::default().fg(Color::Black),
-            )]),*/
-        ];
-        let text = Text::from(spans);
-        let paragraph = Paragraph::new(text)
-            .block(Block::default().borders(Borders::ALL))
-            .style(Style::default().bg(Color::White).fg(Color::Black))
-            .alignment(Alignment::Center)
-            .wrap(Wrap { trim: true });
-        frame.render_widget(paragraph, area);
+        let style = Style::default().bg(Color::White).fg(Color::Black);
+        let layout = Layout::default()
+            .constraints([Constraint::Length(1); 4])
+            .margin(1)
+            .split(area);
+        let (title, done) = match self.permanent {
+            true => ("Deleting", "Deleted"),
+            false => ("Moving to trash", "Moved"),
+        };
+        let block = Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .style(style);
+        let count = Paragraph::new(Span::styled(
+            format!(
+                "{} {} of {}",
+                done, self.progress.copied_files, self.progress.total_files
+            ),
+            style,
+        ))
+        .alignment(Alignment::Center);
+        let current = Paragraph::new(Span::styled(self.progress.file_name.as_str(), style));
+        let percentage =
+            calculate_progress_percentage(self.progress.copied_files, self.progress.total_files);
+        let gauge = Gauge::default()
+            .percent(percentage as u16)
+            .gauge_style(Style::default().fg(Color::LightRed).bg(Color::White));
+        let cancel = Paragraph::new(Span::styled("[X] Cancel", style)).alignment(Alignment::Center);
+
+        frame.render_widget(block, area);
+        frame.render_widget(count, layout[0]);
+        frame.render_widget(current, layout[1]);
+        frame.render_widget(gauge, layout[2]);
+        frame.render_widget(cancel, layout[3]);
     }
 
     /// Decides the confirmation message to be displayed to the user based on the type
     /// and the count of files on the path marked to delete.
     fn confirm_msg(&self) -> String {
-        let count = self.files.len();
-        if count == 1 {
-            if let Some(file) = self.files.get(0) {
-                if file.is_dir() {
-                    String::from(
-                        "Are you sure you want to delete this folder and all of its content ?",
-                    )
-                } else {
-                    String::from("Are you sure you want to delete this file ?")
-                }
-            } else {
-                String::from("Are you sure you want to delete this ?")
+        let items = match self.files.as_slice() {
+            [file]
+                if self
+                    .vfs
+                    .symlink_stat(file)
+                    .is_ok_and(|stat| stat.is_symlink()) =>
+            {
+                String::from("this link")
             }
-        } else {
-            format!("Are you sure you want to delete {} items ?", count)
+            [file] if self.vfs.is_dir(file) => String::from("this folder and all of its content"),
+            [_] => String::from("this file"),
+            files => format!("{} items", files.len()),
+        };
+        match self.permanent {
+            true => format!("Are you sure you want to permanently delete {} ?", items),
+            false => format!("Move {} to the trash ?", items),
         }
     }
 
     fn get_name(&self) -> String {
         let count = self.files.len();
         if count == 1 {
-            if let Some(file) = self.files.get(0) {
+            if let Some(file) = self.files.first() {
                 file.display().to_string()
             } else {
                 String::new()
@@ -184,12 +352,187 @@ impl RmDirDialog {
     }
 }
 
-fn delete_files(files: &Vec<PathBuf>) {
+/// Moves the files to the trash, or deletes them from the `vfs` if `permanent` is set.
+/// The trash is on the local file system.
+fn remove_files(vfs: &dyn Vfs, files: &[PathBuf], permanent: bool, channel: TransferChannel) {
+    match permanent {
+        true => delete_files(vfs, files, channel),
+        false => trash_files(files, channel),
+    }
+}
+
+/// Moves the files to the trash of their volumes one by one, reporting the number of the
+/// trashed files through the `channel` until it is cancelled.
+/// The files could not be trashed are reported as skipped, along with the reason.
+fn trash_files(files: &[PathBuf], channel: TransferChannel) {
+    let mut progress = CopyProgress {
+        total_files: files.len() as u64,
+        ..CopyProgress::default()
+    };
     for file in files {
-        if file.is_file() || file.is_symlink() {
-            let _ = std::fs::remove_file(file);
-        } else if file.is_dir() {
-            let _ = std::fs::remove_dir_all(file);
+        if !channel.control().proceed() {
+            return;
+        }
+        progress.file_name = file.display().to_string();
+        match Trash::for_path(file).and_then(|trash| trash.put(file)) {
+            Ok(entry) => channel.send(TransferProgress::Trashed(entry)),
+            Err(error) => channel.send(TransferProgress::Skipped(TransferError {
+                path: file.clone(),
+                message: error.to_string(),
+            })),
         }
+        progress.copied_files += 1;
+        channel.send(TransferProgress::Progress(progress.clone()));
+    }
+}
+
+/// Deletes the files and directories recursively from the `vfs`, reporting the number of the
+/// deleted entries and the path being deleted through the `channel` until the deletion is
+/// cancelled. Symbolic links are never followed: a link pointing to a directory is removed
+/// without touching the content of the directory it points to.
+/// The entries could not be deleted are reported as skipped, along with the reason.
+fn delete_files(vfs: &dyn Vfs, files: &[PathBuf], channel: TransferChannel) {
+    let entries: Vec<Vec<WalkEntry>> = files.iter().map(|file| walk(vfs, file)).collect();
+    let mut progress = CopyProgress {
+        total_files: entries.iter().map(|entries| entries.len() as u64).sum(),
+        ..CopyProgress::default()
+    };
+    channel.send(TransferProgress::Progress(progress.clone()));
+    let mut failed: Vec<PathBuf> = Vec::new();
+
+    for entry in entries.into_iter().flatten() {
+        if !channel.control().proceed() {
+            return;
+        }
+        let result = match entry.result {
+            // the directory is left in place if any of its content was left
+            Ok(true) if failed.iter().any(|path| path.starts_with(&entry.path)) => Ok(()),
+            Ok(_) => vfs.remove(&entry.path),
+            Err(error) => Err(error),
+        };
+        if let Err(error) = result {
+            channel.send(TransferProgress::Skipped(TransferError {
+                path: entry.path.clone(),
+                message: error.to_string(),
+            }));
+            failed.push(entry.path.clone());
+        }
+        progress.file_name = entry.path.display().to_string();
+        progress.copied_files += 1;
+        channel.send(TransferProgress::Progress(progress.clone()));
+    }
+}
+
+/// A file found by `walk()`.
+struct WalkEntry {
+    path: PathBuf,
+    /// True for a directory, or the error the file could not be read with.
+    result: io::Result<bool>,
+}
+
+/// Collects the `root` and the files under it, the content of a directory before the directory,
+/// without following symbolic links.
+fn walk(vfs: &dyn Vfs, root: &Path) -> Vec<WalkEntry> {
+    let is_dir = match vfs.symlink_stat(root) {
+        Ok(stat) => stat.is_dir(),
+        Err(error) => {
+            return vec![WalkEntry {
+                path: PathBuf::from(root),
+                result: Err(error),
+            }]
+        }
+    };
+    let mut entries = Vec::new();
+    if is_dir {
+        let filter_options = FilterOptions {
+            show_hidden_files: true,
+        };
+        match vfs.list(root, &filter_options) {
+            Ok(files) => {
+                for file in files {
+                    entries.extend(walk(vfs, &root.join(&file.name)));
+                }
+            }
+            Err(error) => entries.push(WalkEntry {
+                path: PathBuf::from(root),
+                result: Err(error),
+            }),
+        }
+    }
+    entries.push(WalkEntry {
+        path: PathBuf::from(root),
+        result: Ok(is_dir),
+    });
+    entries
+}
+
+#[cfg(test)]
+mod test {
+    use super::delete_files;
+    use crate::{
+        core::{
+            test_dir::TestDir,
+            vfs::{LocalFs, MemoryFs, Vfs},
+        },
+        ui::{TransferChannel, TransferProgress},
+    };
+    use std::{fs, os::unix::fs::symlink, path::PathBuf, sync::mpsc};
+
+    #[test]
+    fn test_delete_files_reports_progress_and_failures() {
+        let dir = TestDir::new("rm");
+        fs::create_dir_all(dir.join("tree/sub")).unwrap();
+        fs::create_dir_all(dir.join("kept")).unwrap();
+        fs::write(dir.join("tree/sub/file.txt"), "content").unwrap();
+        fs::write(dir.join("kept/file.txt"), "content").unwrap();
+        symlink(dir.join("kept"), dir.join("tree/link")).unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let (_answers_tx, answers_rx) = mpsc::channel();
+        let files = vec![dir.join("tree"), dir.join("missing")];
+        delete_files(
+            &LocalFs,
+            &files,
+            TransferChannel::new(tx, answers_rx, None, Default::default()),
+        );
+
+        let mut failures = Vec::new();
+        let mut deleted = 0;
+        for progress in rx.try_iter() {
+            match progress {
+                TransferProgress::Progress(progress) => deleted = progress.copied_files,
+                TransferProgress::Skipped(error) => failures.push(error.path),
+                _ => {}
+            }
+        }
+        assert_eq!(failures, [dir.join("missing")]);
+        assert_eq!(deleted, 5);
+        assert!(!dir.join("tree").exists());
+        // the link is removed, not the directory it points to
+        assert!(dir.join("kept/file.txt").exists());
+    }
+
+    #[test]
+    fn test_delete_files_from_memory() {
+        let vfs = MemoryFs::new();
+        vfs.write("/tree/sub/file.txt", "content");
+        vfs.write("/tree/.hidden", "content");
+        vfs.write("/kept.txt", "content");
+
+        let (tx, rx) = mpsc::channel();
+        let (_answers_tx, answers_rx) = mpsc::channel();
+        delete_files(
+            &vfs,
+            &[PathBuf::from("/tree")],
+            TransferChannel::new(tx, answers_rx, None, Default::default()),
+        );
+
+        let skipped = rx
+            .try_iter()
+            .filter(|progress| matches!(progress, TransferProgress::Skipped(_)))
+            .count();
+        assert_eq!(skipped, 0);
+        assert!(!vfs.exists("/tree".as_ref()));
+        assert!(vfs.exists("/kept.txt".as_ref()));
     }
 }
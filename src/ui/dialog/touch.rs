@@ -0,0 +1,415 @@
+use super::{display_errors, handle_input_keys};
+use crate::core::attributes::{
+    apply_timestamps, file_times, format_timestamp, parse_timestamp, TimestampChange,
+};
+use std::{
+    io::Stdout,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use termion::{event::Key, raw::RawTerminal};
+use tui::{
+    backend::TermionBackend,
+    layout::{Alignment, Rect},
+    style::{Color, Style},
+    text::{Span, Spans, Text},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+use tui_input::Input;
+
+enum Buttons {
+    Ok,
+    Cancel,
+}
+
+impl Buttons {
+    fn next(&mut self) {
+        match *self {
+            Buttons::Ok => *self = Buttons::Cancel,
+            Buttons::Cancel => *self = Buttons::Ok,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Components {
+    Source,
+    SetModified,
+    Modified,
+    SetAccessed,
+    Accessed,
+    Reference,
+    Recursive,
+    Buttons,
+}
+
+impl Components {
+    fn next(&mut self) {
+        *self = match self {
+            Components::Source => Components::SetModified,
+            Components::SetModified => Components::Modified,
+            Components::Modified => Components::SetAccessed,
+            Components::SetAccessed => Components::Accessed,
+            Components::Accessed => Components::Reference,
+            Components::Reference => Components::Recursive,
+            Components::Recursive => Components::Buttons,
+            Components::Buttons => Components::Source,
+        }
+    }
+
+    fn previous(&mut self) {
+        *self = match self {
+            Components::Source => Components::Buttons,
+            Components::SetModified => Components::Source,
+            Components::Modified => Components::SetModified,
+            Components::SetAccessed => Components::Modified,
+            Components::Accessed => Components::SetAccessed,
+            Components::Reference => Components::Accessed,
+            Components::Recursive => Components::Reference,
+            Components::Buttons => Components::Recursive,
+        }
+    }
+}
+
+/// Specifies where the new timestamps are taken from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimeSource {
+    /// The current time.
+    Now,
+    /// The times entered by the user.
+    Explicit,
+    /// The times of a reference file.
+    Reference,
+}
+
+impl TimeSource {
+    fn next(&mut self) {
+        *self = match self {
+            TimeSource::Now => TimeSource::Explicit,
+            TimeSource::Explicit => TimeSource::Reference,
+            TimeSource::Reference => TimeSource::Now,
+        }
+    }
+
+    fn previous(&mut self) {
+        *self = match self {
+            TimeSource::Now => TimeSource::Reference,
+            TimeSource::Explicit => TimeSource::Now,
+            TimeSource::Reference => TimeSource::Explicit,
+        }
+    }
+}
+
+/// Represents the state of the dialog
+pub enum TouchDialogState {
+    WaitingForInput,
+    DisplayErrorMessages(Vec<String>),
+}
+
+/// A dialog for changing the last modification and last access time
+/// of the selected or marked files.
+///
+/// ## Key controls
+/// * Tab and Shift+Tab: focus the next/previous component
+/// * <- and -> : select the source of the times or a button
+/// * Space: toggle the focused checkbox
+/// * Enter: apply the changes or cancel, depending on the focused button
+/// * Esc: closes the dialog without applying the changes
+pub struct TouchDialog {
+    accessed: Input,
+    button: Buttons,
+    cwd: PathBuf,
+    files: Vec<PathBuf>,
+    focused: Components,
+    modified: Input,
+    recursive: bool,
+    reference: Input,
+    set_accessed: bool,
+    set_modified: bool,
+    should_quit: bool,
+    source: TimeSource,
+    state: TouchDialogState,
+}
+
+impl TouchDialog {
+    /// Creates a new dialog for the `files`.
+    /// The times of the first file will be displayed initially.
+    /// A relative reference file path will be resolved against `cwd`.
+    pub fn new<P: AsRef<Path>>(files: Vec<PathBuf>, cwd: P) -> Self {
+        let (modified, accessed) = match files.first().map(|file| file_times(file)) {
+            Some(Ok((modified, accessed))) => {
+                (format_timestamp(modified), format_timestamp(accessed))
+            }
+            _ => (String::new(), String::new()),
+        };
+
+        TouchDialog {
+            accessed: Input::new(accessed),
+            button: Buttons::Ok,
+            cwd: PathBuf::from(cwd.as_ref()),
+            files,
+            focused: Components::Source,
+            modified: Input::new(modified),
+            recursive: false,
+            reference: Input::default(),
+            set_accessed: true,
+            set_modified: true,
+            should_quit: false,
+            source: TimeSource::Now,
+            state: TouchDialogState::WaitingForInput,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: Key) {
+        if let TouchDialogState::DisplayErrorMessages(_) = self.state {
+            if key == Key::Char('\n') {
+                self.should_quit = true;
+            }
+            return;
+        }
+
+        match key {
+            Key::Char('\n') => match self.button {
+                Buttons::Ok => self.apply(),
+                Buttons::Cancel => self.should_quit = true,
+            },
+            Key::Char('\t') => self.focused.next(),
+            Key::BackTab => self.focused.previous(),
+            _ => match self.focused {
+                Components::Source => match key {
+                    Key::Left => self.source.previous(),
+                    Key::Right => self.source.next(),
+                    _ => {}
+                },
+                Components::SetModified => {
+                    if key == Key::Char(' ') {
+                        self.set_modified = !self.set_modified;
+                    }
+                }
+                Components::Modified => handle_input_keys(&mut self.modified, key, is_time_char),
+                Components::SetAccessed => {
+                    if key == Key::Char(' ') {
+                        self.set_accessed = !self.set_accessed;
+                    }
+                }
+                Components::Accessed => handle_input_keys(&mut self.accessed, key, is_time_char),
+                Components::Reference => handle_input_keys(&mut self.reference, key, |_| true),
+                Components::Recursive => {
+                    if key == Key::Char(' ') {
+                        self.recursive = !self.recursive;
+                    }
+                }
+                Components::Buttons => {
+                    if let Key::Left | Key::Right = key {
+                        self.button.next()
+                    }
+                }
+            },
+        }
+    }
+
+    pub fn render(&self, frame: &mut Frame<TermionBackend<RawTerminal<Stdout>>>, area: Rect) {
+        match &self.state {
+            TouchDialogState::WaitingForInput => frame.render_widget(self.display_input(), area),
+            TouchDialogState::DisplayErrorMessages(messages) => {
+                frame.render_widget(display_errors(messages), area)
+            }
+        }
+    }
+
+    /// Signals that the dialog should be closed or not
+    pub fn should_quit(&self) -> bool {
+        self.should_quit
+    }
+
+    /// Applies the new timestamps on the files.
+    /// The dialog will be closed if there were no errors, otherwise the errors will be displayed.
+    fn apply(&mut self) {
+        match self.timestamp_change() {
+            Ok(change) => {
+                let errors = apply_timestamps(&self.files, &change);
+                if errors.is_empty() {
+                    self.should_quit = true;
+                } else {
+                    let messages = errors
+                        .iter()
+                        .map(|error| format!("{}: {}", error.path.display(), error.error))
+                        .collect();
+                    self.state = TouchDialogState::DisplayErrorMessages(messages);
+                }
+            }
+            Err(message) => {
+                self.state = TouchDialogState::DisplayErrorMessages(vec![message]);
+            }
+        }
+    }
+
+    fn timestamp_change(&self) -> Result<TimestampChange, String> {
+        let (modified, accessed) = match self.source {
+            TimeSource::Now => (SystemTime::now(), SystemTime::now()),
+            TimeSource::Explicit => {
+                let modified = match self.set_modified {
+                    true => parse_time(self.modified.value())?,
+                    false => SystemTime::UNIX_EPOCH,
+                };
+                let accessed = match self.set_accessed {
+                    true => parse_time(self.accessed.value())?,
+                    false => SystemTime::UNIX_EPOCH,
+                };
+                (modified, accessed)
+            }
+            TimeSource::Reference => {
+                let reference = self.cwd.join(self.reference.value());
+                file_times(&reference)
+                    .map_err(|error| format!("{}: {}", reference.display(), error))?
+            }
+        };
+
+        Ok(TimestampChange {
+            modified: self.set_modified.then_some(modified),
+            accessed: self.set_accessed.then_some(accessed),
+            recursive: self.recursive,
+        })
+    }
+
+    fn display_input(&self) -> Paragraph<'_> {
+        let style = Style::default().fg(Color::Black);
+        let focused_style = Style::default().bg(Color::Cyan).fg(Color::White);
+        let input_style = Style::default().bg(Color::Cyan).fg(Color::Black);
+        let style_of = |component: Components| match self.focused == component {
+            true => focused_style,
+            false => style,
+        };
+        let check_mark = |checked: bool| match checked {
+            true => "[X]",
+            false => "[ ]",
+        };
+        let radio_button = |source: TimeSource, title: &str| match self.source == source {
+            true => format!("(*) {} ", title),
+            false => format!("( ) {} ", title),
+        };
+
+        let files_label = match self.files.len() {
+            1 => self.files[0].display().to_string(),
+            count => format!("{} items", count),
+        };
+        let button_titles = match self.button {
+            Buttons::Ok => ("[X] OK ", "[ ] Cancel"),
+            Buttons::Cancel => ("[ ] OK ", "[X] Cancel"),
+        };
+        let spans = vec![
+            Spans::from(Span::styled(files_label, style)),
+            Spans::from(vec![
+                Span::styled(
+                    radio_button(TimeSource::Now, "Now"),
+                    style_of(Components::Source),
+                ),
+                Span::styled(
+                    radio_button(TimeSource::Explicit, "Explicit"),
+                    style_of(Components::Source),
+                ),
+                Span::styled(
+                    radio_button(TimeSource::Reference, "Reference file"),
+                    style_of(Components::Source),
+                ),
+            ]),
+            Spans::from(vec![
+                Span::styled(
+                    format!("{} Modified: ", check_mark(self.set_modified)),
+                    style_of(Components::SetModified),
+                ),
+                Span::styled(
+                    format!("{:<19}", self.modified.value()),
+                    match self.focused == Components::Modified {
+                        true => focused_style,
+                        false => input_style,
+                    },
+                ),
+            ]),
+            Spans::from(vec![
+                Span::styled(
+                    format!("{} Accessed: ", check_mark(self.set_accessed)),
+                    style_of(Components::SetAccessed),
+                ),
+                Span::styled(
+                    format!("{:<19}", self.accessed.value()),
+                    match self.focused == Components::Accessed {
+                        true => focused_style,
+                        false => input_style,
+                    },
+                ),
+            ]),
+            Spans::from(vec![
+                Span::styled("Reference file: ", style_of(Components::Reference)),
+                Span::styled(format!("{:<20}", self.reference.value()), input_style),
+            ]),
+            Spans::from(Span::styled(
+                format!("{} Recursive", check_mark(self.recursive)),
+                style_of(Components::Recursive),
+            )),
+            Spans::from(vec![
+                Span::styled(button_titles.0, style_of(Components::Buttons)),
+                Span::styled(button_titles.1, style_of(Components::Buttons)),
+            ]),
+        ];
+
+        Paragraph::new(Text::from(spans))
+            .block(
+                Block::default()
+                    .title(Span::styled(
+                        "Change timestamps",
+                        Style::default().fg(Color::Cyan),
+                    ))
+                    .style(Style::default().fg(Color::Black).bg(Color::Gray))
+                    .borders(Borders::ALL)
+                    .title_alignment(Alignment::Center),
+            )
+            .alignment(Alignment::Center)
+    }
+}
+
+fn parse_time(time: &str) -> Result<SystemTime, String> {
+    parse_timestamp(time).ok_or_else(|| format!("Invalid time: {}", time))
+}
+
+fn is_time_char(char: char) -> bool {
+    char.is_ascii_digit() || char == '.' || char == ':' || char == ' '
+}
+
+#[cfg(test)]
+mod test {
+    use super::{TimeSource, TouchDialog, TouchDialogState};
+    use termion::event::Key;
+
+    #[test]
+    fn test_select_time_source() {
+        let mut dialog = TouchDialog::new(vec![], "/");
+        assert_eq!(dialog.source, TimeSource::Now);
+
+        dialog.handle_key(Key::Right);
+        assert_eq!(dialog.source, TimeSource::Explicit);
+        dialog.handle_key(Key::Left);
+        dialog.handle_key(Key::Left);
+        assert_eq!(dialog.source, TimeSource::Reference);
+    }
+
+    #[test]
+    fn test_invalid_explicit_time_is_reported() {
+        let mut dialog = TouchDialog::new(vec![], "/");
+        dialog.handle_key(Key::Right);
+        dialog.handle_key(Key::Char('\t'));
+        dialog.handle_key(Key::Char('\t'));
+        for char in "2023.13".chars() {
+            dialog.handle_key(Key::Char(char));
+        }
+        dialog.handle_key(Key::Char('\n'));
+
+        match dialog.state {
+            TouchDialogState::DisplayErrorMessages(messages) => {
+                assert_eq!(messages, vec![String::from("Invalid time: 2023.13")])
+            }
+            TouchDialogState::WaitingForInput => panic!("error should be displayed"),
+        }
+    }
+}
@@ -1,11 +1,26 @@
-use super::{TransferProgress, TransferStrategy};
-use crate::core::calculate_progress_percentage;
+use super::{
+    display_errors, handle_input_keys, input_spans, ConflictAnswer, ErrorAnswer, TransferAnswer,
+    TransferChannel, TransferError, TransferOptions, TransferProgress, TransferStrategy,
+};
+use crate::core::{
+    calculate_progress_percentage,
+    conflict::{ConflictResolution, FileConflict},
+    copy::{CopyProgress, TransferControl, TransferRate},
+    journal::{CreatedFiles, Operation},
+    list_dir::{complete_path, real_path},
+    vfs::Vfs,
+};
+use crate::ui::{Job, JobStart};
+use chrono::{DateTime, Local};
 use humansize::{SizeFormatter, DECIMAL};
 use std::{
     io::Stdout,
     path::Path,
-    sync::mpsc::{Receiver, TryRecvError},
-    time::Instant,
+    sync::{
+        mpsc::{Receiver, Sender, TryRecvError},
+        Arc,
+    },
+    time::{Instant, SystemTime},
 };
 use std::{path::PathBuf, sync::mpsc};
 use termion::{event::Key, raw::RawTerminal};
@@ -14,38 +29,106 @@ use tui::{
     layout::{Alignment, Constraint, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Span, Spans, Text},
-    widgets::{Block, Borders, Gauge, Paragraph},
+    widgets::{Block, Borders, Gauge, Paragraph, Wrap},
     Frame,
 };
+use tui_input::Input;
 
 enum Buttons {
     Ok,
+    Background,
     Cancel,
 }
 
 impl Buttons {
     fn next(&mut self) {
         match *self {
-            Buttons::Ok => *self = Buttons::Cancel,
+            Buttons::Ok => *self = Buttons::Background,
+            Buttons::Background => *self = Buttons::Cancel,
             Buttons::Cancel => *self = Buttons::Ok,
         }
     }
 }
 
+/// The buttons shown while transferring.
+#[derive(Clone, Copy, PartialEq)]
+enum ProgressButtons {
+    Pause,
+    Cancel,
+    Background,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Components {
+    Destination,
+    FollowSymlinks,
+    CreateDirs,
+    Verify,
+    Buttons,
+}
+
 #[derive(Default)]
 enum TransferDialogStatus {
     #[default]
     WaitingForConfirmation,
     Transfering,
+    /// The transfer waits for the user to decide about an existing target.
+    Conflict(FileConflict),
+    /// The transfer waits for the user to decide about a file failed to transfer.
+    Error(TransferError),
+    /// The transfer is over, the files failed to transfer are listed.
     TransferFinished,
 }
 
+/// A dialog for copying or moving a file or directory, see `TransferStrategy`.
+///
+/// The destination is prefilled with the other panel's directory, but it can be edited:
+/// a relative path is resolved against the source's directory, and a path which is not
+/// an existing directory is the new name of the transferred file.
+///
+/// ## Key controls
+/// * Up and Down: focus the previous/next component
+/// * Tab: completes the destination path
+/// * Space: toggles the focused checkbox
+/// * Enter: starts the transfer or cancels, depending on the selected button
+///
+/// When the target of a file exists, the user is asked what to do with it:
+/// * Arrows: select the resolution
+/// * Space: toggles whether the rest of the conflicts are resolved the same way
+/// * Enter: continues the transfer
+///
+/// When a file cannot be transferred, the user can retry, skip it, skip all the failing files
+/// or abort the transfer. The failed files are listed when the transfer is over.
+///
+/// The running transfer can be paused and resumed, or cancelled with the buttons or Esc.
+/// The transfer can be handed to the `JobManager` with the Background button,
+/// before it is started or while it is running.
 pub struct TransferDialog<T> {
+    aborted: bool,
+    answers: Option<Sender<TransferAnswer>>,
+    apply_to_all: bool,
+    background_job: Option<Job>,
+    conflict_choice: usize,
+    control: Arc<TransferControl>,
     copy_progress: TransferProgress,
+    /// The files created by the transfer, reported when it completed.
+    created: CreatedFiles,
+    /// The missing parent directories of the target created for the transfer, the parents first.
+    created_dirs: Vec<PathBuf>,
+    error_choice: usize,
+    failures: Vec<TransferError>,
+    default_destination: PathBuf,
+    destination: Input,
+    error: Option<String>,
+    focused: Components,
     focused_button: Buttons,
+    options: TransferOptions,
+    progress_button: ProgressButtons,
+    rate: TransferRate,
     source: PathBuf,
-    destination: PathBuf,
     status: TransferDialogStatus,
+    target: PathBuf,
+    target_existed: bool,
     strategy: T,
     rx: Option<Receiver<TransferProgress>>,
     should_quit: bool,
@@ -55,7 +138,7 @@ pub struct TransferDialog<T> {
 
 impl<T> TransferDialog<T>
 where
-    T: TransferStrategy,
+    T: TransferStrategy + Clone + 'static,
 {
     pub(crate) fn new<P: AsRef<Path>>(
         source: P,
@@ -63,12 +146,35 @@ where
         transfer_model: T,
         title: String,
     ) -> Self {
+        let destination = destination.as_ref();
+        let options = TransferOptions {
+            follow_symlinks: transfer_model.can_follow_symlinks(),
+            ..TransferOptions::default()
+        };
         TransferDialog {
+            aborted: false,
+            answers: None,
+            apply_to_all: false,
+            background_job: None,
+            conflict_choice: 0,
+            control: Arc::default(),
             copy_progress: TransferProgress::None,
+            created: CreatedFiles::default(),
+            created_dirs: Vec::new(),
+            error_choice: 0,
+            failures: Vec::new(),
+            default_destination: PathBuf::from(destination),
+            destination: Input::new(destination.to_string_lossy().to_string()),
+            error: None,
+            focused: Components::Destination,
             focused_button: Buttons::Ok,
+            options,
+            progress_button: ProgressButtons::Pause,
+            rate: TransferRate::default(),
             source: PathBuf::from(source.as_ref()),
-            destination: PathBuf::from(destination.as_ref()),
             status: TransferDialogStatus::default(),
+            target: PathBuf::from(destination),
+            target_existed: false,
             strategy: transfer_model,
             rx: None,
             should_quit: false,
@@ -77,56 +183,386 @@ where
         }
     }
 
+    /// Sets how the existing targets are handled without asking the user.
+    pub(crate) fn with_conflict_resolution(
+        mut self,
+        conflict_resolution: Option<ConflictResolution>,
+    ) -> Self {
+        self.options.conflict_resolution = conflict_resolution;
+        self
+    }
+
     pub(crate) fn handle_key(&mut self, key: Key) {
-        match key {
-            Key::Char('\n') => {
-                if let TransferDialogStatus::WaitingForConfirmation = self.status {
-                    match self.focused_button {
-                        Buttons::Ok => {
-                            self.start_time = Instant::now();
-                            self.status = TransferDialogStatus::Transfering;
-                            let (tx, rx) = mpsc::channel();
-                            self.rx = Some(rx);
-                            if self.source.is_dir() {
-                                self.strategy.transfer_dir::<&std::path::Path>(
-                                    self.source.as_ref(),
-                                    self.destination.as_ref(),
-                                    tx,
-                                );
-                            } else if self.source.is_file() {
-                                self.strategy.transfer_file::<&std::path::Path>(
-                                    self.source.as_ref(),
-                                    self.destination.as_ref(),
-                                    tx,
-                                );
-                            }
+        if key == Key::Esc && self.is_transfering() {
+            return self.cancel();
+        }
+        match self.status {
+            TransferDialogStatus::Transfering => return self.handle_progress_key(key),
+            TransferDialogStatus::Conflict(_) => return self.handle_conflict_key(key),
+            TransferDialogStatus::Error(_) => return self.handle_error_key(key),
+            TransferDialogStatus::TransferFinished => {
+                if key == Key::Char('\n') {
+                    self.should_quit = true;
+                }
+                return;
+            }
+            _ => {}
+        }
+        if let TransferDialogStatus::WaitingForConfirmation = self.status {
+            match key {
+                Key::Char('\n') => match self.focused_button {
+                    Buttons::Ok => self.start_transfer(),
+                    Buttons::Background => self.start_in_background(),
+                    Buttons::Cancel => self.should_quit = true,
+                },
+                Key::Up | Key::BackTab => self.focus_previous(),
+                Key::Down => self.focus_next(),
+                Key::Char('\t') => match self.focused {
+                    Components::Destination => self.complete_destination(),
+                    _ => self.focus_next(),
+                },
+                _ => match self.focused {
+                    Components::Destination => {
+                        handle_input_keys(&mut self.destination, key, |_| true)
+                    }
+                    Components::FollowSymlinks => {
+                        if key == Key::Char(' ') {
+                            self.options.follow_symlinks = !self.options.follow_symlinks;
+                        }
+                    }
+                    Components::CreateDirs => {
+                        if key == Key::Char(' ') {
+                            self.options.create_dirs = !self.options.create_dirs;
                         }
-                        Buttons::Cancel => self.should_quit = true,
                     }
+                    Components::Verify => {
+                        if key == Key::Char(' ') {
+                            self.options.verify = !self.options.verify;
+                        }
+                    }
+                    Components::Buttons => {
+                        if let Key::Left | Key::Right = key {
+                            self.focused_button.next();
+                        }
+                    }
+                },
+            }
+        }
+    }
+
+    fn handle_progress_key(&mut self, key: Key) {
+        match key {
+            Key::Left | Key::Right | Key::Char('\t') => {
+                self.progress_button = match self.progress_button {
+                    ProgressButtons::Pause => ProgressButtons::Cancel,
+                    ProgressButtons::Cancel => ProgressButtons::Background,
+                    ProgressButtons::Background => ProgressButtons::Pause,
                 }
             }
-            Key::Left | Key::Right | Key::Up | Key::Down => {
-                self.focused_button.next();
+            Key::Char('\n') => match self.progress_button {
+                ProgressButtons::Pause => self.control.set_paused(!self.control.is_paused()),
+                ProgressButtons::Cancel => self.cancel(),
+                ProgressButtons::Background => self.send_to_background(),
+            },
+            _ => {}
+        }
+    }
+
+    /// Returns true if the transfer was started and it is not over yet.
+    pub(crate) fn is_transfering(&self) -> bool {
+        self.rx.is_some()
+    }
+
+    /// Stops the running transfer: it quits after the file being transferred,
+    /// which is removed if it was not completed.
+    fn cancel(&mut self) {
+        self.control.cancel();
+        self.aborted = true;
+        // a transfer waiting for an answer is unblocked by closing the channel
+        self.answers = None;
+        self.status = TransferDialogStatus::Transfering;
+    }
+
+    fn handle_conflict_key(&mut self, key: Key) {
+        let count = ConflictResolution::ALL.len();
+        let per_row = count / 2;
+        match key {
+            Key::Left => self.conflict_choice = (self.conflict_choice + count - 1) % count,
+            Key::Right => self.conflict_choice = (self.conflict_choice + 1) % count,
+            Key::Up | Key::Down => self.conflict_choice = (self.conflict_choice + per_row) % count,
+            Key::Char(' ') => self.apply_to_all = !self.apply_to_all,
+            Key::Char('\n') => {
+                self.answer(TransferAnswer::Conflict(ConflictAnswer {
+                    resolution: ConflictResolution::ALL[self.conflict_choice],
+                    apply_to_all: self.apply_to_all,
+                }));
             }
             _ => {}
         }
     }
 
+    fn handle_error_key(&mut self, key: Key) {
+        let count = ErrorAnswer::ALL.len();
+        match key {
+            Key::Left => self.error_choice = (self.error_choice + count - 1) % count,
+            Key::Right | Key::Char('\t') => self.error_choice = (self.error_choice + 1) % count,
+            Key::Char('\n') => {
+                let answer = ErrorAnswer::ALL[self.error_choice];
+                if let TransferDialogStatus::Error(error) = &self.status {
+                    if answer != ErrorAnswer::Retry {
+                        self.failures.push(error.clone());
+                    }
+                }
+                self.aborted = answer == ErrorAnswer::Abort;
+                self.answer(TransferAnswer::Error(answer));
+            }
+            _ => {}
+        }
+    }
+
+    /// Sends the `answer` to the waiting transfer and continues to show its progress.
+    fn answer(&mut self, answer: TransferAnswer) {
+        if let Some(answers) = &self.answers {
+            let _ = answers.send(answer);
+        }
+        self.status = TransferDialogStatus::Transfering;
+    }
+
+    /// Returns the focusable components: the symbolic link and the verify options are shown
+    /// only if the strategy supports them.
+    fn components(&self) -> Vec<Components> {
+        let mut components = vec![Components::Destination];
+        if self.strategy.can_follow_symlinks() {
+            components.push(Components::FollowSymlinks);
+        }
+        components.push(Components::CreateDirs);
+        if self.strategy.can_verify() {
+            components.push(Components::Verify);
+        }
+        components.push(Components::Buttons);
+        components
+    }
+
+    fn focus_next(&mut self) {
+        let components = self.components();
+        let index = components.iter().position(|c| *c == self.focused);
+        let next = index.map(|index| (index + 1) % components.len());
+        self.focused = components[next.unwrap_or(0)];
+    }
+
+    fn focus_previous(&mut self) {
+        let components = self.components();
+        let index = components.iter().position(|c| *c == self.focused);
+        let previous = index.map(|index| (index + components.len() - 1) % components.len());
+        self.focused = components[previous.unwrap_or(0)];
+    }
+
+    fn complete_destination(&mut self) {
+        if let Some(completed) = complete_path(self.source_dir(), self.destination.value()) {
+            self.destination = Input::new(completed);
+        }
+    }
+
+    fn source_dir(&self) -> &Path {
+        self.source.parent().unwrap_or_else(|| Path::new("/"))
+    }
+
+    /// Resolves the full path of the transferred file from the entered destination.
+    /// Its missing parent directories are accepted if the user asked for creating them,
+    /// they are created only when the transfer starts.
+    fn resolve_target(&self) -> Result<PathBuf, String> {
+        let value = self.destination.value();
+        if value.is_empty() {
+            return Err(String::from("The destination cannot be empty"));
+        }
+        // a non UTF-8 path is displayed lossy, keep it as it is if it was not edited
+        let destination = match value == self.default_destination.to_string_lossy() {
+            true => self.default_destination.clone(),
+            false => self.source_dir().join(value),
+        };
+        let vfs = self.strategy.vfs();
+        let target = match (
+            vfs.is_dir(&destination) || value.ends_with('/'),
+            self.source.file_name(),
+        ) {
+            (true, Some(file_name)) if !self.strategy.transfers_contents() => {
+                destination.join(file_name)
+            }
+            _ => destination,
+        };
+
+        // compared by their real paths, the entered destination can contain `..` and links,
+        // the paths of other file systems are compared as they are
+        let resolve = |path: &Path| match vfs.local_path(path) {
+            Some(path) => real_path(&path),
+            None => PathBuf::from(path),
+        };
+        let source_is_symlink = vfs
+            .symlink_stat(&self.source)
+            .is_ok_and(|stat| stat.is_symlink());
+        let real_source = match source_is_symlink && !self.options.follow_symlinks {
+            true => resolve(self.source_dir()).join(self.source.file_name().unwrap_or_default()),
+            false => resolve(&self.source),
+        };
+        let real_target = resolve(&target);
+        if real_target == real_source {
+            return Err(String::from("Cannot transfer a file onto itself"));
+        }
+        if vfs.is_dir(&self.source) && real_target.starts_with(&real_source) {
+            return Err(String::from("Cannot transfer a directory into itself"));
+        }
+        if let Some(parent) = target.parent() {
+            if !vfs.is_dir(parent) && !self.options.create_dirs {
+                return Err(format!("{} does not exist", parent.display()));
+            }
+        }
+        Ok(target)
+    }
+
+    fn start_transfer(&mut self) {
+        self.target = match self.resolve_target() {
+            Ok(target) => target,
+            Err(message) => {
+                self.error = Some(message);
+                return;
+            }
+        };
+        self.begin_transfer();
+    }
+
+    /// Starts the transfer to the destination at once, without asking for confirmation,
+    /// when its options were chosen in another dialog, e.g. in the `PackDialog`.
+    pub(crate) fn started(mut self) -> Self {
+        self.begin_transfer();
+        self
+    }
+
+    fn begin_transfer(&mut self) {
+        if self.options.create_dirs {
+            let (created, result) = create_parent_dirs(self.strategy.vfs(), &self.target);
+            self.created_dirs.extend(created);
+            if let Err(message) = result {
+                self.error = Some(message);
+                return;
+            }
+        }
+        self.target_existed = self.strategy.vfs().exists(&self.target);
+        self.start_time = Instant::now();
+        self.rate = TransferRate::default();
+        self.status = TransferDialogStatus::Transfering;
+        let (tx, rx) = mpsc::channel();
+        let (answers_tx, answers_rx) = mpsc::channel();
+        self.rx = Some(rx);
+        self.answers = Some(answers_tx);
+        let channel = TransferChannel::new(
+            tx,
+            answers_rx,
+            self.options.conflict_resolution,
+            Arc::clone(&self.control),
+        );
+        transfer(
+            &mut self.strategy,
+            &self.source,
+            &self.target,
+            self.options,
+            channel,
+        );
+    }
+
+    /// Queues the transfer in the `JobManager` instead of starting it in the dialog.
+    fn start_in_background(&mut self) {
+        self.target = match self.resolve_target() {
+            Ok(target) => target,
+            Err(message) => {
+                self.error = Some(message);
+                return;
+            }
+        };
+
+        let mut strategy = self.strategy.clone();
+        let source = self.source.clone();
+        let target = self.target.clone();
+        let options = self.options;
+        // the directories created by a background job are not recorded in the journal, like the job
+        let start: JobStart = Box::new(move |channel| {
+            if options.create_dirs {
+                if let (_, Err(message)) = create_parent_dirs(strategy.vfs(), &target) {
+                    return channel.send(TransferProgress::Skipped(TransferError {
+                        path: target,
+                        message,
+                    }));
+                }
+            }
+            transfer(&mut strategy, &source, &target, options, channel)
+        });
+        self.background_job = Some(Job::queued(
+            self.job_title(),
+            self.options.conflict_resolution,
+            start,
+        ));
+        self.should_quit = true;
+    }
+
+    /// Hands the running transfer to the `JobManager`.
+    fn send_to_background(&mut self) {
+        if let Some(rx) = self.rx.take() {
+            self.background_job = Some(Job::running(
+                self.job_title(),
+                rx,
+                Arc::clone(&self.control),
+                std::mem::take(&mut self.failures),
+            ));
+            self.answers = None;
+            self.should_quit = true;
+        }
+    }
+
+    fn job_title(&self) -> String {
+        format!(
+            "{} {} → {}",
+            self.title,
+            self.source.display(),
+            self.target.display()
+        )
+    }
+
+    /// Returns the transfer the user sent to the background, if there is one.
+    pub(crate) fn take_background_job(&mut self) -> Option<Job> {
+        self.background_job.take()
+    }
+
     pub(crate) fn tick(&mut self) {
         if let Some(rx) = &self.rx {
-            match rx.try_recv() {
-                Ok(copy_progress) => {
-                    self.copy_progress = copy_progress;
-                }
-                Err(_err) => match _err {
-                    TryRecvError::Disconnected => {
+            // the progress is reported after every chunk, only the latest one is shown
+            loop {
+                match rx.try_recv() {
+                    Ok(TransferProgress::Conflict(conflict)) => {
+                        self.status = TransferDialogStatus::Conflict(conflict);
+                        break;
+                    }
+                    Ok(TransferProgress::Error(error)) => {
+                        self.error_choice = 0;
+                        self.status = TransferDialogStatus::Error(error);
+                        break;
+                    }
+                    Ok(TransferProgress::Skipped(error)) => self.failures.push(error),
+                    Ok(TransferProgress::Completed(created)) => self.created = created,
+                    Ok(copy_progress) => {
+                        self.copy_progress = copy_progress;
+                    }
+                    Err(TryRecvError::Disconnected) => {
+                        self.rx = None;
                         self.status = TransferDialogStatus::TransferFinished;
-                        self.should_quit = true;
+                        self.should_quit = self.failures.is_empty();
+                        break;
                     }
-                    TryRecvError::Empty => {}
-                },
+                    Err(TryRecvError::Empty) => break,
+                }
             }
         }
+        if let TransferProgress::Progress(progress) = &self.copy_progress {
+            self.rate.update(Instant::now(), progress.copied_bytes);
+        }
     }
 
     pub(crate) fn render(
@@ -134,12 +570,27 @@ where
         frame: &mut Frame<TermionBackend<RawTerminal<Stdout>>>,
         area: Rect,
     ) {
-        match self.status {
+        match &self.status {
             TransferDialogStatus::WaitingForConfirmation => {
                 self.show_confirmation_dialog(frame, area)
             }
             TransferDialogStatus::Transfering => self.show_transfer_progress(frame, area),
-            TransferDialogStatus::TransferFinished => (),
+            TransferDialogStatus::Conflict(conflict) => {
+                self.show_conflict_dialog(frame, area, conflict)
+            }
+            TransferDialogStatus::Error(error) => self.show_error_dialog(frame, area, error),
+            TransferDialogStatus::TransferFinished => {
+                let mut messages = vec![match self.aborted {
+                    true => String::from("The transfer was aborted, failed files:"),
+                    false => String::from("Failed files:"),
+                }];
+                messages.extend(
+                    self.failures
+                        .iter()
+                        .map(|failure| format!("{}: {}", failure.path.display(), failure.message)),
+                );
+                frame.render_widget(display_errors(&messages), area);
+            }
         }
     }
 
@@ -150,20 +601,23 @@ where
     ) {
         let button_titles = {
             match self.focused_button {
-                Buttons::Ok => ("[X] OK ", "[ ] Cancel"),
-                Buttons::Cancel => ("[ ] OK ", "[X] Cancel"),
+                Buttons::Ok => ("[X] OK ", "[ ] Background ", "[ ] Cancel"),
+                Buttons::Background => ("[ ] OK ", "[X] Background ", "[ ] Cancel"),
+                Buttons::Cancel => ("[ ] OK ", "[ ] Background ", "[X] Cancel"),
             }
         };
+        let style = Style::default().fg(Color::White);
+        let style_of = |component: Components| match self.focused == component {
+            true => Style::default().bg(Color::Cyan).fg(Color::White),
+            false => style,
+        };
+        let check_mark = |checked: bool| match checked {
+            true => "[X]",
+            false => "[ ]",
+        };
         let dialog_area = Rect::new(area.x, area.y, area.width, area.height);
         let layout = Layout::default()
-            .constraints([
-                Constraint::Length(1),
-                Constraint::Length(1),
-                Constraint::Length(1),
-                Constraint::Length(1),
-                Constraint::Length(1),
-                Constraint::Length(1),
-            ])
+            .constraints([Constraint::Length(1); 9])
             .direction(tui::layout::Direction::Vertical)
             .margin(1)
             .split(area);
@@ -173,7 +627,7 @@ where
             .borders(Borders::ALL)
             .title(self.title.as_ref())
             .title_alignment(Alignment::Center);
-        let label_src = Paragraph::new(Text::styled("Source:", Style::default().fg(Color::White)));
+        let label_src = Paragraph::new(Text::styled("Source:", style));
         let label_src_path = Paragraph::new(Text::styled(
             self.source.display().to_string(),
             Style::default()
@@ -182,65 +636,215 @@ where
                 .add_modifier(Modifier::BOLD),
         ));
         let label_dest = Paragraph::new(Text::styled(
-            "Destination:",
-            Style::default().fg(Color::White),
+            "Destination (Tab completes):",
+            style_of(Components::Destination),
         ));
-        let label_dest_path = Paragraph::new(Text::styled(
-            self.destination.display().to_string(),
+        let dest_input = Paragraph::new(Spans::from(input_spans(
+            &self.destination,
+            layout[3].width as usize,
             Style::default()
                 .bg(Color::Cyan)
                 .fg(Color::White)
                 .add_modifier(Modifier::BOLD),
-        ));
-        let buttons = Paragraph::new(Spans::from(vec![
-            Span::styled(button_titles.0, Style::default().fg(Color::White)),
-            Span::styled(button_titles.1, Style::default().fg(Color::White)),
-        ]))
-        .alignment(Alignment::Center);
+        )));
+        let mut rows = vec![label_src, label_src_path, label_dest, dest_input];
+        if self.strategy.can_follow_symlinks() {
+            rows.push(Paragraph::new(Text::styled(
+                format!(
+                    "{} Follow symbolic links",
+                    check_mark(self.options.follow_symlinks)
+                ),
+                style_of(Components::FollowSymlinks),
+            )));
+        }
+        rows.push(Paragraph::new(Text::styled(
+            format!(
+                "{} Create missing directories",
+                check_mark(self.options.create_dirs)
+            ),
+            style_of(Components::CreateDirs),
+        )));
+        if self.strategy.can_verify() {
+            rows.push(Paragraph::new(Text::styled(
+                format!("{} Verify after copy", check_mark(self.options.verify)),
+                style_of(Components::Verify),
+            )));
+        }
+        rows.push(Paragraph::new(Text::styled(
+            self.error.clone().unwrap_or_default(),
+            Style::default().fg(Color::LightRed),
+        )));
+        rows.push(
+            Paragraph::new(Spans::from(vec![
+                Span::styled(button_titles.0, style_of(Components::Buttons)),
+                Span::styled(button_titles.1, style_of(Components::Buttons)),
+                Span::styled(button_titles.2, style_of(Components::Buttons)),
+            ]))
+            .alignment(Alignment::Center),
+        );
 
         frame.render_widget(block, dialog_area);
-        frame.render_widget(label_src, layout[0]);
-        frame.render_widget(label_src_path, layout[1]);
-        frame.render_widget(label_dest, layout[2]);
-        frame.render_widget(label_dest_path, layout[3]);
-        frame.render_widget(buttons, layout[4]);
+        for (row, area) in rows.into_iter().zip(layout.iter()) {
+            frame.render_widget(row, *area);
+        }
     }
 
-    fn show_transfer_progress(
+    fn show_conflict_dialog(
         &self,
         frame: &mut Frame<TermionBackend<RawTerminal<Stdout>>>,
         area: Rect,
+        conflict: &FileConflict,
     ) {
-        let (total_percent, partial_percent) = match &self.copy_progress {
-            TransferProgress::DirTransfer(dir_progress) => (
-                calculate_progress_percentage(dir_progress.copied_bytes, dir_progress.total_bytes),
-                calculate_progress_percentage(
-                    dir_progress.file_bytes_copied,
-                    dir_progress.file_total_bytes,
-                ),
-            ),
-            TransferProgress::FileTransfer(file_progress) => (
-                0,
-                calculate_progress_percentage(
-                    file_progress.copied_bytes,
-                    file_progress.total_bytes,
+        let style = Style::default().fg(Color::White);
+        let layout = Layout::default()
+            .constraints([Constraint::Length(1); 9])
+            .direction(tui::layout::Direction::Vertical)
+            .margin(1)
+            .split(area);
+        let block = Block::default()
+            .border_type(tui::widgets::BorderType::Rounded)
+            .borders(Borders::ALL)
+            .title("File exists")
+            .title_alignment(Alignment::Center);
+        let file_label = |label: &str, size: u64, modified: Option<SystemTime>| {
+            let modified = modified
+                .map(|modified| {
+                    DateTime::<Local>::from(modified)
+                        .format("%Y.%m.%d %H:%M")
+                        .to_string()
+                })
+                .unwrap_or_default();
+            Paragraph::new(Text::styled(
+                format!(
+                    "{} {} {}",
+                    label,
+                    SizeFormatter::new(size, DECIMAL),
+                    modified
                 ),
-            ),
-            TransferProgress::None => (0, 0),
+                style,
+            ))
         };
-        let file_name = match &self.copy_progress {
-            TransferProgress::DirTransfer(dir_progress) => dir_progress.file_name.clone(),
-            TransferProgress::FileTransfer(_) => self.source.display().to_string(),
-            TransferProgress::None => String::new(),
+        let resolution_row = |resolutions: &[ConflictResolution], offset: usize| {
+            let spans: Vec<Span> = resolutions
+                .iter()
+                .enumerate()
+                .map(|(index, resolution)| {
+                    let selected = offset + index == self.conflict_choice;
+                    Span::styled(
+                        format!(
+                            "({}) {} ",
+                            if selected { '*' } else { ' ' },
+                            resolution.title()
+                        ),
+                        match selected {
+                            true => Style::default().bg(Color::Cyan).fg(Color::White),
+                            false => style,
+                        },
+                    )
+                })
+                .collect();
+            Paragraph::new(Spans::from(spans)).alignment(Alignment::Center)
         };
-        let (copied_bytes, total_bytes) = match &self.copy_progress {
-            TransferProgress::DirTransfer(dir_progress) => {
-                (dir_progress.file_bytes_copied, dir_progress.total_bytes)
-            }
-            TransferProgress::FileTransfer(file_progress) => {
-                (file_progress.copied_bytes, file_progress.total_bytes)
-            }
-            TransferProgress::None => (0, 0),
+        let (first_row, second_row) =
+            ConflictResolution::ALL.split_at(ConflictResolution::ALL.len() / 2);
+        let check_mark = match self.apply_to_all {
+            true => "[X]",
+            false => "[ ]",
+        };
+
+        let rows = vec![
+            Paragraph::new(Text::styled(
+                conflict.target.display().to_string(),
+                Style::default()
+                    .bg(Color::Cyan)
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+            )),
+            file_label("Source:", conflict.source_size, conflict.source_modified),
+            file_label("Target:", conflict.target_size, conflict.target_modified),
+            Paragraph::new(Text::raw("")),
+            resolution_row(first_row, 0),
+            resolution_row(second_row, first_row.len()),
+            Paragraph::new(Text::styled(format!("{} Apply to all", check_mark), style))
+                .alignment(Alignment::Center),
+            Paragraph::new(Text::styled("Enter: continue", style)).alignment(Alignment::Center),
+        ];
+
+        frame.render_widget(block, area);
+        for (row, area) in rows.into_iter().zip(layout.iter()) {
+            frame.render_widget(row, *area);
+        }
+    }
+
+    fn show_error_dialog(
+        &self,
+        frame: &mut Frame<TermionBackend<RawTerminal<Stdout>>>,
+        area: Rect,
+        error: &TransferError,
+    ) {
+        let style = Style::default().bg(Color::LightRed).fg(Color::White);
+        let layout = Layout::default()
+            .constraints([Constraint::Length(1); 9])
+            .direction(tui::layout::Direction::Vertical)
+            .margin(1)
+            .split(area);
+        let block = Block::default()
+            .border_type(tui::widgets::BorderType::Rounded)
+            .borders(Borders::ALL)
+            .title("Error")
+            .title_alignment(Alignment::Center)
+            .style(style);
+        let buttons: Vec<Span> = ErrorAnswer::ALL
+            .iter()
+            .enumerate()
+            .map(|(index, answer)| {
+                let check_mark = match index == self.error_choice {
+                    true => "[X]",
+                    false => "[ ]",
+                };
+                Span::styled(format!("{} {} ", check_mark, answer.title()), style)
+            })
+            .collect();
+        let message =
+            Paragraph::new(Text::styled(error.message.as_str(), style)).wrap(Wrap { trim: false });
+
+        frame.render_widget(block, area);
+        frame.render_widget(
+            Paragraph::new(Text::styled(
+                format!("Cannot transfer {}", error.path.display()),
+                style.add_modifier(Modifier::BOLD),
+            )),
+            layout[0],
+        );
+        frame.render_widget(
+            message,
+            Rect::new(layout[1].x, layout[1].y, layout[1].width, 5),
+        );
+        frame.render_widget(
+            Paragraph::new(Spans::from(buttons)).alignment(Alignment::Center),
+            layout[7],
+        );
+    }
+
+    fn show_transfer_progress(
+        &self,
+        frame: &mut Frame<TermionBackend<RawTerminal<Stdout>>>,
+        area: Rect,
+    ) {
+        let progress = match &self.copy_progress {
+            TransferProgress::Progress(progress) => progress.clone(),
+            _ => CopyProgress::default(),
+        };
+        let total_percent =
+            calculate_progress_percentage(progress.copied_bytes, progress.total_bytes);
+        let partial_percent =
+            calculate_progress_percentage(progress.file_bytes_copied, progress.file_total_bytes);
+        let remaining_time = match self
+            .rate
+            .remaining_time(progress.total_bytes.saturating_sub(progress.copied_bytes))
+        {
+            Some(remaining) => format_duration(remaining.as_secs()),
+            None => String::from("-"),
         };
         let dialog_area = Rect::new(area.x, area.y, area.width, area.height);
         let layout = Layout::default()
@@ -251,62 +855,88 @@ where
                 Constraint::Length(1),
                 Constraint::Length(1),
                 Constraint::Length(1),
+                Constraint::Length(1),
             ])
             .margin(1)
             .split(area);
+        let style = Style::default().fg(Color::White);
 
         let block = Block::default()
             .border_type(tui::widgets::BorderType::Rounded)
             .borders(Borders::ALL);
 
         let current_file_label = Paragraph::new(Span::styled(
-            format!("Current: {}", file_name),
-            Style::default().fg(Color::White),
-        ));
-        let dest_filename = self.destination.display().to_string();
-        let dest_label = Paragraph::new(Span::styled(
-            format!("To: {}", dest_filename),
-            Style::default().fg(Color::White),
+            format!("Current: {}", progress.file_name),
+            style,
         ));
+        let dest_filename = self.target.display().to_string();
+        let dest_label = Paragraph::new(Span::styled(format!("To: {}", dest_filename), style));
 
-        let progress_total = Gauge::default()
-            .percent(total_percent as u16)
-            .gauge_style(Style::default().fg(Color::LightBlue));
         let progress_partial = Gauge::default()
+            .label(format!("File {}%", partial_percent))
             .percent(partial_percent as u16)
             .gauge_style(Style::default().fg(Color::LightBlue));
-        let label_remaining_size = Paragraph::new(Span::styled(
+        let progress_total = Gauge::default()
+            .label(format!("Total {}%", total_percent))
+            .percent(total_percent as u16)
+            .gauge_style(Style::default().fg(Color::LightBlue));
+
+        let label_files = Paragraph::new(Span::styled(
             format!(
-                "{}/{}",
-                SizeFormatter::new(copied_bytes, DECIMAL),
-                SizeFormatter::new(total_bytes, DECIMAL)
+                "Files: {} of {}",
+                progress.copied_files, progress.total_files
             ),
-            Style::default().fg(Color::White),
+            style,
         ))
         .alignment(Alignment::Left);
+        let label_filesizes = Paragraph::new(Span::styled(
+            format!(
+                "{} of {}",
+                SizeFormatter::new(progress.copied_bytes, DECIMAL),
+                SizeFormatter::new(progress.total_bytes, DECIMAL)
+            ),
+            style,
+        ))
+        .alignment(Alignment::Right);
 
-        let secs = self.start_time.elapsed().as_secs() % 60;
-        let mins = (self.start_time.elapsed().as_secs() / 60) % 60;
-        let hours = (self.start_time.elapsed().as_secs() / 60) / 60;
         let label_total_time = Paragraph::new(Span::styled(
-            format!("{}h:{}m:{}s", hours, mins, secs),
-            Style::default().fg(Color::White),
+            format!(
+                "Elapsed: {}",
+                format_duration(self.start_time.elapsed().as_secs())
+            ),
+            style,
         ))
-        .alignment(Alignment::Center);
-
-        let label_filesizes = Paragraph::new(Span::styled(
+        .alignment(Alignment::Left);
+        let label_rate = Paragraph::new(Span::styled(
             format!(
-                "{}/{}",
-                SizeFormatter::new(copied_bytes, DECIMAL),
-                SizeFormatter::new(total_bytes, DECIMAL)
+                "{}/s",
+                SizeFormatter::new(self.rate.bytes_per_sec(), DECIMAL)
             ),
-            Style::default().fg(Color::White),
+            style,
+        ))
+        .alignment(Alignment::Center);
+        let label_remaining_time = Paragraph::new(Span::styled(
+            format!("Remaining: {}", remaining_time),
+            style,
         ))
         .alignment(Alignment::Right);
 
-        let pause_button = Span::styled("[ ] Pause ", Style::default().fg(Color::White));
-        let cancel_button = Span::styled("[ ] Cancel ", Style::default().fg(Color::White));
-        let background_button = Span::styled("[ ] Background", Style::default().fg(Color::White));
+        let button = |button: ProgressButtons, title: &str| {
+            let check_mark = match self.progress_button == button {
+                true => "[X]",
+                false => "[ ]",
+            };
+            Span::styled(
+                format!("{} {} ", check_mark, title),
+                Style::default().fg(Color::White),
+            )
+        };
+        let pause_button = match self.control.is_paused() {
+            true => button(ProgressButtons::Pause, "Resume"),
+            false => button(ProgressButtons::Pause, "Pause"),
+        };
+        let cancel_button = button(ProgressButtons::Cancel, "Cancel");
+        let background_button = button(ProgressButtons::Background, "Background");
         let buttons = Paragraph::new(Text::from(Spans::from(vec![
             pause_button,
             cancel_button,
@@ -317,15 +947,341 @@ where
         frame.render_widget(block, dialog_area);
         frame.render_widget(current_file_label, layout[0]);
         frame.render_widget(dest_label, layout[1]);
-        frame.render_widget(progress_total, layout[2]);
-        frame.render_widget(progress_partial, layout[3]);
-        frame.render_widget(label_remaining_size, layout[4]);
-        frame.render_widget(label_total_time, layout[4]);
+        frame.render_widget(progress_partial, layout[2]);
+        frame.render_widget(progress_total, layout[3]);
+        frame.render_widget(label_files, layout[4]);
         frame.render_widget(label_filesizes, layout[4]);
-        frame.render_widget(buttons, layout[5]);
+        frame.render_widget(label_total_time, layout[5]);
+        frame.render_widget(label_rate, layout[5]);
+        frame.render_widget(label_remaining_time, layout[5]);
+        frame.render_widget(buttons, layout[6]);
     }
 
     pub(crate) fn should_quit(&self) -> bool {
         self.should_quit
     }
+
+    /// Returns the operations to record in the `Journal`: the directories created for the target,
+    /// even if the transfer failed, then the transfer if it finished in the dialog without failures.
+    /// Transfers onto existing files are not recorded, they cannot be undone.
+    pub(crate) fn completed_operations(&self) -> Vec<Operation> {
+        let mut operations = Vec::new();
+        if !self.created_dirs.is_empty() {
+            operations.push(Operation::MkDir {
+                paths: self.created_dirs.clone(),
+            });
+        }
+        let completed = matches!(self.status, TransferDialogStatus::TransferFinished)
+            && self.failures.is_empty()
+            && !self.aborted
+            && !self.target_existed;
+        if completed {
+            operations.extend(self.strategy.journal_operation(
+                &self.source,
+                &self.target,
+                self.created.clone(),
+            ));
+        }
+        operations
+    }
+}
+
+/// Starts transferring the `source` file or directory to the `target` path by the `strategy`.
+fn transfer<T: TransferStrategy>(
+    strategy: &mut T,
+    source: &Path,
+    target: &Path,
+    options: TransferOptions,
+    channel: TransferChannel,
+) {
+    match strategy.source_is_dir(source) {
+        Some(true) => strategy.transfer_dir(source, target, options, channel),
+        Some(false) => strategy.transfer_file(source, target, options, channel),
+        None => channel.send(TransferProgress::Skipped(TransferError {
+            path: PathBuf::from(source),
+            message: String::from("The source does not exist"),
+        })),
+    }
+}
+
+/// Creates the missing parent directories of `target` on the `vfs`.
+/// Returns the created directories, the parents first, even if it failed on the way.
+fn create_parent_dirs(vfs: &dyn Vfs, target: &Path) -> (Vec<PathBuf>, Result<(), String>) {
+    let parent = match target.parent() {
+        Some(parent) => parent,
+        None => return (Vec::new(), Ok(())),
+    };
+    let mut missing: Vec<PathBuf> = parent
+        .ancestors()
+        .take_while(|ancestor| !vfs.exists(ancestor))
+        .map(PathBuf::from)
+        .collect();
+    missing.reverse();
+
+    let result = vfs
+        .mkdir_all(parent)
+        .map_err(|error| format!("{}: {}", parent.display(), error));
+    let created = missing.into_iter().filter(|dir| vfs.is_dir(dir)).collect();
+    (created, result)
+}
+
+/// Formats a duration of `secs` seconds as hours, minutes and seconds.
+fn format_duration(secs: u64) -> String {
+    format!("{}h:{}m:{}s", secs / 3600, (secs / 60) % 60, secs % 60)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{transfer, TransferDialog};
+    use crate::core::{
+        journal::{CreatedFiles, Operation},
+        test_dir::TestDir,
+        vfs::{MemoryFs, Vfs},
+    };
+    use crate::ui::{
+        CopyStrategy, MoveStrategy, TransferChannel, TransferOptions, TransferProgress,
+        TransferStrategy,
+    };
+    use std::{
+        fs,
+        os::unix::fs::symlink,
+        path::{Path, PathBuf},
+        sync::{mpsc, Arc},
+    };
+    use termion::event::Key;
+    use tui_input::Input;
+
+    fn setup(name: &str) -> TestDir {
+        let dir = TestDir::new(name);
+        fs::create_dir_all(dir.join("left")).unwrap();
+        fs::create_dir_all(dir.join("right")).unwrap();
+        fs::write(dir.join("left/a.txt"), "a").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_resolve_target() {
+        let dir = setup("resolve");
+        let mut dialog = TransferDialog::new(
+            dir.join("left/a.txt"),
+            dir.join("right"),
+            CopyStrategy::default(),
+            String::new(),
+        );
+        assert_eq!(dialog.resolve_target(), Ok(dir.join("right/a.txt")));
+
+        // a relative name is resolved against the source's directory
+        dialog.destination = Input::new(String::from("b.txt"));
+        assert_eq!(dialog.resolve_target(), Ok(dir.join("left/b.txt")));
+
+        dialog.destination = Input::new(String::from("a.txt"));
+        assert!(dialog.resolve_target().is_err());
+        dialog.destination = Input::new(String::from("../left/./a.txt"));
+        assert!(dialog.resolve_target().is_err());
+    }
+
+    #[test]
+    fn test_missing_directories_are_created_if_asked() {
+        let dir = setup("create-dirs");
+        let mut dialog = TransferDialog::new(
+            dir.join("left/a.txt"),
+            dir.join("right"),
+            CopyStrategy::default(),
+            String::new(),
+        );
+        dialog.destination = Input::new(format!("{}/new/sub/", dir.display()));
+        assert!(dialog.resolve_target().is_err());
+
+        dialog.handle_key(Key::Down);
+        dialog.handle_key(Key::Down);
+        dialog.handle_key(Key::Char(' '));
+        assert_eq!(dialog.resolve_target(), Ok(dir.join("new/sub/a.txt")));
+        // nothing is created until the transfer starts
+        assert!(!dir.join("new").exists());
+
+        dialog.handle_key(Key::Down);
+        dialog.handle_key(Key::Char('\n'));
+        while dialog.is_transfering() {
+            dialog.tick();
+        }
+        assert_eq!(fs::read_to_string(dir.join("new/sub/a.txt")).unwrap(), "a");
+        let mut created = CreatedFiles::default();
+        created.push(&dir.join("new/sub/a.txt"));
+        assert_eq!(
+            dialog.completed_operations(),
+            vec![
+                Operation::MkDir {
+                    paths: vec![dir.join("new"), dir.join("new/sub")]
+                },
+                Operation::Copy {
+                    to: dir.join("new/sub/a.txt"),
+                    created
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_copied_tree_reports_created_files() {
+        let dir = setup("created");
+        fs::create_dir_all(dir.join("left/sub/deeper")).unwrap();
+        fs::write(dir.join("left/sub/deeper/b.txt"), "b").unwrap();
+        let mut dialog = TransferDialog::new(
+            dir.join("left/sub"),
+            dir.join("right"),
+            CopyStrategy::default(),
+            String::new(),
+        );
+        dialog.start_transfer();
+        while dialog.is_transfering() {
+            dialog.tick();
+        }
+
+        // the transfer lists the files it created, the parents first
+        let mut created = CreatedFiles::default();
+        created.push(&dir.join("right/sub"));
+        created.push(&dir.join("right/sub/deeper"));
+        created.push(&dir.join("right/sub/deeper/b.txt"));
+        assert_eq!(
+            dialog.completed_operations(),
+            vec![Operation::Copy {
+                to: dir.join("right/sub"),
+                created
+            }]
+        );
+    }
+
+    #[test]
+    fn test_copies_follow_symlinks_by_default() {
+        let dir = setup("follow");
+        let copy_dialog = TransferDialog::new(
+            dir.join("left/a.txt"),
+            dir.join("right"),
+            CopyStrategy::default(),
+            String::new(),
+        );
+        assert!(copy_dialog.options.follow_symlinks);
+        let move_dialog = TransferDialog::new(
+            dir.join("left/a.txt"),
+            dir.join("right"),
+            MoveStrategy::default(),
+            String::new(),
+        );
+        assert!(!move_dialog.options.follow_symlinks);
+    }
+
+    #[test]
+    fn test_untransferable_sources_are_reported() {
+        let dir = setup("sources");
+        symlink("missing", dir.join("left/broken")).unwrap();
+        // a broken link is transferred as a file, which fails if it is followed
+        assert_eq!(
+            CopyStrategy::default().source_is_dir(&dir.join("left/broken")),
+            Some(false)
+        );
+        assert_eq!(
+            CopyStrategy::default().source_is_dir(&dir.join("left")),
+            Some(true)
+        );
+
+        let (progress, rx) = mpsc::channel();
+        let (_answers, answer_rx) = mpsc::channel();
+        let channel = TransferChannel::new(progress, answer_rx, None, Arc::default());
+        transfer(
+            &mut CopyStrategy::default(),
+            &dir.join("left/missing"),
+            &dir.join("right/missing"),
+            TransferOptions::default(),
+            channel,
+        );
+        match rx.recv() {
+            Ok(TransferProgress::Skipped(error)) => {
+                assert_eq!(error.path, dir.join("left/missing"))
+            }
+            _ => panic!("the missing source should be reported"),
+        }
+    }
+
+    #[test]
+    fn test_directory_cannot_be_transferred_into_itself() {
+        let dir = setup("into-itself");
+        fs::create_dir_all(dir.join("left/sub")).unwrap();
+        symlink(dir.join("left/sub"), dir.join("right/link")).unwrap();
+        let mut dialog = TransferDialog::new(
+            dir.join("left"),
+            dir.join("right"),
+            CopyStrategy::default(),
+            String::new(),
+        );
+        assert_eq!(dialog.resolve_target(), Ok(dir.join("right/left")));
+
+        for destination in ["left/sub/copy", "right/../left/sub/", "right/link/"] {
+            dialog.destination = Input::new(String::from(destination));
+            assert_eq!(
+                dialog.resolve_target(),
+                Err(String::from("Cannot transfer a directory into itself")),
+                "{}",
+                destination
+            );
+        }
+    }
+
+    #[test]
+    fn test_transfers_go_through_the_vfs() {
+        let vfs = MemoryFs::new();
+        vfs.write("/left/sub/a.txt", "a");
+        vfs.write("/left/sub/deeper/b.txt", "b");
+        vfs.mkdir_all(Path::new("/right")).unwrap();
+
+        let mut dialog = TransferDialog::new(
+            PathBuf::from("/left/sub"),
+            PathBuf::from("/right"),
+            CopyStrategy::new(Arc::new(vfs.clone())),
+            String::new(),
+        );
+        dialog.destination = Input::new(String::from("/right/new/"));
+        assert!(dialog.resolve_target().is_err());
+        dialog.options.create_dirs = true;
+        dialog.start_transfer();
+        while dialog.is_transfering() {
+            dialog.tick();
+        }
+        assert_eq!(vfs.read("/right/new/sub/a.txt"), "a");
+        assert_eq!(vfs.read("/right/new/sub/deeper/b.txt"), "b");
+        let mut created = CreatedFiles::default();
+        for path in [
+            "/right/new/sub",
+            "/right/new/sub/a.txt",
+            "/right/new/sub/deeper",
+            "/right/new/sub/deeper/b.txt",
+        ] {
+            created.push(Path::new(path));
+        }
+        assert_eq!(
+            dialog.completed_operations(),
+            vec![
+                Operation::MkDir {
+                    paths: vec![PathBuf::from("/right/new")]
+                },
+                Operation::Copy {
+                    to: PathBuf::from("/right/new/sub"),
+                    created
+                }
+            ]
+        );
+
+        let mut dialog = TransferDialog::new(
+            PathBuf::from("/right/new/sub"),
+            PathBuf::from("/left"),
+            MoveStrategy::new(Arc::new(vfs.clone())),
+            String::new(),
+        );
+        dialog.destination = Input::new(String::from("/left/moved"));
+        dialog.start_transfer();
+        while dialog.is_transfering() {
+            dialog.tick();
+        }
+        assert!(!vfs.exists(Path::new("/right/new/sub")));
+        assert_eq!(vfs.read("/left/moved/deeper/b.txt"), "b");
+    }
 }
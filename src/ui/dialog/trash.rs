@@ -0,0 +1,130 @@
+use crate::core::trash::{Trash, TrashEntry};
+use std::io::Stdout;
+use termion::{event::Key, raw::RawTerminal};
+use tui::{
+    backend::TermionBackend,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState},
+    Frame,
+};
+
+/// Lists the files in the trash directories, the most recently deleted first.
+///
+/// ## Key controls
+/// * Up and Down: select a file
+/// * r: restores the selected file to its original path
+/// * Delete: deletes the selected file from the trash permanently
+pub struct TrashDialog {
+    entries: Vec<TrashEntry>,
+    error: Option<String>,
+    selected: usize,
+}
+
+impl TrashDialog {
+    pub fn new() -> Self {
+        let mut entries: Vec<TrashEntry> = Trash::all()
+            .iter()
+            .flat_map(|trash| trash.entries())
+            .collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.deletion_date));
+        TrashDialog {
+            entries,
+            error: None,
+            selected: 0,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: Key) {
+        match key {
+            Key::Up => self.selected = self.selected.saturating_sub(1),
+            Key::Down if self.selected + 1 < self.entries.len() => self.selected += 1,
+            Key::Char('r') => self.apply(TrashEntry::restore),
+            Key::Delete => self.apply(TrashEntry::purge),
+            _ => {}
+        }
+    }
+
+    /// Restores or purges the selected entry, which is removed from the list if it succeeded.
+    fn apply(&mut self, operation: fn(&TrashEntry) -> std::io::Result<()>) {
+        let entry = match self.entries.get(self.selected) {
+            Some(entry) => entry,
+            None => return,
+        };
+        match operation(entry) {
+            Ok(()) => {
+                self.entries.remove(self.selected);
+                self.selected = self.selected.min(self.entries.len().saturating_sub(1));
+                self.error = None;
+            }
+            Err(error) => {
+                self.error = Some(format!("{}: {}", entry.name.to_string_lossy(), error));
+            }
+        }
+    }
+
+    pub fn render(&self, frame: &mut Frame<TermionBackend<RawTerminal<Stdout>>>, area: Rect) {
+        let layout = Layout::default()
+            .constraints([
+                Constraint::Min(1),
+                Constraint::Length(1),
+                Constraint::Length(1),
+            ])
+            .margin(1)
+            .split(area);
+        let style = Style::default().fg(Color::White);
+        let key_style = Style::default().fg(Color::LightYellow);
+        let bold_style = style.add_modifier(Modifier::BOLD);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title("Trash")
+            .title_alignment(tui::layout::Alignment::Center);
+
+        let header = Row::new(vec![
+            Cell::from(Span::styled("Name", bold_style)),
+            Cell::from(Span::styled("Deleted", bold_style)),
+            Cell::from(Span::styled("Original path", bold_style)),
+        ]);
+        let rows = self.entries.iter().map(|entry| {
+            let deletion_date = entry
+                .deletion_date
+                .map(|date| date.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_default();
+            Row::new(vec![
+                Cell::from(entry.name.to_string_lossy().to_string()),
+                Cell::from(deletion_date),
+                Cell::from(entry.original_path.display().to_string()),
+            ])
+            .style(style)
+        });
+        let table = Table::new(rows)
+            .header(header)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .widths(&[
+                Constraint::Percentage(30),
+                Constraint::Length(17),
+                Constraint::Percentage(70),
+            ]);
+        let mut state = TableState::default();
+        if !self.entries.is_empty() {
+            state.select(Some(self.selected));
+        }
+
+        let error = Paragraph::new(Span::styled(
+            self.error.clone().unwrap_or_default(),
+            Style::default().fg(Color::LightRed),
+        ));
+        let hints = Paragraph::new(Spans::from(vec![
+            Span::styled("Restore: ", style),
+            Span::styled("r", key_style),
+            Span::styled("  Delete permanently: ", style),
+            Span::styled("Delete", key_style),
+        ]));
+
+        frame.render_widget(block, area);
+        frame.render_stateful_widget(table, layout[0], &mut state);
+        frame.render_widget(error, layout[1]);
+        frame.render_widget(hints, layout[2]);
+    }
+}
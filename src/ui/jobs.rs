@@ -0,0 +1,367 @@
+use super::{TransferChannel, TransferError, TransferProgress};
+use crate::core::{
+    calculate_progress_percentage, conflict::ConflictResolution, copy::TransferControl,
+    journal::Operation, trash::TrashEntry,
+};
+use std::sync::{
+    mpsc::{self, Receiver, TryRecvError},
+    Arc,
+};
+
+/// Starts the work of a queued job on its own thread, reporting through the channel.
+pub type JobStart = Box<dyn FnOnce(TransferChannel)>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Finished,
+    /// The job is over, but some files could not be transferred.
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn title(&self) -> &'static str {
+        match self {
+            JobStatus::Queued => "Queued",
+            JobStatus::Running => "Running",
+            JobStatus::Finished => "Finished",
+            JobStatus::Failed => "Failed",
+            JobStatus::Cancelled => "Cancelled",
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        matches!(self, JobStatus::Queued | JobStatus::Running)
+    }
+}
+
+/// A file operation running in the background, while the user keeps browsing.
+///
+/// Nobody answers the questions of a background job: the conflicting files are resolved
+/// by the configured resolution or skipped, and the files failed to transfer are skipped
+/// and collected in `Job::failures`.
+pub struct Job {
+    conflict_resolution: Option<ConflictResolution>,
+    control: Arc<TransferControl>,
+    failures: Vec<TransferError>,
+    progress: TransferProgress,
+    rx: Option<Receiver<TransferProgress>>,
+    start: Option<JobStart>,
+    status: JobStatus,
+    title: String,
+    /// The files the job moved to the trash, until they are recorded in the `Journal`.
+    trashed: Vec<TrashEntry>,
+}
+
+impl Job {
+    /// Creates a job waiting in the queue to be started by the `JobManager`.
+    pub fn queued(
+        title: String,
+        conflict_resolution: Option<ConflictResolution>,
+        start: JobStart,
+    ) -> Self {
+        Job {
+            conflict_resolution,
+            control: Arc::default(),
+            failures: Vec::new(),
+            progress: TransferProgress::None,
+            rx: None,
+            start: Some(start),
+            status: JobStatus::Queued,
+            title,
+            trashed: Vec::new(),
+        }
+    }
+
+    /// Takes over a transfer already started by a dialog.
+    pub fn running(
+        title: String,
+        rx: Receiver<TransferProgress>,
+        control: Arc<TransferControl>,
+        failures: Vec<TransferError>,
+    ) -> Self {
+        control.set_unattended();
+        Job {
+            conflict_resolution: None,
+            control,
+            failures,
+            progress: TransferProgress::None,
+            rx: Some(rx),
+            start: None,
+            status: JobStatus::Running,
+            title,
+            trashed: Vec::new(),
+        }
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn status(&self) -> JobStatus {
+        self.status
+    }
+
+    pub fn failures(&self) -> &[TransferError] {
+        &self.failures
+    }
+
+    /// Returns the progress of the whole job in percent.
+    pub fn percentage(&self) -> u64 {
+        match (&self.status, &self.progress) {
+            (JobStatus::Finished | JobStatus::Failed, _) => 100,
+            // a deletion counts the files only
+            (_, TransferProgress::Progress(progress)) if progress.total_bytes == 0 => {
+                calculate_progress_percentage(progress.copied_files, progress.total_files)
+            }
+            (_, TransferProgress::Progress(progress)) => {
+                calculate_progress_percentage(progress.copied_bytes, progress.total_bytes)
+            }
+            _ => 0,
+        }
+    }
+
+    /// Cancels the job: a queued job is never started,
+    /// a running one stops after the file being transferred.
+    pub fn cancel(&mut self) {
+        match self.status {
+            JobStatus::Queued => {
+                self.start = None;
+                self.status = JobStatus::Cancelled;
+            }
+            JobStatus::Running => self.control.cancel(),
+            _ => {}
+        }
+    }
+
+    fn start(&mut self) {
+        if let Some(start) = self.start.take() {
+            let (tx, rx) = mpsc::channel();
+            // nobody answers the questions of a background job
+            let (_answers_tx, answers_rx) = mpsc::channel();
+            self.control.set_unattended();
+            self.rx = Some(rx);
+            self.status = JobStatus::Running;
+            start(TransferChannel::new(
+                tx,
+                answers_rx,
+                self.conflict_resolution,
+                Arc::clone(&self.control),
+            ));
+        }
+    }
+
+    /// Processes the progress reported by the running job.
+    /// Returns true if the job is over.
+    fn tick(&mut self) -> bool {
+        let rx = match &self.rx {
+            Some(rx) => rx,
+            None => return false,
+        };
+        loop {
+            match rx.try_recv() {
+                Ok(TransferProgress::Error(error)) | Ok(TransferProgress::Skipped(error)) => {
+                    self.failures.push(error)
+                }
+                Ok(TransferProgress::Trashed(entry)) => self.trashed.push(entry),
+                Ok(TransferProgress::Conflict(_)) | Ok(TransferProgress::Completed(_)) => {}
+                Ok(progress) => self.progress = progress,
+                Err(TryRecvError::Empty) => return false,
+                Err(TryRecvError::Disconnected) => break,
+            }
+        }
+
+        self.rx = None;
+        self.status = if self.control.is_cancelled() {
+            JobStatus::Cancelled
+        } else if self.failures.is_empty() {
+            JobStatus::Finished
+        } else {
+            JobStatus::Failed
+        };
+        true
+    }
+}
+
+/// Runs the background jobs one after the other in the order they were added,
+/// or all of them at once if `JobManager::parallel` is set.
+#[derive(Default)]
+pub struct JobManager {
+    jobs: Vec<Job>,
+    parallel: bool,
+}
+
+impl JobManager {
+    pub fn push(&mut self, job: Job) {
+        self.jobs.push(job);
+        self.start_queued();
+    }
+
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    pub fn job_mut(&mut self, index: usize) -> Option<&mut Job> {
+        self.jobs.get_mut(index)
+    }
+
+    /// Returns the number of the queued and running jobs.
+    pub fn active_count(&self) -> usize {
+        self.jobs
+            .iter()
+            .filter(|job| job.status.is_active())
+            .count()
+    }
+
+    pub fn is_parallel(&self) -> bool {
+        self.parallel
+    }
+
+    pub fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
+        self.start_queued();
+    }
+
+    /// Returns the operations of the jobs over since the last call to record in the `Journal`:
+    /// the files moved to the trash, like the `RmDirDialog` does.
+    /// The other jobs are not recorded.
+    pub fn take_completed_operations(&mut self) -> Vec<Operation> {
+        self.jobs
+            .iter_mut()
+            .filter(|job| !job.status.is_active() && !job.trashed.is_empty())
+            .map(|job| Operation::Trash {
+                entries: std::mem::take(&mut job.trashed),
+            })
+            .collect()
+    }
+
+    /// Removes the jobs which are over.
+    pub fn clear_finished(&mut self) {
+        self.jobs.retain(|job| job.status.is_active());
+    }
+
+    /// Processes the progress of the running jobs, and starts the next ones from the queue.
+    /// Returns true if any of the jobs is over, so the panels should be refreshed.
+    pub fn tick(&mut self) -> bool {
+        let mut finished = false;
+        for job in self.jobs.iter_mut() {
+            finished |= job.tick();
+        }
+        self.start_queued();
+        finished
+    }
+
+    fn start_queued(&mut self) {
+        let parallel = self.parallel;
+        let mut running = self.jobs.iter().any(|job| job.status == JobStatus::Running);
+        for job in self.jobs.iter_mut() {
+            if job.status == JobStatus::Queued && (parallel || !running) {
+                job.start();
+                running = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Job, JobManager, JobStatus};
+    use crate::{
+        core::{journal::Operation, test_dir::TestDir, trash::Trash},
+        ui::{TransferChannel, TransferProgress},
+    };
+    use std::{cell::RefCell, fs, rc::Rc};
+
+    /// Creates a job which keeps its channel open in `channels` until it is removed from there.
+    fn job(channels: &Rc<RefCell<Vec<TransferChannel>>>) -> Job {
+        let channels = Rc::clone(channels);
+        Job::queued(
+            String::from("job"),
+            None,
+            Box::new(move |channel| channels.borrow_mut().push(channel)),
+        )
+    }
+
+    fn statuses(manager: &JobManager) -> Vec<JobStatus> {
+        manager.jobs().iter().map(|job| job.status()).collect()
+    }
+
+    #[test]
+    fn test_jobs_run_in_order() {
+        let channels = Rc::new(RefCell::new(Vec::new()));
+        let mut manager = JobManager::default();
+        manager.push(job(&channels));
+        manager.push(job(&channels));
+        manager.tick();
+        assert_eq!(statuses(&manager), [JobStatus::Running, JobStatus::Queued]);
+        assert_eq!(manager.active_count(), 2);
+
+        channels.borrow_mut().clear();
+        assert!(manager.tick());
+        assert_eq!(
+            statuses(&manager),
+            [JobStatus::Finished, JobStatus::Running]
+        );
+
+        manager.job_mut(1).unwrap().cancel();
+        channels.borrow_mut().clear();
+        manager.tick();
+        assert_eq!(
+            statuses(&manager),
+            [JobStatus::Finished, JobStatus::Cancelled]
+        );
+        manager.clear_finished();
+        assert!(manager.jobs().is_empty());
+    }
+
+    #[test]
+    fn test_parallel_jobs() {
+        let channels = Rc::new(RefCell::new(Vec::new()));
+        let mut manager = JobManager::default();
+        manager.set_parallel(true);
+        manager.push(job(&channels));
+        manager.push(job(&channels));
+        manager.push(job(&channels));
+        assert_eq!(channels.borrow().len(), 3);
+
+        // the failures reported by a job are collected
+        channels.borrow_mut()[0].report_error(
+            std::path::Path::new("/missing"),
+            &std::io::Error::from(std::io::ErrorKind::NotFound),
+        );
+        let dropped = channels.borrow_mut().remove(0);
+        drop(dropped);
+        manager.tick();
+        assert_eq!(manager.jobs()[0].status(), JobStatus::Failed);
+        assert_eq!(manager.jobs()[0].failures().len(), 1);
+        assert!(matches!(manager.jobs()[0].progress, TransferProgress::None));
+    }
+
+    #[test]
+    fn test_trashed_files_are_recorded() {
+        let dir = TestDir::new("jobs-trash");
+        let trash = Trash::new(dir.join(".Trash"), Some(dir.to_path_buf()));
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        let entry = trash.put(&dir.join("a.txt")).unwrap();
+
+        let channels = Rc::new(RefCell::new(Vec::new()));
+        let mut manager = JobManager::default();
+        manager.push(job(&channels));
+        channels.borrow()[0].send(TransferProgress::Trashed(entry.clone()));
+        // nothing is recorded while the job runs
+        manager.tick();
+        assert!(manager.take_completed_operations().is_empty());
+
+        channels.borrow_mut().clear();
+        assert!(manager.tick());
+        assert_eq!(
+            manager.take_completed_operations(),
+            [Operation::Trash {
+                entries: vec![entry]
+            }]
+        );
+        assert!(manager.take_completed_operations().is_empty());
+    }
+}
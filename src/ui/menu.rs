@@ -5,7 +5,7 @@ use tui::{
     layout::Rect,
     style::Style,
     text::Span,
-    widgets::{Block, StatefulWidget, Widget, Clear},
+    widgets::{Block, Clear, StatefulWidget, Widget},
 };
 
 struct MenuItem {
@@ -196,6 +196,14 @@ impl MenuState {
         self.selected_item_idx
     }
 
+    /// Returns the index of the highlighted item in the currently selected submenu group.
+    pub(crate) fn selected_submenu_item(&self) -> usize {
+        self.items
+            .get(self.selected_item_idx)
+            .map(|submenu| submenu.highlighted_item_idx)
+            .unwrap_or_default()
+    }
+
     /// Creates a pre-made Menu instance.
     pub(crate) fn new_premade() -> Self {
         MenuState::new(vec![
@@ -212,6 +220,43 @@ impl MenuState {
                     },
                 ],
             ),
+            SubMenu::new(
+                " File ",
+                vec![
+                    MenuItem {
+                        title: "Attributes".into(),
+                        highlighted: false,
+                    },
+                    MenuItem {
+                        title: "Timestamps".into(),
+                        highlighted: false,
+                    },
+                    MenuItem {
+                        title: "Multi-rename".into(),
+                        highlighted: false,
+                    },
+                    MenuItem {
+                        title: "Jobs".into(),
+                        highlighted: false,
+                    },
+                    MenuItem {
+                        title: "Trash".into(),
+                        highlighted: false,
+                    },
+                    MenuItem {
+                        title: "History".into(),
+                        highlighted: false,
+                    },
+                    MenuItem {
+                        title: "Symbolic link".into(),
+                        highlighted: false,
+                    },
+                    MenuItem {
+                        title: "Hard link".into(),
+                        highlighted: false,
+                    },
+                ],
+            ),
             SubMenu::new(
                 " Options ",
                 vec![MenuItem {
@@ -244,7 +289,7 @@ impl<'block> StatefulWidget for Menu<'block> {
         let mut x = area.left();
         let mut remaining_width = area.right().saturating_sub(x);
 
-        for (_idx, submenu) in state.items.iter().enumerate() {
+        for submenu in state.items.iter() {
             let is_selected = submenu.selected;
             let has_children = !submenu.items.is_empty();
 
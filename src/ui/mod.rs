@@ -2,6 +2,7 @@ use tui::layout::{Constraint, Direction, Layout, Rect};
 
 mod bottom_menu;
 mod dialog;
+mod jobs;
 mod menu;
 mod table;
 mod user_interface;
@@ -9,10 +10,10 @@ mod widgets;
 
 pub use self::bottom_menu::*;
 pub use self::dialog::*;
+pub use self::jobs::*;
 pub use self::menu::*;
 pub use self::table::*;
 pub use self::user_interface::UserInterface;
-pub use self::widgets::*;
 
 /// Helper function to create a centered rect with a fixed height
 /// and using up certain percentage of the available of width of `r`.
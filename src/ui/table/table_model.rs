@@ -1,37 +1,55 @@
 use super::{TableSortDirection, TableSortPredicate, TableSorter};
 use crate::core::{
+    archive::ArchiveIndex,
     config::{Configuration, TableConfiguration},
-    list_dir::{list_dir, DirContent, FilterOptions},
+    list_dir::{DirContent, FilterOptions},
+    vfs::Vfs,
 };
 use std::{
+    collections::HashSet,
+    ffi::{OsStr, OsString},
     io::Error,
     path::{Path, PathBuf},
+    sync::Arc,
 };
 use tui::widgets::TableState;
 
 pub(crate) struct TableViewModel {
+    /// The archive browsed in the panel, `self.cwd` is a path under the archive file then.
+    archive: Option<Arc<ArchiveIndex>>,
     cwd: PathBuf,
     files: Vec<DirContent>,
     filter_options: FilterOptions,
     last_error: Option<Error>,
+    /// Names of the files marked by the user in `self.cwd`.
+    marked: HashSet<OsString>,
     state: TableState,
     sorter: TableSorter,
+    /// The file system browsed in the panel, outside of `self.archive`.
+    vfs: Arc<dyn Vfs>,
 }
 
 impl TableViewModel {
-    pub(crate) fn new(table_config: &TableConfiguration, config: &Configuration) -> Self {
+    pub(crate) fn new(
+        table_config: &TableConfiguration,
+        config: &Configuration,
+        vfs: Arc<dyn Vfs>,
+    ) -> Self {
         TableViewModel {
+            archive: None,
             cwd: table_config.path().clone(),
             files: Vec::new(),
             filter_options: FilterOptions {
                 show_hidden_files: config.show_hidden_files(),
             },
             last_error: None,
+            marked: HashSet::new(),
             state: TableState::default(),
             sorter: TableSorter::new(
                 TableSortDirection::from(table_config.sort_direction()),
                 TableSortPredicate::from(table_config.sort_predicate()),
             ),
+            vfs,
         }
     }
 
@@ -54,6 +72,7 @@ impl TableViewModel {
                 // the cwd is not the root dir
                 if let Some(parent) = self.cwd.parent() {
                     self.set_cwd(parent.to_path_buf());
+                    self.marked.clear();
                     return Ok(());
                 }
                 // cannot go higher than root
@@ -66,7 +85,9 @@ impl TableViewModel {
                     let dir_name = PathBuf::from(&file.name);
                     new_path.push(dir_name);
                     self.set_cwd(new_path);*/
-                    self.cwd.push::<PathBuf>(file.name.clone().into());
+                    let dir_name = file.name.clone();
+                    self.cwd.push(dir_name);
+                    self.marked.clear();
                     let _ = self.list();
                     self.select(0);
                     return Ok(());
@@ -91,7 +112,7 @@ impl TableViewModel {
     }
 
     pub(crate) fn list(&mut self) -> Result<(), Error> {
-        match list_dir(&self.cwd, &self.filter_options) {
+        match self.read_dir() {
             Ok(files) => {
                 self.files = files;
                 Ok(())
@@ -103,10 +124,74 @@ impl TableViewModel {
         }
     }
 
+    /// Lists the content of `self.cwd`, from the archive index if it is under the archive file.
+    /// The archive is closed when `self.cwd` leaves it.
+    fn read_dir(&mut self) -> Result<Vec<DirContent>, Error> {
+        if let Some(archive) = &self.archive {
+            if !archive
+                .member_path(&self.cwd)
+                .is_some_and(|dir| archive.is_dir(dir))
+            {
+                self.archive = None;
+            }
+        }
+        self.vfs().list(&self.cwd, &self.filter_options)
+    }
+
+    /// Returns the file system of `self.cwd`: the browsed archive, or the panel's own.
+    pub(crate) fn vfs(&self) -> Arc<dyn Vfs> {
+        match &self.archive {
+            Some(archive) => Arc::clone(archive) as Arc<dyn Vfs>,
+            None => Arc::clone(&self.vfs),
+        }
+    }
+
+    /// Opens the `archive` as the working directory, its members can be browsed read-only.
+    pub(crate) fn open_archive(&mut self, archive: ArchiveIndex) {
+        self.cwd = PathBuf::from(archive.path());
+        self.archive = Some(Arc::new(archive));
+        self.marked.clear();
+    }
+
+    /// Returns the archive browsed in the panel, if there is one.
+    pub(crate) fn archive(&self) -> Option<&Arc<ArchiveIndex>> {
+        self.archive.as_ref()
+    }
+
     pub(crate) fn get_file(&self, index: usize) -> Option<&DirContent> {
         self.files.get(index)
     }
 
+    /// Marks the file on `index` if it was not marked, unmarks it otherwise.
+    /// The ".." entry of the parent directory cannot be marked.
+    pub(crate) fn toggle_mark(&mut self, index: usize) {
+        if let Some(file) = self.files.get(index) {
+            if file.name == ".." {
+                return;
+            }
+
+            if !self.marked.remove(&file.name) {
+                self.marked.insert(file.name.clone());
+            }
+        }
+    }
+
+    pub(crate) fn is_marked(&self, file: &DirContent) -> bool {
+        self.marked.contains(&file.name)
+    }
+
+    /// Returns the marked files in the order they are listed.
+    pub(crate) fn marked_files(&self) -> Vec<&DirContent> {
+        self.files
+            .iter()
+            .filter(|file| self.marked.contains(&file.name))
+            .collect()
+    }
+
+    pub(crate) fn clear_marks(&mut self) {
+        self.marked.clear();
+    }
+
     /// Returns the last encountered error during listing a directory's content if it had.
     pub fn last_error(&self) -> &Option<Error> {
         &self.last_error
@@ -123,11 +208,14 @@ impl TableViewModel {
     pub(crate) fn push_parent_front(&mut self) {
         if let Some(_parent) = self.cwd.parent() {
             let parent = DirContent {
-                name: String::from(".."),
+                name: OsString::from(".."),
                 size: None,
                 is_dir: true,
                 date: String::from("Date"),
                 attrs: String::new(),
+                is_symlink: false,
+                link_target: None,
+                is_broken_link: false,
             };
             self.files.insert(0, parent);
         }
@@ -143,6 +231,13 @@ impl TableViewModel {
         }
     }
 
+    /// Selects the file named `name`, if it is listed.
+    pub(crate) fn select_by_name(&mut self, name: &OsStr) {
+        if let Some(index) = self.files.iter().position(|file| file.name == name) {
+            self.state.select(Some(index));
+        }
+    }
+
     pub(crate) fn selected(&self) -> Option<usize> {
         self.state.selected()
     }
@@ -209,7 +304,10 @@ impl TableViewModel {
     }
 
     pub(crate) fn refresh(&mut self) {
-        if let Ok(files) = list_dir(&self.cwd, &self.filter_options) {
+        if let Ok(files) = self.read_dir() {
+            // forget the marks of the files no longer exist
+            self.marked
+                .retain(|name| files.iter().any(|file| file.name == *name));
             self.files = files;
             self.sort();
             self.push_parent_front();
@@ -220,3 +318,35 @@ impl TableViewModel {
         self.state.select(None);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::TableViewModel;
+    use crate::core::{
+        config::{Configuration, TableConfiguration},
+        vfs::MemoryFs,
+    };
+    use std::{path::PathBuf, sync::Arc};
+
+    #[test]
+    fn test_browse_vfs() {
+        let vfs = MemoryFs::new();
+        vfs.write("/home/notes.txt", "notes");
+        vfs.write("/home/projects/.hidden", "");
+        let mut model = TableViewModel::new(
+            &TableConfiguration::default(),
+            &Configuration::default(),
+            Arc::new(vfs),
+        );
+
+        model.set_cwd(PathBuf::from("/home"));
+        model.refresh();
+        let names: Vec<_> = model.files().iter().map(|file| &file.name).collect();
+        assert_eq!(names, ["..", "projects", "notes.txt"]);
+
+        model.select_by_name("projects".as_ref());
+        assert!(model.cd().is_ok());
+        assert_eq!(model.pwd(), PathBuf::from("/home/projects"));
+        assert!(model.files().is_empty());
+    }
+}
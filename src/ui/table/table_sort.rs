@@ -333,6 +333,7 @@ impl SortBy for LastModifiedSorterDesc {
 mod test {
     use super::*;
     use crate::core::list_dir::DirContent;
+    use std::ffi::OsString;
 
     #[test]
     fn test_sort_direction_default() {
@@ -373,12 +374,12 @@ mod test {
         sorter.sort(&mut files);
 
         // directories first
-        assert_eq!(files[0].name, String::from("Alpha"));
-        assert_eq!(files[2].name, String::from("Omega"));
+        assert_eq!(files[0].name, OsString::from("Alpha"));
+        assert_eq!(files[2].name, OsString::from("Omega"));
 
         //then files
-        assert_eq!(files[3].name, String::from("a.out"));
-        assert_eq!(files[files.len() - 1].name, String::from("test.txt"));
+        assert_eq!(files[3].name, OsString::from("a.out"));
+        assert_eq!(files[files.len() - 1].name, OsString::from("test.txt"));
     }
 
     #[test]
@@ -389,12 +390,12 @@ mod test {
         sorter.sort(&mut files);
 
         // directories first
-        assert_eq!(files[0].name, String::from("Omega"));
-        assert_eq!(files[2].name, String::from("Alpha"));
+        assert_eq!(files[0].name, OsString::from("Omega"));
+        assert_eq!(files[2].name, OsString::from("Alpha"));
 
         //then files
-        assert_eq!(files[3].name, String::from("test.txt"));
-        assert_eq!(files[files.len() - 1].name, String::from("a.out"));
+        assert_eq!(files[3].name, OsString::from("test.txt"));
+        assert_eq!(files[files.len() - 1].name, OsString::from("a.out"));
     }
 
     #[test]
@@ -469,42 +470,58 @@ mod test {
         );
     }
 
+    #[allow(clippy::vec_init_then_push, clippy::needless_return)]
     fn setup() -> Vec<DirContent> {
         let mut files = Vec::new();
         files.push(DirContent {
-            name: String::from("Beta"),
+            name: OsString::from("Beta"),
             is_dir: true,
             size: None,
             date: String::from("2022.11.24 12:04:02"),
             attrs: String::new(),
+            is_symlink: false,
+            link_target: None,
+            is_broken_link: false,
         });
         files.push(DirContent {
-            name: String::from("Omega"),
+            name: OsString::from("Omega"),
             is_dir: true,
             size: None,
             date: String::from("2022.11.25 13:05:03"),
             attrs: String::new(),
+            is_symlink: false,
+            link_target: None,
+            is_broken_link: false,
         });
         files.push(DirContent {
-            name: String::from("Alpha"),
+            name: OsString::from("Alpha"),
             is_dir: true,
             size: None,
             date: String::from("2022.11.23 11:03:01"),
             attrs: String::new(),
+            is_symlink: false,
+            link_target: None,
+            is_broken_link: false,
         });
         files.push(DirContent {
-            name: String::from("test.txt"),
+            name: OsString::from("test.txt"),
             is_dir: false,
             size: Some(816),
             date: String::from("2022.11.26 14:06:04"),
             attrs: String::new(),
+            is_symlink: false,
+            link_target: None,
+            is_broken_link: false,
         });
         files.push(DirContent {
-            name: String::from("a.out"),
+            name: OsString::from("a.out"),
             is_dir: false,
             size: Some(8467),
             date: String::from("2022.11.27 15:07:05"),
             attrs: String::new(),
+            is_symlink: false,
+            link_target: None,
+            is_broken_link: false,
         });
         return files;
     }
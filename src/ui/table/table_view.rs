@@ -1,9 +1,16 @@
 use super::{centered_rect, table_model::TableViewModel, TableSortDirection, TableSortPredicate};
-use crate::core::config::{Configuration, TableConfiguration};
+use crate::core::{
+    archive::{ArchiveFormat, ArchiveIndex},
+    config::{Configuration, TableConfiguration},
+    list_dir::escape_file_name,
+    vfs::{LocalFs, Vfs},
+};
 use humansize::{SizeFormatter, DECIMAL};
 use std::{
-    io::Stdout,
+    ffi::OsStr,
+    io::{self, Stdout},
     path::{Path, PathBuf},
+    sync::Arc,
 };
 use termion::raw::RawTerminal;
 use tui::{
@@ -41,13 +48,14 @@ pub struct TableView {
 impl TableView {
     /// Creates a new TableView instance with the provided configuration.
     pub fn new(table_config: &TableConfiguration, config: &Configuration) -> Self {
-        let mut model = TableViewModel::new(table_config, config);
-        model.refresh();
-
-        TableView {
+        let model = TableViewModel::new(table_config, config, Arc::new(LocalFs));
+        let mut table_view = TableView {
             model,
             is_active: false,
-        }
+        };
+        table_view.apply_dir_sort_order(table_config, config);
+        table_view.model.refresh();
+        table_view
     }
 
     pub fn activate(&mut self) {
@@ -70,29 +78,47 @@ impl TableView {
         self.model.refresh()
     }
 
-    pub fn change_dir(&mut self) {
+    /// Changes into the selected directory, or opens the selected archive as a directory.
+    /// If the sort order is remembered per directory, the sort order of the new working directory
+    /// is applied, falling back to the panel's own sort order found in `table_config`.
+    /// Returns the error if the selected archive cannot be read.
+    pub fn change_dir(
+        &mut self,
+        table_config: &TableConfiguration,
+        config: &Configuration,
+    ) -> io::Result<()> {
+        if let Some(archive) = self.selected_archive() {
+            self.model.open_archive(ArchiveIndex::open(&archive)?);
+            let _ = self.model.list();
+            self.apply_dir_sort_order(table_config, config);
+            self.model.sort();
+            self.model.push_parent_front();
+            self.select_first();
+            return Ok(());
+        }
+
         // remember current dir name before switching working dir
         let current_dir = PathBuf::from(self.model.pwd());
         let current_dir = current_dir.file_name();
 
         if self.model.cd().is_ok() && self.model.list().is_ok() {
+            self.apply_dir_sort_order(table_config, config);
             self.model.sort();
             self.model.push_parent_front();
 
             let file_count = self.model.files().len();
             if file_count <= 1 {
                 self.select_first();
-                return;
+                return Ok(());
             }
 
-            // select previous dir
+            // select previous dir, or the archive left
             if let Some(current_dir_name) = current_dir {
                 if let Some(previous_dir_index) = self
                     .model
                     .files()
                     .iter()
-                    .filter(|f| f.is_dir)
-                    .position(|f| f.name.as_str().eq(current_dir_name))
+                    .position(|f| f.name == current_dir_name)
                 {
                     if previous_dir_index <= file_count {
                         self.model.select(previous_dir_index);
@@ -102,6 +128,64 @@ impl TableView {
                 }
             }
         }
+        Ok(())
+    }
+
+    /// Returns the selected file if it is an archive which can be opened as a directory.
+    /// Archives inside archives are not opened.
+    fn selected_archive(&self) -> Option<PathBuf> {
+        if self.model.archive().is_some() {
+            return None;
+        }
+        let selected = self.model.get_file(self.model.selected()?)?;
+        if selected.is_dir || ArchiveFormat::from_path(Path::new(&selected.name)).is_none() {
+            return None;
+        }
+        Some(self.pwd().join(&selected.name))
+    }
+
+    /// Returns the archive browsed in the panel, its working directory is inside it then.
+    pub fn archive(&self) -> Option<&Arc<ArchiveIndex>> {
+        self.model.archive()
+    }
+
+    /// Changes the working directory to `dir` and selects its first entry.
+    /// The sort order is applied the same way as in `change_dir()`.
+    pub fn open_dir(
+        &mut self,
+        dir: &Path,
+        table_config: &TableConfiguration,
+        config: &Configuration,
+    ) {
+        let previous_dir = PathBuf::from(self.model.pwd());
+        self.model.set_cwd(PathBuf::from(dir));
+        if self.model.list().is_err() {
+            self.model.set_cwd(previous_dir);
+            return;
+        }
+        self.model.clear_marks();
+        self.apply_dir_sort_order(table_config, config);
+        self.model.sort();
+        self.model.push_parent_front();
+        self.select_first();
+    }
+
+    /// Sets the sort order remembered for the current working directory
+    /// without re-sorting the table.
+    /// Does nothing if the sort order is not remembered per directory.
+    fn apply_dir_sort_order(&mut self, table_config: &TableConfiguration, config: &Configuration) {
+        if !config.remember_sort_per_dir() {
+            return;
+        }
+
+        let (predicate, direction) = match config.dir_sort_orders().get(self.model.pwd()) {
+            Some(order) => (order.sort_predicate(), order.sort_direction()),
+            None => (table_config.sort_predicate(), table_config.sort_direction()),
+        };
+        self.model
+            .set_sort_predicate(TableSortPredicate::from(predicate));
+        self.model
+            .set_sort_direction(TableSortDirection::from(direction));
     }
 
     pub fn deactivate(&mut self) {
@@ -124,13 +208,53 @@ impl TableView {
     pub fn get_selected_file(&self) -> Option<PathBuf> {
         if let Some(idx) = self.model.selected() {
             if let Some(file) = self.model.files().get(idx) {
-                let path = self.pwd().join(file.name.as_str());
+                let path = self.pwd().join(&file.name);
                 return Some(path);
             }
         }
         None
     }
 
+    /// Returns the paths of the marked files,
+    /// or the path of the selected file if there are no marked files.
+    pub fn get_selected_files(&self) -> Vec<PathBuf> {
+        let marked_files = self.model.marked_files();
+        if marked_files.is_empty() {
+            return self.get_selected_file().into_iter().collect();
+        }
+
+        marked_files
+            .iter()
+            .map(|file| self.pwd().join(&file.name))
+            .collect()
+    }
+
+    /// Marks the selected file if it was not marked, unmarks it otherwise,
+    /// then selects the next file.
+    pub fn toggle_mark_selected(&mut self) {
+        if let Some(selected) = self.model.selected() {
+            self.model.toggle_mark(selected);
+            if selected + 1 < self.model.files().len() {
+                self.model.select(selected + 1);
+            }
+        }
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.model.clear_marks();
+    }
+
+    /// Re-reads the content of the working directory,
+    /// e.g. after a file operation changed it.
+    pub fn refresh(&mut self) {
+        self.model.refresh();
+    }
+
+    /// Moves the cursor onto the file named `name` in the working directory.
+    pub fn select_file(&mut self, name: &OsStr) {
+        self.model.select_by_name(name);
+    }
+
     pub fn has_selection(&self) -> bool {
         self.model.selected().is_some()
     }
@@ -143,6 +267,11 @@ impl TableView {
         self.model.pwd()
     }
 
+    /// Returns the file system of the working directory.
+    pub fn vfs(&self) -> Arc<dyn Vfs> {
+        self.model.vfs()
+    }
+
     pub fn render_table(
         &mut self,
         main_layout: Rect,
@@ -184,12 +313,22 @@ impl TableView {
                         .add_modifier(Modifier::BOLD),
                     false => Style::default().bg(Color::Blue).fg(Color::White),
                 };
+                let cell_style = if self.model.is_marked(file) {
+                    cell_style.fg(Color::LightYellow)
+                } else if file.is_broken_link {
+                    cell_style.fg(Color::LightRed)
+                } else if file.is_symlink {
+                    cell_style.fg(Color::LightCyan)
+                } else {
+                    cell_style
+                };
                 let size_cell = match file.size {
+                    Some(_) if file.is_broken_link => Cell::from("<BROKEN>"),
                     Some(size) => Cell::from(format!("{}", SizeFormatter::new(size, DECIMAL))),
                     None => Cell::from("<DIR>"),
                 };
                 Row::new(vec![
-                    Cell::style(Cell::from(file.name.clone()), cell_style),
+                    Cell::style(Cell::from(file.display_name()), cell_style),
                     Cell::style(size_cell, cell_style),
                     Cell::style(Cell::from(file.date.clone()), cell_style),
                 ])
@@ -203,7 +342,7 @@ impl TableView {
                 .bg(Color::Red)
                 .add_modifier(Modifier::REVERSED),
         };
-        let cwd = String::from(self.model.pwd().to_str().unwrap());
+        let cwd = escape_file_name(self.model.pwd().as_os_str());
         let name_column_width = table_layout[0].width - 3 - (8 + 16);
         let widths = [
             Constraint::Length(name_column_width),
@@ -1,20 +1,35 @@
 use super::{
-    centered_rect, fixed_height_centered_rect, BottomMenu, BoxedDialog, CopyStrategy, HelpDialog,
-    Menu, MenuState, MkDirDialog, MoveStrategy, PanelOpionsDialog, RmDirDialog, SortingDialog,
-    TableSortDirection, TableSortPredicate, TableView, TransferDialog,
+    centered_rect, display_errors, fixed_height_centered_rect, AttributesDialog, BottomMenu,
+    BoxedDialog, CopyStrategy, ExtractStrategy, HelpDialog, HistoryDialog, JobManager, JobsDialog,
+    LinkDialog, Menu, MenuState, MkDirDialog, MoveStrategy, MultiRenameDialog, NewFileDialog,
+    PackDialog, PackStrategy, PanelOpionsDialog, RenameDialog, RmDirDialog, SortingDialog,
+    TableSortDirection, TableSortPredicate, TableView, TouchDialog, TransferDialog, TrashDialog,
 };
 use crate::app::{Application, InputMode};
-use crate::core::config::Configuration;
-use std::io::Stdout;
+use crate::core::{
+    archive::{ArchiveFormat, ArchiveIndex},
+    config::Configuration,
+    journal::Journal,
+    link::LinkKind,
+    template::{list_templates, templates_dir},
+    vfs::copy_to_temporary,
+};
+use std::ffi::OsString;
+use std::io::{self, Stdout};
 use std::path::PathBuf;
+use std::sync::Arc;
 use termion::event::Key;
 use termion::raw::RawTerminal;
 use tui::backend::TermionBackend;
+use tui::layout::{Alignment, Rect};
 use tui::layout::{Constraint, Layout};
 use tui::style::{Color, Style};
-use tui::widgets::{Block, Borders, Clear};
+use tui::text::Span;
+use tui::widgets::{Block, Borders, Clear, Paragraph};
 use tui::Frame;
 
+const READ_ONLY_ARCHIVE: &str = "The archive is read-only, copy the files out of it with F5";
+
 #[derive(Copy, Clone)]
 pub enum ActivePanel {
     Left,
@@ -34,8 +49,21 @@ enum Dialog {
     Help(HelpDialog),
     Copy(TransferDialog<CopyStrategy>),
     Move(TransferDialog<MoveStrategy>),
+    Extract(TransferDialog<ExtractStrategy>),
+    Pack(PackDialog),
+    Packing(TransferDialog<PackStrategy>),
     MkDir(MkDirDialog),
+    NewFile(NewFileDialog),
+    Link(LinkDialog),
     RmDir(RmDirDialog),
+    Rename(RenameDialog),
+    Attributes(AttributesDialog),
+    Touch(TouchDialog),
+    MultiRename(MultiRenameDialog),
+    Jobs(JobsDialog),
+    Trash(TrashDialog),
+    History(HistoryDialog),
+    Error(Vec<String>),
     Menu(Box<dyn BoxedDialog>),
 }
 
@@ -57,6 +85,8 @@ pub struct UserInterface {
     active_panel: ActivePanel,
     config: Configuration,
     dialog: Option<Dialog>,
+    jobs: JobManager,
+    journal: Journal,
     top_menu: MenuState,
     left_panel: TableView,
     right_panel: TableView,
@@ -67,9 +97,9 @@ pub struct UserInterface {
 impl UserInterface {
     pub(crate) fn new(config: Configuration) -> Self {
         let (left_panel, right_panel) = {
-            let left_table_config = config.left_table_config().clone();
-            let right_table_config = config.right_table_config().clone();
-            let mut left_panel = TableView::new(left_table_config.clone(), &config);
+            let left_table_config = config.left_table_config();
+            let right_table_config = config.right_table_config();
+            let mut left_panel = TableView::new(left_table_config, &config);
             left_panel.activate();
             (left_panel, TableView::new(right_table_config, &config))
         };
@@ -78,6 +108,8 @@ impl UserInterface {
             active_panel: ActivePanel::Left,
             config,
             dialog: None,
+            jobs: JobManager::default(),
+            journal: Journal::default(),
             top_menu: MenuState::new_premade(),
             left_panel,
             right_panel,
@@ -121,6 +153,17 @@ impl UserInterface {
                         .borders(Borders::ALL),
                 );
             frame.render_stateful_widget(top_menu, layout[0], &mut self.top_menu);
+
+            let active_jobs = self.jobs.active_count();
+            if active_jobs > 0 {
+                let indicator = Paragraph::new(Span::styled(
+                    format!(" Jobs: {} ", active_jobs),
+                    Style::default().bg(Color::Black).fg(Color::White),
+                ))
+                .alignment(Alignment::Right);
+                let area = Rect::new(layout[0].x, layout[0].y, layout[0].width, 1);
+                frame.render_widget(indicator, area);
+            }
         }
 
         {
@@ -128,30 +171,97 @@ impl UserInterface {
             if let Some(dialog) = &mut self.dialog {
                 match dialog {
                     Dialog::Help(help_dialog) => {
-                        let area = fixed_height_centered_rect(75, 14, frame_size);
+                        let area = fixed_height_centered_rect(75, 15, frame_size);
                         frame.render_widget(Clear, area);
                         help_dialog.render(frame, area);
                     }
                     Dialog::Copy(transfer_dialog) => {
-                        let area = fixed_height_centered_rect(50, 8, frame_size);
+                        let area = fixed_height_centered_rect(50, 11, frame_size);
                         frame.render_widget(Clear, area);
                         transfer_dialog.render(frame, area);
                     }
                     Dialog::Move(mv_dialog) => {
-                        let area = fixed_height_centered_rect(50, 8, frame_size);
+                        let area = fixed_height_centered_rect(50, 11, frame_size);
                         frame.render_widget(Clear, area);
                         mv_dialog.render(frame, area);
                     }
+                    Dialog::Extract(extract_dialog) => {
+                        let area = fixed_height_centered_rect(50, 11, frame_size);
+                        frame.render_widget(Clear, area);
+                        extract_dialog.render(frame, area);
+                    }
+                    Dialog::Pack(pack_dialog) => {
+                        let area = fixed_height_centered_rect(50, 9, frame_size);
+                        frame.render_widget(Clear, area);
+                        frame.render_widget(pack_dialog.widget(), area);
+                    }
+                    Dialog::Packing(packing_dialog) => {
+                        let area = fixed_height_centered_rect(50, 11, frame_size);
+                        frame.render_widget(Clear, area);
+                        packing_dialog.render(frame, area);
+                    }
                     Dialog::MkDir(mkdir_dialog) => {
-                        let area = fixed_height_centered_rect(33, 6, frame_size);
+                        let area = fixed_height_centered_rect(33, 7, frame_size);
                         frame.render_widget(Clear, area);
                         frame.render_widget(mkdir_dialog.widget(), area);
                     }
-                    Dialog::RmDir(rmdir_dialog) => {
+                    Dialog::NewFile(new_file_dialog) => {
                         let area = fixed_height_centered_rect(33, 7, frame_size);
                         frame.render_widget(Clear, area);
+                        frame.render_widget(new_file_dialog.widget(), area);
+                    }
+                    Dialog::Link(link_dialog) => {
+                        let area = fixed_height_centered_rect(50, link_dialog.height(), frame_size);
+                        frame.render_widget(Clear, area);
+                        frame.render_widget(link_dialog.widget(), area);
+                    }
+                    Dialog::RmDir(rmdir_dialog) => {
+                        let area =
+                            fixed_height_centered_rect(33, rmdir_dialog.height(), frame_size);
+                        frame.render_widget(Clear, area);
                         rmdir_dialog.render(frame, area);
                     }
+                    Dialog::Rename(rename_dialog) => {
+                        let area = fixed_height_centered_rect(50, 5, frame_size);
+                        frame.render_widget(Clear, area);
+                        rename_dialog.render(frame, area);
+                    }
+                    Dialog::Attributes(attributes_dialog) => {
+                        let area = fixed_height_centered_rect(50, 13, frame_size);
+                        frame.render_widget(Clear, area);
+                        attributes_dialog.render(frame, area);
+                    }
+                    Dialog::Touch(touch_dialog) => {
+                        let area = fixed_height_centered_rect(50, 9, frame_size);
+                        frame.render_widget(Clear, area);
+                        touch_dialog.render(frame, area);
+                    }
+                    Dialog::MultiRename(multi_rename_dialog) => {
+                        let area = centered_rect(70, 70, frame_size);
+                        frame.render_widget(Clear, area);
+                        multi_rename_dialog.render(frame, area);
+                    }
+                    Dialog::Jobs(jobs_dialog) => {
+                        let area = centered_rect(70, 50, frame_size);
+                        frame.render_widget(Clear, area);
+                        jobs_dialog.render(frame, area, &self.jobs);
+                    }
+                    Dialog::Trash(trash_dialog) => {
+                        let area = centered_rect(80, 60, frame_size);
+                        frame.render_widget(Clear, area);
+                        trash_dialog.render(frame, area);
+                    }
+                    Dialog::History(history_dialog) => {
+                        let area = centered_rect(70, 50, frame_size);
+                        frame.render_widget(Clear, area);
+                        history_dialog.render(frame, area, &self.journal);
+                    }
+                    Dialog::Error(messages) => {
+                        let height = (messages.len() as u16 + 3).min(frame_size.height);
+                        let area = fixed_height_centered_rect(50, height, frame_size);
+                        frame.render_widget(Clear, area);
+                        frame.render_widget(display_errors(messages), area);
+                    }
                     Dialog::Menu(menu_dialog) => {
                         let area = centered_rect(33, 30, frame_size);
                         frame.render_widget(Clear, area);
@@ -185,28 +295,80 @@ impl UserInterface {
                     ActivePanel::Left => self.left_panel.select_next(),
                     ActivePanel::Right => self.right_panel.select_next(),
                 },
-                Key::Char('\n') => match &self.active_panel {
-                    ActivePanel::Left => self.left_panel.change_dir(),
-                    ActivePanel::Right => self.right_panel.change_dir(),
-                },
+                Key::Insert => self.active_panel_mut().toggle_mark_selected(),
+                Key::Char('\n') => {
+                    let result = match &self.active_panel {
+                        ActivePanel::Left => self
+                            .left_panel
+                            .change_dir(self.config.left_table_config(), &self.config),
+                        ActivePanel::Right => self
+                            .right_panel
+                            .change_dir(self.config.right_table_config(), &self.config),
+                    };
+                    if let Err(error) = result {
+                        self.show_error(error.to_string(), app);
+                    }
+                }
                 // Tableview sorting by
-                Key::Ctrl('n') => self.active_panel_mut().sort_by(TableSortPredicate::Name),
-                Key::Ctrl('l') => self
-                    .active_panel_mut()
-                    .sort_by(TableSortPredicate::LastModified),
-                Key::Ctrl('s') => self.active_panel_mut().sort_by(TableSortPredicate::Size),
+                Key::Ctrl('n') => {
+                    self.active_panel_mut().sort_by(TableSortPredicate::Name);
+                    self.remember_dir_sort_order();
+                }
+                Key::Ctrl('l') => {
+                    self.active_panel_mut()
+                        .sort_by(TableSortPredicate::LastModified);
+                    self.remember_dir_sort_order();
+                }
+                Key::Ctrl('s') => {
+                    self.active_panel_mut().sort_by(TableSortPredicate::Size);
+                    self.remember_dir_sort_order();
+                }
                 // Tableview sorting order
-                Key::Ctrl('u') => self
-                    .active_panel_mut()
-                    .set_direction(TableSortDirection::Ascending),
-                Key::Ctrl('d') => self
-                    .active_panel_mut()
-                    .set_direction(TableSortDirection::Descending),
+                Key::Ctrl('u') => {
+                    self.active_panel_mut()
+                        .set_direction(TableSortDirection::Ascending);
+                    self.remember_dir_sort_order();
+                }
+                Key::Ctrl('d') => {
+                    self.active_panel_mut()
+                        .set_direction(TableSortDirection::Descending);
+                    self.remember_dir_sort_order();
+                }
+                // Undo the latest file operation
+                Key::Ctrl('z') => self.undo(app),
                 // Bottom menu
                 Key::F(1) => {
                     app.set_input_mode(InputMode::Editing);
                     self.create_help_dialog();
                 }
+                // View the selected file
+                Key::F(3) => self.view_selected_file(app),
+                // The archives are browsed read-only
+                Key::F(6)
+                | Key::F(7)
+                | Key::F(8)
+                | Key::F(16)
+                | Key::F(18)
+                | Key::F(20)
+                | Key::F(53)
+                | Key::F(57)
+                    if self.active_panel().archive().is_some() =>
+                {
+                    self.show_error(String::from(READ_ONLY_ARCHIVE), app)
+                }
+                Key::F(5) | Key::F(6) | Key::F(53) | Key::F(57)
+                    if self.inactive_panel().archive().is_some() =>
+                {
+                    self.show_error(String::from(READ_ONLY_ARCHIVE), app)
+                }
+                // Copy the members of an archive out of it
+                Key::F(5) if self.active_panel().archive().is_some() => {
+                    if let Ok(extract_dialog) = self.create_extract_dialog() {
+                        self.dialog = Some(Dialog::Extract(extract_dialog));
+                        self.focused_widget = Widgets::Dialog;
+                        app.set_input_mode(InputMode::Editing);
+                    }
+                }
                 // Copy file(s) dialog
                 Key::F(5) => {
                     if let Ok(copy_dialog) = self.create_copy_dialog() {
@@ -225,19 +387,56 @@ impl UserInterface {
                     }
                     // show error message about no selection
                 }
+                // Pack the marked files into an archive in the other panel (Alt+F5)
+                Key::F(53) => {
+                    if let Ok(pack_dialog) = self.create_pack_dialog() {
+                        self.dialog = Some(Dialog::Pack(pack_dialog));
+                        self.focused_widget = Widgets::Dialog;
+                        app.set_input_mode(InputMode::Editing);
+                    }
+                }
+                // Unpack the selected archive into the other panel (Alt+F9)
+                Key::F(57) => match self.create_unpack_dialog() {
+                    Ok(Some(unpack_dialog)) => {
+                        self.dialog = Some(Dialog::Extract(unpack_dialog));
+                        self.focused_widget = Widgets::Dialog;
+                        app.set_input_mode(InputMode::Editing);
+                    }
+                    Ok(None) => {}
+                    Err(error) => self.show_error(error.to_string(), app),
+                },
+                // Rename dialog (Shift+F6)
+                Key::F(18) => {
+                    if let Some(selected_file) = self.active_panel_mut().get_selected_file() {
+                        if selected_file.file_name().is_some() {
+                            let vfs = self.active_panel().vfs();
+                            self.dialog =
+                                Some(Dialog::Rename(RenameDialog::new(selected_file, vfs)));
+                            self.focused_widget = Widgets::Dialog;
+                            app.set_input_mode(InputMode::Editing);
+                        }
+                    }
+                }
+                // Create file dialog (Shift+F4)
+                Key::F(16) => {
+                    let templates = templates_dir()
+                        .map(|dir| list_templates(&dir))
+                        .unwrap_or_default();
+                    let parent_dir = self.active_panel_mut().pwd().to_path_buf();
+                    self.dialog = Some(Dialog::NewFile(NewFileDialog::new(parent_dir, templates)));
+                    app.set_input_mode(InputMode::Editing);
+                    self.focused_widget = Widgets::Dialog;
+                }
                 // Create directory dialog
                 Key::F(7) => {
-                    let parent_dir = match &self.active_panel {
-                        ActivePanel::Left => self.left_panel.pwd(),
-                        ActivePanel::Right => self.right_panel.pwd(),
-                    };
-                    self.dialog = Some(Dialog::MkDir(MkDirDialog::new(parent_dir)));
+                    let panel = self.active_panel();
+                    self.dialog = Some(Dialog::MkDir(MkDirDialog::new(panel.pwd(), panel.vfs())));
                     app.set_input_mode(InputMode::Editing);
                     self.focused_widget = Widgets::Dialog;
                 }
-                // Remove directory dialog
-                Key::F(8) => {
-                    if let Ok(rm_dialog) = self.create_rm_dialog() {
+                // Remove directory dialog: F8 moves to the trash, Shift+F8 deletes permanently
+                Key::F(8) | Key::F(20) => {
+                    if let Ok(rm_dialog) = self.create_rm_dialog(key == Key::F(20)) {
                         self.dialog = Some(Dialog::RmDir(rm_dialog));
                         app.set_input_mode(InputMode::Editing);
                         self.focused_widget = Widgets::Dialog;
@@ -263,6 +462,9 @@ impl UserInterface {
                 Key::Char('\n') => {
                     app.set_input_mode(InputMode::Editing);
                     self.create_menu_dialog();
+                    if self.dialog.is_none() {
+                        app.set_input_mode(InputMode::Normal);
+                    }
                 }
                 _ => (),
             },
@@ -274,22 +476,85 @@ impl UserInterface {
                         }
                         Dialog::Copy(copy_dialog) => match key {
                             Key::Char('\n') => copy_dialog.handle_key(key),
-                            Key::Esc => self.close_dialog(app),
+                            Key::Esc if !copy_dialog.is_transfering() => self.close_dialog(app),
                             _ => copy_dialog.handle_key(key),
                         },
                         Dialog::Move(mv_dialog) => match key {
                             Key::Char('\n') => mv_dialog.handle_key(key),
-                            Key::Esc => self.close_dialog(app),
+                            Key::Esc if !mv_dialog.is_transfering() => self.close_dialog(app),
                             _ => mv_dialog.handle_key(key),
                         },
+                        Dialog::Extract(extract_dialog) => match key {
+                            Key::Esc if !extract_dialog.is_transfering() => self.close_dialog(app),
+                            _ => extract_dialog.handle_key(key),
+                        },
+                        Dialog::Pack(pack_dialog) => match key {
+                            Key::Esc => self.close_dialog(app),
+                            _ => pack_dialog.handle_key(key),
+                        },
+                        Dialog::Packing(packing_dialog) => match key {
+                            Key::Esc if !packing_dialog.is_transfering() => self.close_dialog(app),
+                            _ => packing_dialog.handle_key(key),
+                        },
                         Dialog::MkDir(mkdir_dialog) => match key {
                             Key::Esc => self.close_dialog(app),
                             _ => mkdir_dialog.handle_key(key),
                         },
-                        Dialog::RmDir(rmdir_dialog) => match key {
+                        Dialog::NewFile(new_file_dialog) => match key {
+                            Key::Esc => self.close_dialog(app),
+                            _ => new_file_dialog.handle_key(key),
+                        },
+                        Dialog::Link(link_dialog) => match key {
                             Key::Esc => self.close_dialog(app),
+                            _ => link_dialog.handle_key(key),
+                        },
+                        Dialog::RmDir(rmdir_dialog) => match key {
+                            Key::Esc if !rmdir_dialog.is_deleting() => self.close_dialog(app),
                             _ => rmdir_dialog.handle_keys(key),
                         },
+                        Dialog::Rename(rename_dialog) => match key {
+                            Key::Esc => self.close_dialog(app),
+                            _ => rename_dialog.handle_key(key),
+                        },
+                        Dialog::Attributes(attributes_dialog) => match key {
+                            Key::Esc => self.close_dialog(app),
+                            _ => attributes_dialog.handle_key(key),
+                        },
+                        Dialog::Touch(touch_dialog) => match key {
+                            Key::Esc => self.close_dialog(app),
+                            _ => touch_dialog.handle_key(key),
+                        },
+                        Dialog::MultiRename(multi_rename_dialog) => match key {
+                            Key::Esc => self.close_dialog(app),
+                            _ => multi_rename_dialog.handle_key(key),
+                        },
+                        Dialog::Jobs(jobs_dialog) => match key {
+                            Key::Esc => self.close_dialog(app),
+                            _ => jobs_dialog.handle_key(key, &mut self.jobs),
+                        },
+                        Dialog::Trash(trash_dialog) => match key {
+                            Key::Esc => {
+                                // the restored files appear in the panels
+                                self.left_panel.refresh();
+                                self.right_panel.refresh();
+                                self.close_dialog(app)
+                            }
+                            _ => trash_dialog.handle_key(key),
+                        },
+                        Dialog::Error(_) => {
+                            if let Key::Esc | Key::Char('\n') = key {
+                                self.close_dialog(app)
+                            }
+                        }
+                        Dialog::History(history_dialog) => match key {
+                            Key::Esc => self.close_dialog(app),
+                            _ => {
+                                if history_dialog.handle_key(key, &mut self.journal) {
+                                    self.left_panel.refresh();
+                                    self.right_panel.refresh();
+                                }
+                            }
+                        },
                         Dialog::Menu(menu_dialog) => match key {
                             Key::Esc => self.close_dialog(app),
                             _ => menu_dialog.handle_keys(key, app),
@@ -315,6 +580,13 @@ impl UserInterface {
 
     /// Updates the ui's dialog if it has.
     pub(crate) fn tick(&mut self, app: &mut Application) {
+        if self.jobs.tick() {
+            for operation in self.jobs.take_completed_operations() {
+                self.journal.record(operation);
+            }
+            self.left_panel.refresh();
+            self.right_panel.refresh();
+        }
         if let Some(dialog) = &mut self.dialog {
             match dialog {
                 Dialog::Help(help_dialog) => {
@@ -325,25 +597,180 @@ impl UserInterface {
                 Dialog::Copy(copy_dialog) => {
                     copy_dialog.tick();
                     if copy_dialog.should_quit() {
+                        if let Some(job) = copy_dialog.take_background_job() {
+                            self.jobs.push(job);
+                        }
+                        for operation in copy_dialog.completed_operations() {
+                            self.journal.record(operation);
+                        }
+                        self.close_dialog(app)
+                    }
+                }
+                Dialog::Extract(extract_dialog) => {
+                    extract_dialog.tick();
+                    if extract_dialog.should_quit() {
+                        if let Some(job) = extract_dialog.take_background_job() {
+                            self.jobs.push(job);
+                        }
+                        for operation in extract_dialog.completed_operations() {
+                            self.journal.record(operation);
+                        }
+                        self.left_panel.refresh();
+                        self.right_panel.refresh();
+                        self.close_dialog(app)
+                    }
+                }
+                Dialog::Pack(pack_dialog) => {
+                    if let Some(packing_dialog) = pack_dialog.take_transfer() {
+                        self.dialog = Some(Dialog::Packing(packing_dialog));
+                    } else if pack_dialog.should_quit() {
+                        self.close_dialog(app)
+                    }
+                }
+                Dialog::Packing(packing_dialog) => {
+                    packing_dialog.tick();
+                    if packing_dialog.should_quit() {
+                        if let Some(job) = packing_dialog.take_background_job() {
+                            self.jobs.push(job);
+                        }
+                        for operation in packing_dialog.completed_operations() {
+                            self.journal.record(operation);
+                        }
+                        self.active_panel_mut().clear_marks();
+                        self.left_panel.refresh();
+                        self.right_panel.refresh();
                         self.close_dialog(app)
                     }
                 }
                 Dialog::Move(move_dialog) => {
                     move_dialog.tick();
                     if move_dialog.should_quit() {
+                        if let Some(job) = move_dialog.take_background_job() {
+                            self.jobs.push(job);
+                        }
+                        for operation in move_dialog.completed_operations() {
+                            self.journal.record(operation);
+                        }
                         self.close_dialog(app)
                     }
                 }
                 Dialog::MkDir(mk_dialog) => {
                     if mk_dialog.should_hide() {
+                        if let Some(operation) = mk_dialog.completed_operation() {
+                            self.journal.record(operation);
+                        }
+                        let selected_name = mk_dialog.selected_name();
+                        match mk_dialog.dir_to_enter() {
+                            Some(dir) => match self.active_panel {
+                                ActivePanel::Left => self.left_panel.open_dir(
+                                    &dir,
+                                    self.config.left_table_config(),
+                                    &self.config,
+                                ),
+                                ActivePanel::Right => self.right_panel.open_dir(
+                                    &dir,
+                                    self.config.right_table_config(),
+                                    &self.config,
+                                ),
+                            },
+                            None => {
+                                let panel = self.active_panel_mut();
+                                panel.refresh();
+                                if let Some(name) = selected_name {
+                                    panel.select_file(&name);
+                                }
+                            }
+                        }
+                        self.close_dialog(app)
+                    }
+                }
+                Dialog::NewFile(new_file_dialog) => {
+                    if new_file_dialog.should_quit() {
+                        if let Some(operation) = new_file_dialog.completed_operation() {
+                            self.journal.record(operation);
+                        }
+                        if let Some(file) = new_file_dialog.file_to_edit() {
+                            app.edit_file(file);
+                        }
+                        let created_name = new_file_dialog.created_name();
+                        let panel = self.active_panel_mut();
+                        panel.refresh();
+                        if let Some(name) = created_name {
+                            panel.select_file(&name);
+                        }
+                        self.close_dialog(app)
+                    }
+                }
+                Dialog::Link(link_dialog) => {
+                    if link_dialog.should_quit() {
+                        if let Some(operation) = link_dialog.completed_operation() {
+                            self.journal.record(operation);
+                        }
+                        let created_name = link_dialog.created_name();
+                        let panel = match self.active_panel {
+                            ActivePanel::Left => &mut self.right_panel,
+                            ActivePanel::Right => &mut self.left_panel,
+                        };
+                        panel.refresh();
+                        if let Some(name) = created_name {
+                            panel.select_file(&name);
+                        }
                         self.close_dialog(app)
                     }
                 }
                 Dialog::RmDir(rm_dialog) => {
+                    rm_dialog.tick();
                     if rm_dialog.should_quit() {
+                        if let Some(job) = rm_dialog.take_background_job() {
+                            self.jobs.push(job);
+                        }
+                        if let Some(operation) = rm_dialog.completed_operation() {
+                            self.journal.record(operation);
+                        }
+                        self.left_panel.refresh();
+                        self.right_panel.refresh();
+                        self.close_dialog(app)
+                    }
+                }
+                Dialog::Rename(rename_dialog) => {
+                    if rename_dialog.should_quit() {
+                        if let Some(operation) = rename_dialog.completed_operation() {
+                            self.journal.record(operation);
+                        }
+                        let renamed_to = rename_dialog.renamed_to().map(OsString::from);
+                        let panel = self.active_panel_mut();
+                        panel.refresh();
+                        if let Some(name) = renamed_to {
+                            panel.select_file(&name);
+                        }
                         self.close_dialog(app)
                     }
                 }
+                Dialog::Attributes(attributes_dialog) => {
+                    if attributes_dialog.should_quit() {
+                        self.active_panel_mut().clear_marks();
+                        self.active_panel_mut().refresh();
+                        self.close_dialog(app)
+                    }
+                }
+                Dialog::Touch(touch_dialog) => {
+                    if touch_dialog.should_quit() {
+                        self.active_panel_mut().clear_marks();
+                        self.active_panel_mut().refresh();
+                        self.close_dialog(app)
+                    }
+                }
+                Dialog::MultiRename(multi_rename_dialog) => {
+                    if multi_rename_dialog.should_quit() {
+                        if let Some(operation) = multi_rename_dialog.completed_operation() {
+                            self.journal.record(operation);
+                        }
+                        self.active_panel_mut().clear_marks();
+                        self.active_panel_mut().refresh();
+                        self.close_dialog(app)
+                    }
+                }
+                Dialog::Jobs(_) | Dialog::Trash(_) | Dialog::History(_) | Dialog::Error(_) => {}
                 Dialog::Menu(dialog) => {
                     if dialog.should_quit() {
                         if dialog.request_config_change() {
@@ -357,14 +784,14 @@ impl UserInterface {
                                         .update_config(self.config.left_table_config());
                                 }
                                 // Panel options
-                                1 => {
+                                2 => {
                                     dialog
                                         .change_configuration(&mut self.config, self.active_panel);
                                     self.left_panel.change_config(&self.config);
                                     self.right_panel.change_config(&self.config);
                                 }
                                 // Right panel menu
-                                2 => {
+                                3 => {
                                     dialog
                                         .change_configuration(&mut self.config, self.active_panel);
                                     self.right_panel
@@ -382,6 +809,8 @@ impl UserInterface {
 
     /// Collects the configuration values from widgets that may changed during runtime,
     /// and updates the current configuration with those changes.
+    /// If the sort order is remembered per directory, the panel's own sort order is left intact,
+    /// because the panels are sorted by the order of their current directories.
     pub fn update_config(&mut self) {
         let left_path = PathBuf::from(self.left_panel.pwd());
         let left_sort_predicate = self.left_panel.sort_predicate();
@@ -391,13 +820,18 @@ impl UserInterface {
         let right_sort_dir = self.right_panel.sort_direction();
 
         self.config.left_table_config_mut().set_path(left_path);
+        self.config.right_table_config_mut().set_path(right_path);
+
+        if self.config.remember_sort_per_dir() {
+            return;
+        }
+
         self.config
             .left_table_config_mut()
             .set_predicate(left_sort_predicate.into());
         self.config
             .left_table_config_mut()
             .set_sort_direction(left_sort_dir.into());
-        self.config.right_table_config_mut().set_path(right_path);
         self.config
             .right_table_config_mut()
             .set_predicate(right_sort_predicate.into());
@@ -406,6 +840,39 @@ impl UserInterface {
             .set_sort_direction(right_sort_dir.into());
     }
 
+    /// Remembers the sort order of the active panel for its current working directory,
+    /// if the sort order is remembered per directory.
+    fn remember_dir_sort_order(&mut self) {
+        if !self.config.remember_sort_per_dir() {
+            return;
+        }
+
+        let panel = match &self.active_panel {
+            ActivePanel::Left => &self.left_panel,
+            ActivePanel::Right => &self.right_panel,
+        };
+        let path = PathBuf::from(panel.pwd());
+        let predicate = panel.sort_predicate().into();
+        let direction = panel.sort_direction().into();
+        self.config
+            .dir_sort_orders_mut()
+            .insert(path, predicate, direction);
+    }
+
+    fn active_panel(&self) -> &TableView {
+        match &self.active_panel {
+            ActivePanel::Left => &self.left_panel,
+            ActivePanel::Right => &self.right_panel,
+        }
+    }
+
+    fn inactive_panel(&self) -> &TableView {
+        match &self.active_panel {
+            ActivePanel::Left => &self.right_panel,
+            ActivePanel::Right => &self.left_panel,
+        }
+    }
+
     fn active_panel_mut(&mut self) -> &mut TableView {
         match &self.active_panel {
             ActivePanel::Left => &mut self.left_panel,
@@ -419,23 +886,26 @@ impl UserInterface {
 
     fn create_move_dialog(&self) -> Result<TransferDialog<MoveStrategy>, ShowDialogError> {
         match &self.active_panel {
-            ActivePanel::Left => return inner(&self.left_panel, &self.right_panel),
-            ActivePanel::Right => return inner(&self.right_panel, &self.left_panel),
+            ActivePanel::Left => return inner(&self.left_panel, &self.right_panel, &self.config),
+            ActivePanel::Right => return inner(&self.right_panel, &self.left_panel, &self.config),
         }
 
         fn inner(
             source: &TableView,
             target: &TableView,
+            config: &Configuration,
         ) -> Result<TransferDialog<MoveStrategy>, ShowDialogError> {
             if let Some(selected_file) = source.get_selected_file() {
+                let vfs = source.vfs();
                 let source = selected_file.as_path();
                 let destination = target.pwd();
                 Ok(TransferDialog::new(
                     PathBuf::from(source),
                     PathBuf::from(destination),
-                    MoveStrategy,
+                    MoveStrategy::new(vfs),
                     String::from("Move file(s)"),
-                ))
+                )
+                .with_conflict_resolution(config.conflict_resolution()))
             } else {
                 Err(ShowDialogError::NoSelectedSource)
             }
@@ -444,45 +914,198 @@ impl UserInterface {
 
     fn create_copy_dialog(&self) -> Result<TransferDialog<CopyStrategy>, ShowDialogError> {
         match &self.active_panel {
-            ActivePanel::Left => return inner(&self.left_panel, &self.right_panel),
-            ActivePanel::Right => return inner(&self.right_panel, &self.left_panel),
+            ActivePanel::Left => return inner(&self.left_panel, &self.right_panel, &self.config),
+            ActivePanel::Right => return inner(&self.right_panel, &self.left_panel, &self.config),
         }
 
         fn inner(
             source: &TableView,
             target: &TableView,
+            config: &Configuration,
         ) -> Result<TransferDialog<CopyStrategy>, ShowDialogError> {
             if let Some(selected_file) = source.get_selected_file() {
+                let vfs = source.vfs();
                 let source = selected_file.as_path();
                 let destination = target.pwd();
                 Ok(TransferDialog::new(
                     PathBuf::from(source),
                     PathBuf::from(destination),
-                    CopyStrategy,
+                    CopyStrategy::new(vfs),
                     String::from("Copy file(s)"),
-                ))
+                )
+                .with_conflict_resolution(config.conflict_resolution()))
             } else {
                 Err(ShowDialogError::NoSelectedSource)
             }
         }
     }
 
-    fn create_rm_dialog(&self) -> Result<RmDirDialog, ShowDialogError> {
+    fn create_link_dialog(&self, kind: LinkKind) -> Result<LinkDialog, ShowDialogError> {
         match &self.active_panel {
-            ActivePanel::Left => return inner(&self.left_panel),
-            ActivePanel::Right => return inner(&self.right_panel),
+            ActivePanel::Left => return inner(&self.left_panel, &self.right_panel, kind),
+            ActivePanel::Right => return inner(&self.right_panel, &self.left_panel, kind),
         }
 
-        fn inner(source: &TableView) -> Result<RmDirDialog, ShowDialogError> {
-            if let Some(selected_file) = source.get_selected_file() {
-                let source = selected_file.as_path();
-                Ok(RmDirDialog::new(vec![PathBuf::from(source)]))
-            } else {
+        fn inner(
+            source: &TableView,
+            target: &TableView,
+            kind: LinkKind,
+        ) -> Result<LinkDialog, ShowDialogError> {
+            match source.get_selected_file() {
+                // the ".." entry has no file name
+                Some(selected_file) if selected_file.file_name().is_some() => {
+                    Ok(LinkDialog::new(selected_file, target.pwd(), kind))
+                }
+                _ => Err(ShowDialogError::NoSelectedSource),
+            }
+        }
+    }
+
+    fn create_extract_dialog(&self) -> Result<TransferDialog<ExtractStrategy>, ShowDialogError> {
+        let source = self.active_panel();
+        let archive = source.archive().ok_or(ShowDialogError::NoSelectedSource)?;
+        match source.get_selected_file() {
+            // the ".." entry has no file name
+            Some(selected_file) if selected_file.file_name().is_some() => Ok(TransferDialog::new(
+                selected_file,
+                self.inactive_panel().pwd().to_path_buf(),
+                ExtractStrategy::new(Arc::clone(archive)),
+                String::from("Copy from archive"),
+            )
+            .with_conflict_resolution(self.config.conflict_resolution())),
+            _ => Err(ShowDialogError::NoSelectedSource),
+        }
+    }
+
+    fn create_pack_dialog(&self) -> Result<PackDialog, ShowDialogError> {
+        let source = self.active_panel();
+        let files: Vec<PathBuf> = source
+            .get_selected_files()
+            .into_iter()
+            // the ".." entry has no file name
+            .filter(|file| file.file_name().is_some())
+            .collect();
+        if files.is_empty() {
+            return Err(ShowDialogError::NoSelectedSource);
+        }
+        Ok(PackDialog::new(
+            files,
+            source.pwd(),
+            self.inactive_panel().pwd(),
+            self.config.conflict_resolution(),
+        ))
+    }
+
+    /// Opens the selected archive for unpacking it into the other panel's directory.
+    /// Returns None if no file is selected.
+    fn create_unpack_dialog(&self) -> io::Result<Option<TransferDialog<ExtractStrategy>>> {
+        let selected_file = match self.active_panel().get_selected_file() {
+            Some(selected_file) if selected_file.file_name().is_some() => selected_file,
+            _ => return Ok(None),
+        };
+        if !selected_file.is_file() || ArchiveFormat::from_path(&selected_file).is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not a supported archive", selected_file.display()),
+            ));
+        }
+        let archive = ArchiveIndex::open(&selected_file).map_err(|error| {
+            io::Error::new(
+                error.kind(),
+                format!("{}: {}", selected_file.display(), error),
+            )
+        })?;
+        Ok(Some(
+            TransferDialog::new(
+                selected_file,
+                self.inactive_panel().pwd().to_path_buf(),
+                ExtractStrategy::unpack(Arc::new(archive)),
+                String::from("Unpack archive"),
+            )
+            .with_conflict_resolution(self.config.conflict_resolution()),
+        ))
+    }
+
+    /// Opens the selected file in the pager, a file which is not on the local file system,
+    /// e.g. an archive member, is copied to a temporary file for it.
+    fn view_selected_file(&mut self, app: &mut Application) {
+        let panel = self.active_panel();
+        let selected_file = match panel.get_selected_file() {
+            Some(selected_file) if selected_file.file_name().is_some() => selected_file,
+            _ => return,
+        };
+        let vfs = panel.vfs();
+        if !vfs.stat(&selected_file).is_ok_and(|stat| stat.is_file()) {
+            return;
+        }
+        let file = match vfs.local_path(&selected_file) {
+            Some(file) => Ok(file),
+            None => copy_to_temporary(vfs.as_ref(), &selected_file),
+        };
+        match file {
+            Ok(file) => app.view_file(&file),
+            Err(error) => self.show_error(format!("{}: {}", selected_file.display(), error), app),
+        }
+    }
+
+    fn create_rm_dialog(&self, permanent: bool) -> Result<RmDirDialog, ShowDialogError> {
+        match &self.active_panel {
+            ActivePanel::Left => return inner(&self.left_panel, permanent),
+            ActivePanel::Right => return inner(&self.right_panel, permanent),
+        }
+
+        fn inner(source: &TableView, permanent: bool) -> Result<RmDirDialog, ShowDialogError> {
+            let selected_files = source.get_selected_files();
+            if selected_files.is_empty() {
                 Err(ShowDialogError::NoSelectedSource)
+            } else {
+                Ok(RmDirDialog::new(selected_files, permanent, source.vfs()))
             }
         }
     }
 
+    fn create_attributes_dialog(&self) -> Result<AttributesDialog, ShowDialogError> {
+        let selected_files = match &self.active_panel {
+            ActivePanel::Left => self.left_panel.get_selected_files(),
+            ActivePanel::Right => self.right_panel.get_selected_files(),
+        };
+        if selected_files.is_empty() {
+            Err(ShowDialogError::NoSelectedSource)
+        } else {
+            Ok(AttributesDialog::new(selected_files))
+        }
+    }
+
+    fn create_touch_dialog(&self) -> Result<TouchDialog, ShowDialogError> {
+        let panel = match &self.active_panel {
+            ActivePanel::Left => &self.left_panel,
+            ActivePanel::Right => &self.right_panel,
+        };
+        let selected_files = panel.get_selected_files();
+        if selected_files.is_empty() {
+            Err(ShowDialogError::NoSelectedSource)
+        } else {
+            Ok(TouchDialog::new(selected_files, panel.pwd()))
+        }
+    }
+
+    fn create_multi_rename_dialog(&self) -> Result<MultiRenameDialog, ShowDialogError> {
+        let panel = match &self.active_panel {
+            ActivePanel::Left => &self.left_panel,
+            ActivePanel::Right => &self.right_panel,
+        };
+        let selected_files: Vec<PathBuf> = panel
+            .get_selected_files()
+            .into_iter()
+            .filter(|file| file.file_name().is_some())
+            .collect();
+        if selected_files.is_empty() {
+            Err(ShowDialogError::NoSelectedSource)
+        } else {
+            Ok(MultiRenameDialog::new(panel.pwd(), selected_files))
+        }
+    }
+
     fn create_menu_dialog(&mut self) {
         let selectem_item_idx = self.top_menu.selected_item();
         match selectem_item_idx {
@@ -493,11 +1116,73 @@ impl UserInterface {
                     predicate, direction,
                 ))));
             }
-            1 => {
+            1 => match self.top_menu.selected_submenu_item() {
+                // The archives are browsed read-only, a link is created in the other panel
+                0..=2 | 6 | 7 if self.active_panel().archive().is_some() => {
+                    self.dialog = Some(Dialog::Error(vec![String::from(READ_ONLY_ARCHIVE)]));
+                    self.focused_widget = Widgets::Dialog;
+                }
+                6 | 7 if self.inactive_panel().archive().is_some() => {
+                    self.dialog = Some(Dialog::Error(vec![String::from(READ_ONLY_ARCHIVE)]));
+                    self.focused_widget = Widgets::Dialog;
+                }
+                // Attributes
+                0 => {
+                    if let Ok(attributes_dialog) = self.create_attributes_dialog() {
+                        self.dialog = Some(Dialog::Attributes(attributes_dialog));
+                        self.focused_widget = Widgets::Dialog;
+                    }
+                }
+                // Timestamps
+                1 => {
+                    if let Ok(touch_dialog) = self.create_touch_dialog() {
+                        self.dialog = Some(Dialog::Touch(touch_dialog));
+                        self.focused_widget = Widgets::Dialog;
+                    }
+                }
+                // Multi-rename
+                2 => {
+                    if let Ok(multi_rename_dialog) = self.create_multi_rename_dialog() {
+                        self.dialog = Some(Dialog::MultiRename(multi_rename_dialog));
+                        self.focused_widget = Widgets::Dialog;
+                    }
+                }
+                // Jobs
+                3 => {
+                    self.dialog = Some(Dialog::Jobs(JobsDialog::new()));
+                    self.focused_widget = Widgets::Dialog;
+                }
+                // Trash
+                4 => {
+                    self.dialog = Some(Dialog::Trash(TrashDialog::new()));
+                    self.focused_widget = Widgets::Dialog;
+                }
+                // History
+                5 => {
+                    self.dialog = Some(Dialog::History(HistoryDialog::new()));
+                    self.focused_widget = Widgets::Dialog;
+                }
+                // Symbolic link
+                6 => {
+                    if let Ok(link_dialog) = self.create_link_dialog(LinkKind::Symbolic) {
+                        self.dialog = Some(Dialog::Link(link_dialog));
+                        self.focused_widget = Widgets::Dialog;
+                    }
+                }
+                // Hard link
+                7 => {
+                    if let Ok(link_dialog) = self.create_link_dialog(LinkKind::Hard) {
+                        self.dialog = Some(Dialog::Link(link_dialog));
+                        self.focused_widget = Widgets::Dialog;
+                    }
+                }
+                _ => {}
+            },
+            2 => {
                 let config = &self.config;
                 self.dialog = Some(Dialog::Menu(Box::new(PanelOpionsDialog::new(config))));
             }
-            2 => {
+            3 => {
                 let predicate = self.right_panel.sort_predicate();
                 let direction = self.right_panel.sort_direction();
                 self.dialog = Some(Dialog::Menu(Box::new(SortingDialog::new(
@@ -509,6 +1194,29 @@ impl UserInterface {
         self.top_menu.deactivate();
     }
 
+    /// Shows an error message in a dialog, e.g. when an external program failed.
+    pub(crate) fn show_error(&mut self, message: String, app: &mut Application) {
+        self.dialog = Some(Dialog::Error(vec![message]));
+        self.focused_widget = Widgets::Dialog;
+        app.set_input_mode(InputMode::Editing);
+    }
+
+    /// Undoes the latest file operation, the error is shown in the `HistoryDialog`
+    /// if it could not be undone.
+    fn undo(&mut self, app: &mut Application) {
+        match self.journal.undo() {
+            Ok(_) => {
+                self.left_panel.refresh();
+                self.right_panel.refresh();
+            }
+            Err(error) => {
+                self.dialog = Some(Dialog::History(HistoryDialog::with_error(error)));
+                self.focused_widget = Widgets::Dialog;
+                app.set_input_mode(InputMode::Editing);
+            }
+        }
+    }
+
     fn close_dialog(&mut self, app: &mut Application) {
         app.set_input_mode(InputMode::Normal);
         self.dialog = None;
@@ -5,9 +5,6 @@ use tui::{backend::TermionBackend, layout::Rect, Frame};
 mod panel;
 mod text_file_viewer;
 
-pub use self::panel::*;
-pub use self::text_file_viewer::*;
-
 /// A list of available widgets to use in a `Panel`.
 #[allow(unused)]
 pub enum Widgets {